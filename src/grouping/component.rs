@@ -0,0 +1,199 @@
+use std::fmt;
+
+use serde_derive::Serialize;
+
+/// A value of a grouping component.
+///
+/// This is either a plain string that is hashed or a nested component.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum GroupingComponentValue {
+    /// A leaf value that contributes to the hash.
+    Value(String),
+    /// A nested component.
+    Component(GroupingComponent),
+}
+
+/// A node in the grouping component tree.
+///
+/// Every component describes one aspect of an event (for instance the type of an exception or a
+/// single stack frame) and records whether it contributed to the final hash.  Components that do
+/// not contribute carry a hint explaining why they were ignored.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupingComponent {
+    /// The identifier of this component (e.g. `exception` or `function`).
+    pub id: &'static str,
+
+    /// Indicates whether this component contributes to the hash.
+    pub contributes: bool,
+
+    /// An optional human readable hint about the component.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+
+    /// The values or child components.
+    pub values: Vec<GroupingComponentValue>,
+}
+
+impl GroupingComponent {
+    /// Creates a new component from leaf values.
+    ///
+    /// The component contributes if there is at least one value.
+    pub fn new(id: &'static str, values: Vec<String>) -> Self {
+        GroupingComponent {
+            id,
+            contributes: !values.is_empty(),
+            hint: None,
+            values: values
+                .into_iter()
+                .map(GroupingComponentValue::Value)
+                .collect(),
+        }
+    }
+
+    /// Creates a new component from child components.
+    ///
+    /// The component contributes if any of its children contribute.
+    pub fn from_children(id: &'static str, children: Vec<GroupingComponent>) -> Self {
+        GroupingComponent {
+            id,
+            contributes: children.iter().any(|child| child.contributes),
+            hint: None,
+            values: children
+                .into_iter()
+                .map(GroupingComponentValue::Component)
+                .collect(),
+        }
+    }
+
+    /// Creates an empty component that does not contribute.
+    pub fn empty(id: &'static str) -> Self {
+        GroupingComponent::new(id, vec![])
+    }
+
+    /// Sets a hint on this component.
+    pub fn with_hint<S: Into<String>>(mut self, hint: S) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Marks this component as not contributing with the given hint.
+    ///
+    /// Components that do not contribute anyway keep their original hint.
+    pub fn ignore<S: Into<String>>(&mut self, hint: S) {
+        if self.contributes {
+            self.contributes = false;
+            self.hint = Some(hint.into());
+        }
+    }
+
+    /// Iterates over direct child components.
+    pub fn children(&self) -> impl Iterator<Item = &GroupingComponent> {
+        self.values.iter().filter_map(|value| match value {
+            GroupingComponentValue::Component(component) => Some(component),
+            GroupingComponentValue::Value(_) => None,
+        })
+    }
+
+    /// Finds the first contributing component with the given id in this tree.
+    pub fn find(&self, id: &str) -> Option<&GroupingComponent> {
+        if !self.contributes {
+            return None;
+        }
+
+        if self.id == id {
+            return Some(self);
+        }
+
+        self.children().filter_map(|child| child.find(id)).next()
+    }
+
+    /// Returns all values that contribute to the hash in order.
+    pub fn flatten_values(&self) -> Vec<&str> {
+        let mut rv = vec![];
+        self.collect_values(&mut rv);
+        rv
+    }
+
+    fn collect_values<'a>(&'a self, rv: &mut Vec<&'a str>) {
+        if !self.contributes {
+            return;
+        }
+
+        for value in &self.values {
+            match value {
+                GroupingComponentValue::Value(value) => rv.push(value),
+                GroupingComponentValue::Component(component) => component.collect_values(rv),
+            }
+        }
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.id, indent = depth * 2)?;
+        if !self.contributes {
+            write!(f, " (ignored)")?;
+        }
+        if let Some(ref hint) = self.hint {
+            write!(f, " -- {}", hint)?;
+        }
+        writeln!(f)?;
+
+        for value in &self.values {
+            match value {
+                GroupingComponentValue::Value(value) => {
+                    writeln!(f, "{:indent$}{:?}", "", value, indent = (depth + 1) * 2)?
+                }
+                GroupingComponentValue::Component(component) => component.fmt_tree(f, depth + 1)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for GroupingComponent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
+
+#[test]
+fn test_flatten_values_skips_ignored() {
+    let mut value = GroupingComponent::new("value", vec!["foo".to_string()]);
+    value.ignore("ignored for testing");
+
+    let component = GroupingComponent::from_children(
+        "exception",
+        vec![
+            GroupingComponent::new("type", vec!["ValueError".to_string()]),
+            value,
+        ],
+    );
+
+    assert!(component.contributes);
+    assert_eq!(component.flatten_values(), vec!["ValueError"]);
+}
+
+#[test]
+fn test_display_tree() {
+    let mut value = GroupingComponent::new("value", vec!["foo".to_string()]);
+    value.ignore("ignored for testing");
+
+    let component = GroupingComponent::from_children(
+        "exception",
+        vec![
+            GroupingComponent::new("type", vec!["ValueError".to_string()]),
+            value,
+        ],
+    );
+
+    assert_eq_str!(
+        component.to_string(),
+        r#"exception
+  type
+    "ValueError"
+  value (ignored) -- ignored for testing
+    "foo"
+"#
+    );
+}
@@ -0,0 +1,387 @@
+//! Computes grouping hashes for events.
+//!
+//! Grouping first builds a tree of `GroupingComponent`s out of the event payload.  Every
+//! component records whether it contributed to the hash and, if not, why it was ignored.  The hash
+//! is then computed from all contributing leaf values in order.  If the event carries a custom
+//! fingerprint, the fingerprint is hashed instead and the `{{ default }}` variable expands to the
+//! values of the default component tree.
+//!
+//! ### Example
+//!
+//! ```
+//! use general::grouping;
+//! use general::protocol::{Event, LogEntry};
+//! use general::types::Annotated;
+//!
+//! let event = Event {
+//!     logentry: Annotated::new(LogEntry {
+//!         message: Annotated::new("Hello %s".to_string()),
+//!         ..Default::default()
+//!     }),
+//!     ..Default::default()
+//! };
+//!
+//! let info = grouping::get_grouping_info(&event);
+//! assert_eq!(info.component.flatten_values(), vec!["Hello %s"]);
+//! assert!(info.hash.is_some());
+//! ```
+
+use serde_derive::Serialize;
+
+use crate::protocol::{Event, Exception, Fingerprint, FingerprintVariable, Frame};
+use crate::types::Annotated;
+
+mod component;
+mod strategies;
+
+pub use self::component::{GroupingComponent, GroupingComponentValue};
+
+/// The result of grouping an event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupingInfo {
+    /// The default component tree computed from the event payload.
+    pub component: GroupingComponent,
+
+    /// The expanded values of a custom fingerprint if the event has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<Vec<String>>,
+
+    /// The final grouping hash or `None` if nothing contributed.
+    pub hash: Option<String>,
+}
+
+/// Checks if the fingerprint only consists of the `{{ default }}` variable.
+pub fn is_default_fingerprint(fingerprint: &Fingerprint) -> bool {
+    fingerprint.len() == 1
+        && FingerprintVariable::parse(&fingerprint[0]) == Some(FingerprintVariable::Default)
+}
+
+fn get_top_exception(event: &Event) -> Option<&Exception> {
+    event
+        .exceptions
+        .value()
+        .and_then(|exceptions| exceptions.values.value())
+        .and_then(|exceptions| exceptions.iter().rev().filter_map(Annotated::value).next())
+}
+
+fn get_top_frame(event: &Event) -> Option<&Frame> {
+    get_top_exception(event)
+        .and_then(|exception| exception.stacktrace.value())
+        .or_else(|| event.stacktrace.value())
+        .and_then(|stacktrace| stacktrace.frames.value())
+        .and_then(|frames| frames.iter().rev().filter_map(Annotated::value).next())
+}
+
+fn expand_variable(
+    variable: &FingerprintVariable,
+    event: &Event,
+    component: &GroupingComponent,
+) -> Vec<String> {
+    let value = match variable {
+        FingerprintVariable::Default => {
+            return component
+                .flatten_values()
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+        }
+        FingerprintVariable::Transaction => event
+            .transaction
+            .value()
+            .cloned()
+            .unwrap_or_else(|| "<no-transaction>".to_string()),
+        FingerprintVariable::Type => get_top_exception(event)
+            .and_then(|exception| exception.ty.value())
+            .cloned()
+            .unwrap_or_else(|| "<no-type>".to_string()),
+        FingerprintVariable::Function => get_top_frame(event)
+            .and_then(|frame| frame.function.value())
+            .cloned()
+            .unwrap_or_else(|| "<no-function>".to_string()),
+        FingerprintVariable::Module => get_top_frame(event)
+            .and_then(|frame| frame.module.value())
+            .cloned()
+            .unwrap_or_else(|| "<no-module>".to_string()),
+        FingerprintVariable::Package => get_top_frame(event)
+            .and_then(|frame| frame.package.value())
+            .cloned()
+            .unwrap_or_else(|| "<no-package>".to_string()),
+        FingerprintVariable::Level => event
+            .level
+            .value()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "<no-level>".to_string()),
+        FingerprintVariable::Tag(key) => event
+            .tags
+            .value()
+            .and_then(|tags| {
+                tags.iter()
+                    .filter_map(Annotated::value)
+                    .find(|(k, _)| k.value().map(String::as_str) == Some(key.as_str()))
+                    .and_then(|(_, v)| v.value().cloned())
+            }).unwrap_or_else(|| format!("<no-value-for-tag-{}>", key)),
+    };
+
+    vec![value]
+}
+
+fn expand_fingerprint_with(
+    fingerprint: &Fingerprint,
+    event: &Event,
+    component: &GroupingComponent,
+) -> Vec<String> {
+    let mut rv = vec![];
+    for (value, variable) in fingerprint.iter().zip(fingerprint.variables()) {
+        match variable {
+            Some(variable) => rv.extend(expand_variable(&variable, event, component)),
+            None => rv.push(value.clone()),
+        }
+    }
+    rv
+}
+
+/// Expands a fingerprint against an event into concrete values.
+///
+/// Template variables are replaced by the corresponding values of the event.  If a value is
+/// missing, a placeholder such as `<no-transaction>` is used instead.  The `{{ default }}`
+/// variable expands to all contributing values of the default component tree.  Plain values and
+/// unknown variables are used verbatim.
+pub fn expand_fingerprint(fingerprint: &Fingerprint, event: &Event) -> Vec<String> {
+    let component = get_grouping_component(event);
+    expand_fingerprint_with(fingerprint, event, &component)
+}
+
+/// Computes the grouping hash of the given values.
+///
+/// Returns `None` if there are no values.
+pub fn hash_values<I, S>(values: I) -> Option<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut context = md5::Context::new();
+    let mut empty = true;
+    for value in values {
+        context.consume(value.as_ref().as_bytes());
+        empty = false;
+    }
+
+    if empty {
+        None
+    } else {
+        Some(format!("{:x}", context.compute()))
+    }
+}
+
+/// Computes the default grouping component tree of an event.
+///
+/// This ignores the fingerprint of the event.
+pub fn get_grouping_component(event: &Event) -> GroupingComponent {
+    strategies::default_component(event)
+}
+
+/// Computes the grouping component tree and the final hash of an event.
+pub fn get_grouping_info(event: &Event) -> GroupingInfo {
+    let component = get_grouping_component(event);
+
+    let fingerprint = event
+        .fingerprint
+        .value()
+        .filter(|fingerprint| !fingerprint.is_empty() && !is_default_fingerprint(fingerprint))
+        .map(|fingerprint| expand_fingerprint_with(fingerprint, event, &component));
+
+    let hash = match fingerprint {
+        Some(ref values) => hash_values(values),
+        None => hash_values(component.flatten_values()),
+    };
+
+    GroupingInfo {
+        component,
+        fingerprint,
+        hash,
+    }
+}
+
+#[cfg(test)]
+use crate::protocol::{LogEntry, Stacktrace, Values};
+
+#[cfg(test)]
+fn make_frame(module: &str, function: &str, in_app: bool) -> Annotated<Frame> {
+    Annotated::new(Frame {
+        module: Annotated::new(module.to_string()),
+        function: Annotated::new(function.to_string()),
+        in_app: Annotated::new(in_app),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+fn make_exception_event() -> Event {
+    Event {
+        exceptions: Annotated::new(Values::new(vec![Annotated::new(Exception {
+            ty: Annotated::new("ValueError".to_string()),
+            value: Annotated::new("invalid literal 42".to_string().into()),
+            stacktrace: Annotated::new(Stacktrace {
+                frames: Annotated::new(vec![
+                    make_frame("threading", "run", false),
+                    make_frame("app.views", "index", true),
+                    make_frame("app.models", "save", true),
+                ]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })])),
+        logentry: Annotated::new(LogEntry {
+            formatted: Annotated::new("something failed".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_hash_values() {
+    assert_eq!(hash_values(Vec::<String>::new()), None);
+    assert_eq!(
+        hash_values(vec!["foo", "bar"]),
+        Some("3858f62230ac3c915f300c664312c63f".to_string())
+    );
+}
+
+#[test]
+fn test_exception_in_app_frames() {
+    let event = make_exception_event();
+    let info = get_grouping_info(&event);
+
+    assert_eq!(
+        info.component.flatten_values(),
+        vec!["ValueError", "app.views", "index", "app.models", "save"]
+    );
+    assert_eq!(
+        info.hash,
+        hash_values(vec!["ValueError", "app.views", "index", "app.models", "save"])
+    );
+
+    let message = info.component.children().nth(3).unwrap();
+    assert_eq!(message.id, "message");
+    assert!(!message.contributes);
+    assert_eq!(message.hint.as_ref().unwrap(), "exception takes precedence");
+}
+
+#[test]
+fn test_exception_without_stacktrace_uses_value() {
+    let event = Event {
+        exceptions: Annotated::new(Values::new(vec![Annotated::new(Exception {
+            ty: Annotated::new("ValueError".to_string()),
+            value: Annotated::new("invalid literal".to_string().into()),
+            ..Default::default()
+        })])),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        get_grouping_component(&event).flatten_values(),
+        vec!["ValueError", "invalid literal"]
+    );
+}
+
+#[test]
+fn test_fingerprint_default_expansion() {
+    let mut event = make_exception_event();
+    event.fingerprint = Annotated::new(vec!["{{ default }}".to_string()].into());
+    let default_hash = get_grouping_info(&event).hash;
+    assert_eq!(get_grouping_info(&make_exception_event()).hash, default_hash);
+
+    event.fingerprint =
+        Annotated::new(vec!["{{ default }}".to_string(), "custom".to_string()].into());
+    let info = get_grouping_info(&event);
+    assert_eq_dbg!(
+        info.fingerprint,
+        Some(vec![
+            "ValueError".to_string(),
+            "app.views".to_string(),
+            "index".to_string(),
+            "app.models".to_string(),
+            "save".to_string(),
+            "custom".to_string(),
+        ])
+    );
+    assert_ne!(info.hash, default_hash);
+}
+
+#[test]
+fn test_expand_fingerprint_variables() {
+    use crate::protocol::{Level, Tags};
+
+    let mut event = make_exception_event();
+    event.transaction = Annotated::new("/users/".to_string());
+    event.level = Annotated::new(Level::Warning);
+    event.tags = Annotated::new(Tags(vec![Annotated::new((
+        Annotated::new("server".to_string()),
+        Annotated::new("web1".to_string()),
+    ))]));
+
+    let fingerprint = vec![
+        "{{ transaction }}",
+        "{{ type }}",
+        "{{ function }}",
+        "{{ module }}",
+        "{{ package }}",
+        "{{ level }}",
+        "{{ tags.server }}",
+        "{{ tags.missing }}",
+        "{{ unknown }}",
+        "plain",
+    ].into_iter()
+    .map(str::to_string)
+    .collect::<Vec<_>>()
+    .into();
+
+    assert_eq_dbg!(
+        expand_fingerprint(&fingerprint, &event),
+        vec![
+            "/users/",
+            "ValueError",
+            "save",
+            "app.models",
+            "<no-package>",
+            "warning",
+            "web1",
+            "<no-value-for-tag-missing>",
+            "{{ unknown }}",
+            "plain",
+        ]
+    );
+}
+
+#[test]
+fn test_csp_grouping() {
+    use crate::types::{Object, Value};
+
+    let mut report = Object::new();
+    report.insert(
+        "effective_directive".to_string(),
+        Annotated::new(Value::String("script-src".to_string())),
+    );
+    report.insert(
+        "blocked_uri".to_string(),
+        Annotated::new(Value::String("https://evil.example.com/x.js".to_string())),
+    );
+
+    let event = Event {
+        csp: Annotated::new(Value::Object(report)),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        get_grouping_component(&event).flatten_values(),
+        vec!["script-src", "evil.example.com"]
+    );
+}
+
+#[test]
+fn test_nothing_contributes() {
+    let info = get_grouping_info(&Event::default());
+    assert!(!info.component.contributes);
+    assert_eq!(info.hash, None);
+}
@@ -0,0 +1,363 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use url::Url;
+
+use crate::grouping::GroupingComponent;
+use crate::protocol::{Event, Exception, Frame, LogEntry, Stacktrace};
+use crate::types::{Object, Value};
+
+/// Context lines longer than this are not used for grouping.
+const MAX_CONTEXT_LINE_LEN: usize = 120;
+
+lazy_static! {
+    static ref FILENAME_VERSION_RE: Regex = Regex::new(
+        r"(?ix)
+        ^(?:
+            v?(?:\d+\.)*\d+|  # version numbers, v1, 1.0.0
+            [a-f0-9]{7,8}|    # short sha
+            [a-f0-9]{32}|     # md5
+            [a-f0-9]{40}      # sha1
+        )$"
+    ).unwrap();
+    static ref JAVA_GENERATED_RE: Regex =
+        Regex::new(r"(GeneratedMethodAccessor|GeneratedConstructorAccessor)\d+").unwrap();
+    static ref JAVA_ENHANCER_RE: Regex =
+        Regex::new(r"\$\$(EnhancerByCGLIB|FastClassByCGLIB|EnhancerBySpringCGLIB)\$\$[a-fA-F0-9]+").unwrap();
+    static ref JAVA_LAMBDA_RE: Regex = Regex::new(r"\$\$Lambda\$\d+(?:/(?:0x)?[a-fA-F0-9]+)?").unwrap();
+    static ref RUBY_BLOCK_RE: Regex = Regex::new(r"^block (?:\(\d+ levels\) )?in ").unwrap();
+}
+
+fn remove_filename_outliers(filename: &str, platform: Option<&str>) -> String {
+    // on cocoa the directory is different for every build, so only the basename is stable.
+    let filename = match platform {
+        Some("cocoa") | Some("objc") => filename.rsplit('/').next().unwrap_or(filename),
+        _ => filename,
+    };
+
+    // query strings and fragments are commonly used for cache busting.
+    let filename = filename.split(&['?', '#'][..]).next().unwrap_or("");
+
+    // only entire directory names are versions, not directories that end in digits.
+    let mut segments = filename.split('/').peekable();
+    let mut rv = String::with_capacity(filename.len());
+    while let Some(segment) = segments.next() {
+        if segments.peek().is_none() {
+            rv.push_str(segment);
+        } else {
+            if FILENAME_VERSION_RE.is_match(segment) {
+                rv.push_str("<version>");
+            } else {
+                rv.push_str(segment);
+            }
+            rv.push('/');
+        }
+    }
+
+    rv
+}
+
+fn remove_module_outliers(module: &str) -> String {
+    let module = JAVA_GENERATED_RE.replace_all(module, "$1");
+    let module = JAVA_ENHANCER_RE.replace_all(&module, "$$$$$1$$$$<auto>");
+    JAVA_LAMBDA_RE
+        .replace_all(&module, "$$$$Lambda$$")
+        .into_owned()
+}
+
+fn remove_function_outliers(function: &str) -> Option<String> {
+    let function = function.trim();
+    match function {
+        "" | "?" | "<anonymous>" | "<unknown>" | "anonymous" => return None,
+        _ => {}
+    }
+
+    let function = JAVA_LAMBDA_RE.replace_all(function, "$$$$Lambda$$");
+    Some(RUBY_BLOCK_RE.replace(&function, "block in ").into_owned())
+}
+
+fn frame_component(frame: &Frame, platform: Option<&str>) -> GroupingComponent {
+    let mut values = vec![];
+
+    let module = frame.module.value().map(|module| remove_module_outliers(module));
+    let has_module = module.is_some();
+    values.push(match module {
+        Some(module) => GroupingComponent::new("module", vec![module]),
+        None => GroupingComponent::empty("module"),
+    });
+
+    let mut filename = match frame.filename.value().or_else(|| frame.abs_path.value()) {
+        Some(filename) => GroupingComponent::new(
+            "filename",
+            vec![remove_filename_outliers(filename, platform)],
+        ),
+        None => GroupingComponent::empty("filename"),
+    };
+    if has_module {
+        filename.ignore("module takes precedence");
+    }
+    let has_filename = filename.contributes;
+    values.push(filename);
+
+    let function = frame
+        .function
+        .value()
+        .and_then(|function| remove_function_outliers(function));
+    let has_function = function.is_some();
+    values.push(match function {
+        Some(function) => GroupingComponent::new("function", vec![function]),
+        None => GroupingComponent::empty("function"),
+    });
+
+    let mut context_line = match frame.current_line.value() {
+        Some(line) if line.len() <= MAX_CONTEXT_LINE_LEN => {
+            GroupingComponent::new("context-line", vec![line.trim().to_string()])
+        }
+        Some(_) => GroupingComponent::empty("context-line").with_hint("discarded because too long"),
+        None => GroupingComponent::empty("context-line"),
+    };
+    if has_function {
+        context_line.ignore("function takes precedence");
+    }
+    let has_context_line = context_line.contributes;
+    values.push(context_line);
+
+    let mut lineno = match frame.line.value() {
+        Some(line) if has_filename || has_module => {
+            GroupingComponent::new("lineno", vec![line.to_string()])
+        }
+        _ => GroupingComponent::empty("lineno"),
+    };
+    if has_function || has_context_line {
+        lineno.ignore("function or context line takes precedence");
+    }
+    values.push(lineno);
+
+    GroupingComponent::from_children("frame", values)
+}
+
+/// Computes the grouping component of a stack trace.
+///
+/// If the stack trace contains in-app frames, only those contribute.
+pub fn stacktrace_component(stacktrace: &Stacktrace, platform: Option<&str>) -> GroupingComponent {
+    let frames: Vec<_> = stacktrace
+        .frames
+        .value()
+        .map(|frames| frames.iter().filter_map(|frame| frame.value()).collect())
+        .unwrap_or_default();
+
+    let has_in_app = frames
+        .iter()
+        .any(|frame| frame.in_app.value() == Some(&true));
+
+    let children = frames
+        .iter()
+        .map(|frame| {
+            let mut component = frame_component(frame, platform);
+            if has_in_app && frame.in_app.value() != Some(&true) {
+                component.ignore("non app frame");
+            }
+            component
+        }).collect();
+
+    GroupingComponent::from_children("stacktrace", children)
+}
+
+fn exception_component(exception: &Exception, platform: Option<&str>) -> GroupingComponent {
+    let ty = match exception.ty.value() {
+        Some(ty) => GroupingComponent::new("type", vec![ty.clone()]),
+        None => GroupingComponent::empty("type"),
+    };
+
+    let stacktrace = match exception.stacktrace.value() {
+        Some(stacktrace) => stacktrace_component(stacktrace, platform),
+        None => GroupingComponent::empty("stacktrace"),
+    };
+
+    let mut value = match exception.value.value() {
+        Some(value) => GroupingComponent::new("value", vec![value.as_str().to_string()]),
+        None => GroupingComponent::empty("value"),
+    };
+    if stacktrace.contributes {
+        value.ignore("stacktrace takes precedence");
+    }
+
+    GroupingComponent::from_children("exception", vec![ty, value, stacktrace])
+}
+
+fn exceptions_component(event: &Event, platform: Option<&str>) -> GroupingComponent {
+    let exceptions: Vec<_> = event
+        .exceptions
+        .value()
+        .and_then(|values| values.values.value())
+        .map(|values| values.iter().filter_map(|value| value.value()).collect())
+        .unwrap_or_default();
+
+    match exceptions.len() {
+        0 => GroupingComponent::empty("exception"),
+        1 => exception_component(exceptions[0], platform),
+        _ => GroupingComponent::from_children(
+            "chained-exception",
+            exceptions
+                .into_iter()
+                .map(|exception| exception_component(exception, platform))
+                .collect(),
+        ),
+    }
+}
+
+fn message_component(logentry: &LogEntry) -> GroupingComponent {
+    match logentry
+        .message
+        .value()
+        .or_else(|| logentry.formatted.value())
+    {
+        Some(message) => GroupingComponent::new("message", vec![message.trim().to_string()]),
+        None => GroupingComponent::empty("message"),
+    }
+}
+
+fn get_csp_field<'a>(report: &'a Object<Value>, key: &str) -> Option<&'a str> {
+    report
+        .get(key)
+        .or_else(|| report.get(&key.replace('_', "-")))
+        .and_then(|value| value.value())
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn normalize_csp_uri(uri: &str) -> String {
+    match uri {
+        "self" | "'self'" => "'self'".to_string(),
+        "inline" | "'unsafe-inline'" => "'unsafe-inline'".to_string(),
+        "eval" | "'unsafe-eval'" => "'unsafe-eval'".to_string(),
+        uri => match Url::parse(uri) {
+            Ok(ref url) if url.host_str().is_some() => url.host_str().unwrap_or("").to_string(),
+            Ok(url) => format!("{}:", url.scheme()),
+            Err(_) => uri.to_string(),
+        },
+    }
+}
+
+fn csp_component(csp: &Value) -> GroupingComponent {
+    let report = match csp {
+        Value::Object(report) => report,
+        _ => return GroupingComponent::empty("csp"),
+    };
+
+    let directive = get_csp_field(report, "effective_directive").or_else(|| {
+        get_csp_field(report, "violated_directive")
+            .and_then(|directive| directive.split_whitespace().next())
+    });
+
+    let salt = match directive {
+        Some(directive) => GroupingComponent::new("salt", vec![directive.to_string()]),
+        None => GroupingComponent::empty("salt"),
+    };
+
+    let uri = match get_csp_field(report, "blocked_uri") {
+        Some(uri) => GroupingComponent::new("uri", vec![normalize_csp_uri(uri)]),
+        None => GroupingComponent::empty("uri"),
+    };
+
+    GroupingComponent::from_children("csp", vec![salt, uri])
+}
+
+/// Computes the default grouping component of an event.
+///
+/// All strategies are evaluated, but only the first one that contributes is used for the hash in
+/// the following order: exceptions, the event stack trace, CSP reports and the log message.
+pub fn default_component(event: &Event) -> GroupingComponent {
+    let platform = event.platform.value().map(String::as_str);
+
+    let mut children = vec![
+        exceptions_component(event, platform),
+        match event.stacktrace.value() {
+            Some(stacktrace) => stacktrace_component(stacktrace, platform),
+            None => GroupingComponent::empty("stacktrace"),
+        },
+        match event.csp.value() {
+            Some(csp) => csp_component(csp),
+            None => GroupingComponent::empty("csp"),
+        },
+        match event.logentry.value() {
+            Some(logentry) => message_component(logentry),
+            None => GroupingComponent::empty("message"),
+        },
+    ];
+
+    let mut precedence = None;
+    for child in &mut children {
+        match precedence {
+            Some(id) => child.ignore(format!("{} takes precedence", id)),
+            None if child.contributes => precedence = Some(child.id),
+            None => {}
+        }
+    }
+
+    GroupingComponent::from_children("default", children)
+}
+
+#[test]
+fn test_remove_filename_outliers() {
+    assert_eq!(
+        remove_filename_outliers("/static/1.2.3/app.js?v=42", None),
+        "/static/<version>/app.js"
+    );
+    assert_eq!(
+        remove_filename_outliers("/assets/deadbeef/app.js", None),
+        "/assets/<version>/app.js"
+    );
+    assert_eq!(
+        remove_filename_outliers("/static/v2/1.0.0/deadbeef/app.js", None),
+        "/static/<version>/<version>/<version>/app.js"
+    );
+    assert_eq!(
+        remove_filename_outliers("/static/js2/app.js", None),
+        "/static/js2/app.js"
+    );
+    assert_eq!(
+        remove_filename_outliers("/vendor/lib-abcdef12/1.2.js", None),
+        "/vendor/lib-abcdef12/1.2.js"
+    );
+    assert_eq!(
+        remove_filename_outliers("/Users/x/Build/Foo/AppDelegate.swift", Some("cocoa")),
+        "AppDelegate.swift"
+    );
+}
+
+#[test]
+fn test_remove_module_outliers() {
+    assert_eq!(
+        remove_module_outliers("sun.reflect.GeneratedMethodAccessor42"),
+        "sun.reflect.GeneratedMethodAccessor"
+    );
+    assert_eq!(
+        remove_module_outliers("com.example.Foo$$EnhancerByCGLIB$$1ab2c3d4"),
+        "com.example.Foo$$EnhancerByCGLIB$$<auto>"
+    );
+    assert_eq!(
+        remove_module_outliers("com.example.Foo$$Lambda$123/1234567"),
+        "com.example.Foo$$Lambda$"
+    );
+}
+
+#[test]
+fn test_remove_function_outliers() {
+    assert_eq!(remove_function_outliers("?"), None);
+    assert_eq!(remove_function_outliers("<anonymous>"), None);
+    assert_eq!(
+        remove_function_outliers("block (2 levels) in foo"),
+        Some("block in foo".to_string())
+    );
+}
+
+#[test]
+fn test_csp_uri() {
+    assert_eq!(normalize_csp_uri("self"), "'self'");
+    assert_eq!(
+        normalize_csp_uri("https://evil.example.com/script.js"),
+        "evil.example.com"
+    );
+    assert_eq!(normalize_csp_uri("data:image/png;base64,xx"), "data:");
+}
@@ -1,16 +1,24 @@
+extern crate base64;
 extern crate chrono;
 extern crate cookie;
+extern crate cpp_demangle;
 extern crate debugid;
 extern crate failure;
+extern crate flate2;
 extern crate itertools;
 extern crate lazy_static;
 extern crate maxminddb;
+extern crate md5;
+extern crate msvc_demangler;
 extern crate regex;
+extern crate rustc_demangle;
 extern crate serde;
 extern crate serde_derive;
 extern crate serde_json;
 extern crate serde_urlencoded;
 extern crate smallvec;
+extern crate symbolic_common;
+extern crate symbolic_demangle;
 extern crate url;
 extern crate uuid;
 
@@ -29,6 +37,9 @@ mod macros;
 #[macro_use]
 mod testutils;
 
+pub mod auth;
+pub mod grouping;
+pub mod payload;
 pub mod processor;
 pub mod protocol;
 pub mod store;
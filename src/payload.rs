@@ -0,0 +1,338 @@
+//! Decoding of event payloads as they are received from SDKs.
+//!
+//! Most SDKs send plain JSON, optionally compressed with the `Content-Encoding` of the request.
+//! Older SDKs send the base64 encoding of zlib compressed JSON without declaring it.  All
+//! decompression is limited to a maximum size to protect against compression bombs.
+use std::borrow::Cow;
+use std::io::Read;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use failure::Fail;
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+
+use crate::protocol::Event;
+use crate::types::Annotated;
+
+/// An error returned when decoding a payload fails.
+#[derive(Debug, Fail)]
+pub enum DecodePayloadError {
+    /// The content encoding of the request is not supported.
+    #[fail(display = "unsupported content encoding: {}", _0)]
+    UnsupportedEncoding(String),
+
+    /// The payload is neither JSON nor valid base64.
+    #[fail(display = "invalid base64 payload")]
+    InvalidBase64,
+
+    /// The payload is not valid gzip, zlib or deflate data.
+    #[fail(display = "invalid compressed payload")]
+    InvalidCompression,
+
+    /// The decoded payload exceeds the maximum size.
+    #[fail(display = "payload exceeds the maximum size of {} bytes", _0)]
+    TooLarge(usize),
+
+    /// The decoded payload is not a valid event.
+    #[fail(display = "invalid event payload")]
+    InvalidJson(#[cause] serde_json::Error),
+}
+
+/// The `Content-Encoding` of a request.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    /// The payload is not compressed.
+    #[default]
+    Identity,
+    /// The payload is compressed with gzip.
+    Gzip,
+    /// The payload is compressed with deflate, with or without a zlib header.
+    Deflate,
+}
+
+impl FromStr for ContentEncoding {
+    type Err = DecodePayloadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => Ok(ContentEncoding::Identity),
+            "gzip" | "x-gzip" => Ok(ContentEncoding::Gzip),
+            "deflate" => Ok(ContentEncoding::Deflate),
+            other => Err(DecodePayloadError::UnsupportedEncoding(other.to_string())),
+        }
+    }
+}
+
+fn is_zlib(data: &[u8]) -> bool {
+    match *data {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+/// Reads a decompressed stream up to `max_size` bytes.
+fn read_limited<R: Read>(reader: R, max_size: usize) -> Result<Vec<u8>, DecodePayloadError> {
+    let mut decoded = Vec::new();
+    reader
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|_| DecodePayloadError::InvalidCompression)?;
+
+    if decoded.len() > max_size {
+        return Err(DecodePayloadError::TooLarge(max_size));
+    }
+
+    Ok(decoded)
+}
+
+fn inflate_zlib(data: &[u8], max_size: usize) -> Result<Vec<u8>, DecodePayloadError> {
+    read_limited(ZlibDecoder::new(data), max_size)
+}
+
+fn inflate_deflate(data: &[u8], max_size: usize) -> Result<Vec<u8>, DecodePayloadError> {
+    // Many clients send zlib data as `deflate`, as the HTTP specification requires.
+    if is_zlib(data) {
+        match inflate_zlib(data, max_size) {
+            Err(DecodePayloadError::InvalidCompression) => (),
+            result => return result,
+        }
+    }
+
+    read_limited(DeflateDecoder::new(data), max_size)
+}
+
+/// Decompresses all members of a gzip stream as specified in RFC 1952.
+fn inflate_gzip(data: &[u8], max_size: usize) -> Result<Vec<u8>, DecodePayloadError> {
+    read_limited(MultiGzDecoder::new(data), max_size)
+}
+
+/// Decodes standard or URL-safe base64, ignoring whitespace and padding.
+fn decode_base64(data: &[u8]) -> Result<Vec<u8>, DecodePayloadError> {
+    let mut data: Vec<u8> = data
+        .iter()
+        .cloned()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+
+    while data.last() == Some(&b'=') {
+        data.pop();
+    }
+
+    if data.is_empty() {
+        return Err(DecodePayloadError::InvalidBase64);
+    }
+
+    let engine = if data.iter().any(|&c| c == b'-' || c == b'_') {
+        &URL_SAFE_NO_PAD
+    } else {
+        &STANDARD_NO_PAD
+    };
+
+    engine
+        .decode(&data)
+        .map_err(|_| DecodePayloadError::InvalidBase64)
+}
+
+fn is_json(data: &[u8]) -> bool {
+    data.iter()
+        .find(|c| !c.is_ascii_whitespace())
+        .is_some_and(|&c| c == b'{')
+}
+
+/// Decodes a payload into JSON bytes.
+///
+/// The payload is first decompressed according to the content encoding of the request.  If the
+/// result is not a JSON object, it is treated as a legacy payload, which is base64 encoded and
+/// optionally zlib compressed.  The decoded payload never exceeds `max_size` bytes.
+pub fn decode_payload(
+    data: &[u8],
+    encoding: ContentEncoding,
+    max_size: usize,
+) -> Result<Cow<'_, [u8]>, DecodePayloadError> {
+    let data = match encoding {
+        ContentEncoding::Identity => Cow::Borrowed(data),
+        ContentEncoding::Gzip => Cow::Owned(inflate_gzip(data, max_size)?),
+        ContentEncoding::Deflate => Cow::Owned(inflate_deflate(data, max_size)?),
+    };
+
+    if data.len() > max_size {
+        return Err(DecodePayloadError::TooLarge(max_size));
+    }
+
+    if is_json(&data) {
+        return Ok(data);
+    }
+
+    let data = if is_zlib(&data) {
+        data
+    } else {
+        Cow::Owned(decode_base64(&data)?)
+    };
+
+    if is_zlib(&data) {
+        Ok(Cow::Owned(inflate_zlib(&data, max_size)?))
+    } else {
+        Ok(data)
+    }
+}
+
+/// Decodes and deserializes an event from a payload as it was received.
+///
+/// See `decode_payload` for the supported encodings.
+pub fn event_from_payload(
+    data: &[u8],
+    encoding: ContentEncoding,
+    max_size: usize,
+) -> Result<Annotated<Event>, DecodePayloadError> {
+    let json = decode_payload(data, encoding, max_size)?;
+    Annotated::from_json_bytes(&json).map_err(DecodePayloadError::InvalidJson)
+}
+
+#[cfg(test)]
+const JSON: &[u8] = br#"{"message":"hello"}"#;
+
+#[test]
+fn test_decode_base64() {
+    assert_eq_dbg!(decode_base64(b"aGVsbG8=").unwrap(), b"hello".to_vec());
+    assert_eq_dbg!(decode_base64(b"aGVs\nbG8").unwrap(), b"hello".to_vec());
+    assert_eq_dbg!(decode_base64(b"-_8=").unwrap(), vec![0xfb, 0xff]);
+    assert!(decode_base64(b"aGVsbG8*").is_err());
+    assert!(decode_base64(b"aGVsb").is_err());
+}
+
+#[test]
+fn test_decode_payload() {
+    let gzip = [
+        31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 171, 86, 202, 77, 45, 46, 78, 76, 79, 85, 178, 82, 202,
+        72, 205, 201, 201, 87, 170, 5, 0, 140, 107, 216, 17, 19, 0, 0, 0,
+    ];
+    let zlib = [
+        120, 156, 171, 86, 202, 77, 45, 46, 78, 76, 79, 85, 178, 82, 202, 72, 205, 201, 201, 87,
+        170, 5, 0, 67, 194, 6, 180,
+    ];
+
+    let decode = |data: &[u8], encoding| decode_payload(data, encoding, 1024).unwrap().into_owned();
+
+    assert_eq_dbg!(decode(JSON, ContentEncoding::Identity), JSON.to_vec());
+    assert_eq_dbg!(decode(&gzip, ContentEncoding::Gzip), JSON.to_vec());
+    assert_eq_dbg!(decode(&zlib, ContentEncoding::Deflate), JSON.to_vec());
+    assert_eq_dbg!(
+        decode(&zlib[2..23], ContentEncoding::Deflate),
+        JSON.to_vec()
+    );
+    assert_eq_dbg!(
+        decode(
+            b"eJyrVspNLS5OTE9VslLKSM3JyVeqBQBDwga0",
+            ContentEncoding::Identity
+        ),
+        JSON.to_vec()
+    );
+    assert_eq_dbg!(
+        decode(b"eyJtZXNzYWdlIjoiaGVsbG8ifQ==", ContentEncoding::Identity),
+        JSON.to_vec()
+    );
+
+    let mut corrupt = gzip;
+    corrupt[gzip.len() - 5] ^= 1;
+    assert_eq_str!(
+        decode_payload(&corrupt, ContentEncoding::Gzip, 1024)
+            .unwrap_err()
+            .to_string(),
+        "invalid compressed payload"
+    );
+    assert_eq_str!(
+        "br".parse::<ContentEncoding>().unwrap_err().to_string(),
+        "unsupported content encoding: br"
+    );
+}
+
+#[cfg(test)]
+fn compress_gzip(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn test_decode_gzip_members() {
+    let mut gzip = compress_gzip(br#"{"message":"#);
+    gzip.extend(compress_gzip(br#""hello"}"#));
+
+    assert_eq_dbg!(
+        decode_payload(&gzip, ContentEncoding::Gzip, 1024)
+            .unwrap()
+            .into_owned(),
+        JSON.to_vec()
+    );
+}
+
+#[test]
+fn test_decode_gzip_header_crc() {
+    // Set FHCRC and insert the CRC16 of the header after it.
+    let mut gzip = compress_gzip(JSON);
+    gzip[3] |= 0x02;
+
+    let mut crc = flate2::Crc::new();
+    crc.update(&gzip[..10]);
+    let header_crc = (crc.sum() as u16).to_le_bytes();
+    gzip.splice(10..10, header_crc.iter().cloned());
+
+    assert_eq_dbg!(
+        decode_payload(&gzip, ContentEncoding::Gzip, 1024)
+            .unwrap()
+            .into_owned(),
+        JSON.to_vec()
+    );
+
+    gzip[10] ^= 1;
+    assert!(decode_payload(&gzip, ContentEncoding::Gzip, 1024).is_err());
+}
+
+#[test]
+fn test_decode_payload_too_large() {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&[b' '; 100_000]).unwrap();
+    let bomb = encoder.finish().unwrap();
+    assert!(bomb.len() < 1024);
+
+    assert_eq_str!(
+        decode_payload(&bomb, ContentEncoding::Deflate, 1024)
+            .unwrap_err()
+            .to_string(),
+        "payload exceeds the maximum size of 1024 bytes"
+    );
+
+    let bomb = compress_gzip(&[b' '; 100_000]);
+    assert_eq_str!(
+        decode_payload(&bomb, ContentEncoding::Gzip, 1024)
+            .unwrap_err()
+            .to_string(),
+        "payload exceeds the maximum size of 1024 bytes"
+    );
+    assert_eq_str!(
+        decode_payload(JSON, ContentEncoding::Identity, 10)
+            .unwrap_err()
+            .to_string(),
+        "payload exceeds the maximum size of 10 bytes"
+    );
+}
+
+#[test]
+fn test_event_from_payload() {
+    let event = event_from_payload(
+        b"eJyrVspNLS5OTE9VslLKSM3JyVeqBQBDwga0",
+        ContentEncoding::Identity,
+        1024,
+    )
+    .unwrap();
+    let logentry = event.value().unwrap().logentry.value().unwrap();
+    assert_eq_str!(logentry.formatted.value().unwrap(), "hello");
+
+    let error = event_from_payload(b"{", ContentEncoding::Identity, 1024).unwrap_err();
+    assert_eq_str!(error.to_string(), "invalid event payload");
+}
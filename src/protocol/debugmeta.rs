@@ -129,6 +129,92 @@ pub struct SymbolicDebugImage {
     pub other: Object<Value>,
 }
 
+/// A native debug image of an ELF, PE or WebAssembly module.
+///
+/// The code file and identifier describe the executable as it was loaded, the debug file and
+/// identifier describe the file containing its debug information.  On Linux, both are usually
+/// the same file and the debug identifier is derived from the GNU build id.  On Windows, the code
+/// identifier is derived from the PE header and the debug identifier from the PDB reference.
+#[derive(Debug, Clone, PartialEq, Default, FromValue, ToValue, ProcessValue)]
+pub struct NativeDebugImage {
+    /// Path and name of the image file as loaded by the process (required).
+    #[metastructure(required = "true")]
+    pub code_file: Annotated<String>,
+
+    /// Identifier of the image file, such as the hex encoded GNU build id.
+    pub code_id: Annotated<String>,
+
+    /// Path and name of the debug companion file.
+    pub debug_file: Annotated<String>,
+
+    /// Unique debug identifier of the image.
+    pub debug_id: Annotated<DebugId>,
+
+    /// CPU architecture target.
+    pub arch: Annotated<String>,
+
+    /// Starting memory address of the image.
+    pub image_addr: Annotated<Addr>,
+
+    /// Size of the image in bytes.
+    pub image_size: Annotated<u64>,
+
+    /// Loading address in virtual memory.
+    pub image_vmaddr: Annotated<Addr>,
+
+    /// Additional arbitrary fields for forwards compatibility.
+    #[metastructure(additional_properties)]
+    pub other: Object<Value>,
+}
+
+/// Derives a debug identifier from an ELF GNU build id.
+///
+/// The first 16 bytes of the build id are interpreted as a little-endian GUID, which matches the
+/// identifiers used by Breakpad.  Shorter build ids are padded with zeros.  Returns `None` if the
+/// build id is empty.
+pub fn debug_id_from_build_id(build_id: &[u8]) -> Option<DebugId> {
+    if build_id.is_empty() {
+        return None;
+    }
+
+    let mut guid = [0; 16];
+    let len = build_id.len().min(16);
+    guid[..len].copy_from_slice(&build_id[..len]);
+    DebugId::from_guid_age(&guid, 0).ok()
+}
+
+/// Derives a debug identifier from a hex encoded ELF GNU build id.
+///
+/// Returns `None` if the code identifier is not valid hex.
+pub fn debug_id_from_code_id(code_id: &str) -> Option<DebugId> {
+    // `from_str_radix` accepts a leading sign, so validate the digits upfront.
+    if !code_id.len().is_multiple_of(2) || !code_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let build_id = (0..code_id.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(code_id.get(index..index + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    debug_id_from_build_id(&build_id)
+}
+
+/// Computes the code identifier of a PE image from its header.
+///
+/// This is the `TimeDateStamp` followed by the `SizeOfImage`, as used by symbol servers.
+pub fn pe_code_id(timestamp: u32, size_of_image: u32) -> String {
+    format!("{:08X}{:x}", timestamp, size_of_image)
+}
+
+/// Derives a debug identifier from the PDB signature and age of a PE image.
+///
+/// The signature is the little-endian GUID of the CodeView record.  Returns `None` if the
+/// signature is not 16 bytes long.
+pub fn debug_id_from_pdb(signature: &[u8], age: u32) -> Option<DebugId> {
+    DebugId::from_guid_age(signature, age).ok()
+}
+
 /// Proguard mapping file.
 #[derive(Debug, Clone, PartialEq, Default, FromValue, ToValue, ProcessValue)]
 pub struct ProguardDebugImage {
@@ -149,6 +235,12 @@ pub enum DebugImage {
     Apple(Box<AppleDebugImage>),
     /// Symbolic (new style) debug infos.
     Symbolic(Box<SymbolicDebugImage>),
+    /// ELF images on Linux and other Unix platforms.
+    Elf(Box<NativeDebugImage>),
+    /// PE images on Windows with PDB debug files.
+    Pe(Box<NativeDebugImage>),
+    /// WebAssembly modules.
+    Wasm(Box<NativeDebugImage>),
     /// A reference to a proguard debug file.
     Proguard(Box<ProguardDebugImage>),
     /// A debug image that is unknown to this protocol specification.
@@ -424,3 +516,95 @@ fn test_debug_meta_default_values() {
     assert_eq_dbg!(meta, Annotated::from_json(json).unwrap());
     assert_eq_str!(json, meta.to_json_pretty().unwrap());
 }
+
+#[test]
+fn test_debug_image_elf_roundtrip() {
+    let json = r#"{
+  "code_file": "/lib/x86_64-linux-gnu/libc.so.6",
+  "code_id": "dfb85de42daffd09640c8fe377d572de3e168920",
+  "debug_file": "/usr/lib/debug/libc.so.6.debug",
+  "debug_id": "e45db8df-af2d-09fd-640c-8fe377d572de",
+  "arch": "x86_64",
+  "image_addr": "0x7f5140527000",
+  "image_size": 1835008,
+  "other": "value",
+  "type": "elf"
+}"#;
+
+    let image = Annotated::new(DebugImage::Elf(Box::new(NativeDebugImage {
+        code_file: Annotated::new("/lib/x86_64-linux-gnu/libc.so.6".to_string()),
+        code_id: Annotated::new("dfb85de42daffd09640c8fe377d572de3e168920".to_string()),
+        debug_file: Annotated::new("/usr/lib/debug/libc.so.6.debug".to_string()),
+        debug_id: Annotated::new("e45db8df-af2d-09fd-640c-8fe377d572de".parse().unwrap()),
+        arch: Annotated::new("x86_64".to_string()),
+        image_addr: Annotated::new(Addr(0x7f51_4052_7000)),
+        image_size: Annotated::new(1_835_008),
+        image_vmaddr: Annotated::empty(),
+        other: {
+            let mut map = Object::new();
+            map.insert(
+                "other".to_string(),
+                Annotated::new(Value::String("value".to_string())),
+            );
+            map
+        },
+    })));
+
+    assert_eq_dbg!(image, Annotated::from_json(json).unwrap());
+    assert_eq_str!(json, image.to_json_pretty().unwrap());
+}
+
+#[test]
+fn test_debug_image_pe_wasm_default_values() {
+    let json = r#"{"code_file":"C:\\Windows\\System32\\kernel32.dll","type":"pe"}"#;
+    let image = Annotated::new(DebugImage::Pe(Box::new(NativeDebugImage {
+        code_file: Annotated::new("C:\\Windows\\System32\\kernel32.dll".to_string()),
+        ..Default::default()
+    })));
+
+    assert_eq_dbg!(image, Annotated::from_json(json).unwrap());
+    assert_eq_str!(json, image.to_json().unwrap());
+
+    let json = r#"{"type":"wasm"}"#;
+    let image = Annotated::new(DebugImage::Wasm(Box::new(NativeDebugImage {
+        code_file: Annotated::from_error("value required", None),
+        ..Default::default()
+    })));
+
+    assert_eq_dbg!(image, Annotated::from_json(json).unwrap());
+}
+
+#[test]
+fn test_debug_id_from_build_id() {
+    assert_eq_dbg!(
+        debug_id_from_code_id("dfb85de42daffd09640c8fe377d572de3e168920"),
+        Some("e45db8df-af2d-09fd-640c-8fe377d572de".parse().unwrap())
+    );
+
+    // Short build ids are padded with zeros.
+    assert_eq_dbg!(
+        debug_id_from_build_id(&[0x01, 0x02, 0x03, 0x04, 0x05]),
+        Some("04030201-0005-0000-0000-000000000000".parse().unwrap())
+    );
+
+    assert_eq_dbg!(debug_id_from_build_id(&[]), None);
+    assert_eq_dbg!(debug_id_from_code_id("dfb85"), None);
+    assert_eq_dbg!(debug_id_from_code_id("zz"), None);
+    assert_eq_dbg!(debug_id_from_code_id("+f"), None);
+    assert_eq_dbg!(debug_id_from_code_id("+fdfb85de42daffd09640c8fe377d572de3e1689"), None);
+}
+
+#[test]
+fn test_pe_identifiers() {
+    assert_eq!(pe_code_id(0x5ab3_8077, 0x37000), "5AB3807737000");
+
+    let signature = [
+        0x3f, 0x3e, 0x1c, 0xa3, 0x2d, 0x0b, 0x0a, 0x4f, 0x9a, 0xd3, 0x37, 0x2b, 0x2e, 0x74,
+        0x28, 0x1d,
+    ];
+    assert_eq_dbg!(
+        debug_id_from_pdb(&signature, 2),
+        Some("a31c3e3f-0b2d-4f0a-9ad3-372b2e74281d-2".parse().unwrap())
+    );
+    assert_eq_dbg!(debug_id_from_pdb(&signature[..4], 2), None);
+}
@@ -2,6 +2,74 @@ use crate::processor::{FromValue, ProcessValue, ToValue};
 use crate::protocol::LenientString;
 use crate::types::{Annotated, Value};
 
+/// A template variable in a fingerprint.
+///
+/// Variables are written as `{{ name }}` and are replaced by values from the event when the
+/// fingerprint is expanded.  Variable names are case insensitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FingerprintVariable {
+    /// The default grouping values of the event.
+    Default,
+    /// The transaction of the event.
+    Transaction,
+    /// The type of the top exception.
+    Type,
+    /// The function of the top frame.
+    Function,
+    /// The module of the top frame.
+    Module,
+    /// The package of the top frame.
+    Package,
+    /// The level of the event.
+    Level,
+    /// The value of a tag, written as `{{ tags.name }}`.
+    Tag(String),
+}
+
+impl FingerprintVariable {
+    /// Returns the variable name if the given fingerprint value is a template variable.
+    ///
+    /// The returned name is trimmed and its kind is lowercased, while the key of a tag variable
+    /// is kept as written since tag keys are case sensitive.  It is not checked whether the
+    /// variable is known.
+    pub fn parse_name(value: &str) -> Option<String> {
+        let value = value.trim();
+        if value.len() < 4 || !value.starts_with("{{") || !value.ends_with("}}") {
+            return None;
+        }
+
+        let name = value[2..value.len() - 2].trim();
+        Some(match name.find('.') {
+            Some(index) => format!("{}{}", name[..index].to_lowercase(), &name[index..]),
+            None => name.to_lowercase(),
+        })
+    }
+
+    /// Looks up a variable by its name.
+    ///
+    /// Returns `None` for unknown variables.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "default" => FingerprintVariable::Default,
+            "transaction" => FingerprintVariable::Transaction,
+            "type" => FingerprintVariable::Type,
+            "function" => FingerprintVariable::Function,
+            "module" => FingerprintVariable::Module,
+            "package" => FingerprintVariable::Package,
+            "level" => FingerprintVariable::Level,
+            _ if name.starts_with("tags.") && name.len() > 5 => {
+                FingerprintVariable::Tag(name[5..].to_string())
+            }
+            _ => return None,
+        })
+    }
+
+    /// Parses a known template variable from a fingerprint value.
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::parse_name(value).and_then(|name| Self::from_name(&name))
+    }
+}
+
 /// A fingerprint value.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Fingerprint(Vec<String>);
@@ -14,6 +82,15 @@ impl std::ops::Deref for Fingerprint {
     }
 }
 
+impl Fingerprint {
+    /// Iterates over the parsed template variables of this fingerprint.
+    ///
+    /// Plain values and unknown variables yield `None`.
+    pub fn variables(&self) -> impl Iterator<Item = Option<FingerprintVariable>> + '_ {
+        self.0.iter().map(|value| FingerprintVariable::parse(value))
+    }
+}
+
 impl From<Vec<String>> for Fingerprint {
     fn from(vec: Vec<String>) -> Fingerprint {
         Fingerprint(vec)
@@ -33,6 +110,14 @@ impl FromValue for Fingerprint {
                 for elem in array {
                     let Annotated(value, mut elem_meta) = LenientString::from_value(elem);
                     if let (Some(value), false) = (value, elem_meta.has_errors()) {
+                        if let Some(name) = FingerprintVariable::parse_name(&value.0) {
+                            if FingerprintVariable::from_name(&name).is_none() {
+                                meta.add_error(
+                                    format!("unknown fingerprint variable '{}'", name),
+                                    None,
+                                );
+                            }
+                        }
                         fingerprint.push(value.0);
                     }
                     if let Some(bad_value) = elem_meta.take_original_value() {
@@ -91,6 +176,48 @@ impl ToValue for Fingerprint {
 // Fingerprints must not be trimmed.
 impl ProcessValue for Fingerprint {}
 
+#[test]
+fn test_parse_variable() {
+    assert_eq!(
+        FingerprintVariable::parse_name("{{ default }}"),
+        Some("default".to_string())
+    );
+    assert_eq!(
+        FingerprintVariable::parse("{{Default}}"),
+        Some(FingerprintVariable::Default)
+    );
+    assert_eq!(
+        FingerprintVariable::parse("{{ tags.Server_Name }}"),
+        Some(FingerprintVariable::Tag("Server_Name".to_string()))
+    );
+    assert_eq!(
+        FingerprintVariable::parse_name("{{ Tags.Server_Name }}"),
+        Some("tags.Server_Name".to_string())
+    );
+    assert_eq!(FingerprintVariable::parse("{{ tags. }}"), None);
+    assert_eq!(FingerprintVariable::parse("{{ defualt }}"), None);
+    assert_eq!(FingerprintVariable::parse_name("default"), None);
+    assert_eq!(FingerprintVariable::parse_name("{{"), None);
+}
+
+#[test]
+fn test_fingerprint_unknown_variable() {
+    use crate::types::Meta;
+
+    let mut meta = Meta::default();
+    meta.add_error("unknown fingerprint variable 'defualt'", None);
+    assert_eq_dbg!(
+        Annotated(
+            Some(Fingerprint(vec![
+                "{{ defualt }}".to_string(),
+                "{{ tags.foo }}".to_string(),
+            ])),
+            meta
+        ),
+        Annotated::<Fingerprint>::from_json("[\"{{ defualt }}\", \"{{ tags.foo }}\"]").unwrap()
+    );
+}
+
 #[test]
 fn test_fingerprint_string() {
     assert_eq_dbg!(
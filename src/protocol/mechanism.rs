@@ -48,6 +48,51 @@ pub struct PosixSignal {
     pub code_name: Annotated<String>,
 }
 
+/// Windows NTSTATUS or structured exception code.
+#[derive(Debug, Clone, PartialEq, Default, FromValue, ToValue, ProcessValue)]
+pub struct NtStatus {
+    /// The 32-bit status code, either signed or unsigned.
+    #[metastructure(required = "true")]
+    pub number: Annotated<i64>,
+
+    /// Optional name of the status constant (e.g. `STATUS_ACCESS_VIOLATION`).
+    pub name: Annotated<String>,
+
+    /// Optional name of the exception code constant (e.g. `EXCEPTION_ACCESS_VIOLATION`).
+    pub exception_name: Annotated<String>,
+}
+
+/// Windows COM error code.
+#[derive(Debug, Clone, PartialEq, Default, FromValue, ToValue, ProcessValue)]
+pub struct HResult {
+    /// The 32-bit error code, either signed or unsigned.
+    #[metastructure(required = "true")]
+    pub number: Annotated<i64>,
+
+    /// Optional name of the HRESULT constant.
+    pub name: Annotated<String>,
+}
+
+/// An `NSError` on Apple platforms.
+#[derive(Debug, Clone, PartialEq, Default, FromValue, ToValue, ProcessValue)]
+pub struct NsError {
+    /// The error code.
+    #[metastructure(required = "true")]
+    pub code: Annotated<i64>,
+
+    /// The error domain (e.g. `NSURLErrorDomain`).
+    #[metastructure(required = "true", nonempty = "true")]
+    pub domain: Annotated<String>,
+}
+
+/// A C++ exception that was thrown.
+#[derive(Debug, Clone, PartialEq, Default, FromValue, ToValue, ProcessValue)]
+pub struct CppException {
+    /// The mangled name of the exception type (e.g. `St13runtime_error`).
+    #[metastructure(required = "true", nonempty = "true", max_chars = "symbol")]
+    pub name: Annotated<String>,
+}
+
 /// Operating system or runtime meta information to an exception mechanism.
 #[derive(Debug, Clone, PartialEq, Default, FromValue, ToValue, ProcessValue)]
 pub struct MechanismMeta {
@@ -60,6 +105,18 @@ pub struct MechanismMeta {
     /// Optional mach exception information.
     pub mach_exception: Annotated<MachException>,
 
+    /// Optional Windows NTSTATUS or exception code.
+    pub ntstatus: Annotated<NtStatus>,
+
+    /// Optional Windows HRESULT.
+    pub hresult: Annotated<HResult>,
+
+    /// Optional `NSError` information.
+    pub ns_error: Annotated<NsError>,
+
+    /// Optional C++ exception information.
+    pub cpp_exception: Annotated<CppException>,
+
     /// Additional arbitrary fields for forwards compatibility.
     #[metastructure(additional_properties)]
     pub other: Object<Value>,
@@ -183,6 +240,10 @@ impl FromValue for Mechanism {
                                     name: legacy.exception_name,
                                 }
                             }),
+                            ntstatus: Annotated::empty(),
+                            hresult: Annotated::empty(),
+                            ns_error: Annotated::empty(),
+                            cpp_exception: Annotated::empty(),
                             other: Object::default(),
                         }),
                         other: Object::default(),
@@ -227,6 +288,22 @@ fn test_mechanism_roundtrip() {
       "subcode": 8,
       "name": "EXC_BAD_ACCESS"
     },
+    "ntstatus": {
+      "number": 3221225477,
+      "name": "STATUS_ACCESS_VIOLATION",
+      "exception_name": "EXCEPTION_ACCESS_VIOLATION"
+    },
+    "hresult": {
+      "number": 2147500037,
+      "name": "E_FAIL"
+    },
+    "ns_error": {
+      "code": -1001,
+      "domain": "NSURLErrorDomain"
+    },
+    "cpp_exception": {
+      "name": "St13runtime_error"
+    },
     "other": "value"
   },
   "other": "value"
@@ -263,6 +340,22 @@ fn test_mechanism_roundtrip() {
                 name: Annotated::new("SIGSEGV".to_string()),
                 code_name: Annotated::new("SEGV_NOOP".to_string()),
             }),
+            ntstatus: Annotated::new(NtStatus {
+                number: Annotated::new(0xc000_0005),
+                name: Annotated::new("STATUS_ACCESS_VIOLATION".to_string()),
+                exception_name: Annotated::new("EXCEPTION_ACCESS_VIOLATION".to_string()),
+            }),
+            hresult: Annotated::new(HResult {
+                number: Annotated::new(0x8000_4005),
+                name: Annotated::new("E_FAIL".to_string()),
+            }),
+            ns_error: Annotated::new(NsError {
+                code: Annotated::new(-1001),
+                domain: Annotated::new("NSURLErrorDomain".to_string()),
+            }),
+            cpp_exception: Annotated::new(CppException {
+                name: Annotated::new("St13runtime_error".to_string()),
+            }),
             other: {
                 let mut map = Object::new();
                 map.insert(
@@ -311,7 +404,9 @@ fn test_mechanism_invalid_meta() {
   "meta": {
     "errno": {"name": "ENOENT"},
     "mach_exception": {"name": "EXC_BAD_ACCESS"},
-    "signal": {"name": "SIGSEGV"}
+    "signal": {"name": "SIGSEGV"},
+    "ns_error": {"domain": "NSCocoaErrorDomain"},
+    "cpp_exception": {"name": ""}
   }
 }"#;
     let mechanism = Annotated::new(Mechanism {
@@ -333,6 +428,13 @@ fn test_mechanism_invalid_meta() {
                 name: Annotated::new("SIGSEGV".to_string()),
                 code_name: Annotated::empty(),
             }),
+            ns_error: Annotated::new(NsError {
+                code: Annotated::from_error("value required", None),
+                domain: Annotated::new("NSCocoaErrorDomain".to_string()),
+            }),
+            cpp_exception: Annotated::new(CppException {
+                name: Annotated::from_error("non-empty value required", None),
+            }),
             ..Default::default()
         }),
         ..Default::default()
@@ -408,6 +510,10 @@ fn test_mechanism_legacy_conversion() {
                 name: Annotated::new("SIGSEGV".to_string()),
                 code_name: Annotated::new("SEGV_NOOP".to_string()),
             }),
+            ntstatus: Annotated::empty(),
+            hresult: Annotated::empty(),
+            ns_error: Annotated::empty(),
+            cpp_exception: Annotated::empty(),
             other: Object::default(),
         }),
         other: Object::default(),
@@ -22,13 +22,18 @@ pub use self::contexts::{
     AppContext, BrowserContext, Context, Contexts, DeviceContext, OsContext, RuntimeContext,
 };
 pub use self::debugmeta::{
-    AppleDebugImage, DebugImage, DebugMeta, SymbolicDebugImage, SystemSdkInfo,
+    debug_id_from_build_id, debug_id_from_code_id, debug_id_from_pdb, pe_code_id,
+    AppleDebugImage, DebugImage, DebugMeta, NativeDebugImage, ProguardDebugImage,
+    SymbolicDebugImage, SystemSdkInfo,
 };
 pub use self::event::{Event, EventId, EventProcessingError, EventType, ParseEventTypeError};
 pub use self::exception::Exception;
-pub use self::fingerprint::Fingerprint;
+pub use self::fingerprint::{Fingerprint, FingerprintVariable};
 pub use self::logentry::LogEntry;
-pub use self::mechanism::{CError, MachException, Mechanism, MechanismMeta, PosixSignal};
+pub use self::mechanism::{
+    CError, CppException, HResult, MachException, Mechanism, MechanismMeta, NsError, NtStatus,
+    PosixSignal,
+};
 pub use self::request::{Cookies, Headers, Query, Request};
 pub use self::stacktrace::{Frame, Stacktrace};
 pub use self::tags::Tags;
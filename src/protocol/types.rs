@@ -4,6 +4,7 @@ use std::net;
 use std::str::FromStr;
 
 use failure::Fail;
+use serde::de::{self, Deserialize, Deserializer};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
 use serde_derive::{Deserialize, Serialize};
 
@@ -279,6 +280,19 @@ impl fmt::Display for Level {
     }
 }
 
+impl Serialize for Level {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Level {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        string.parse().map_err(de::Error::custom)
+    }
+}
+
 impl FromValue for Level {
     fn from_value(value: Annotated<Value>) -> Annotated<Self> {
         match value {
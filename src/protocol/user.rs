@@ -17,6 +17,33 @@ pub struct Geo {
     #[metastructure(pii_kind = "location", max_chars = "summary")]
     pub region: Annotated<String>,
 
+    /// Subdivision code of the region (ISO 3166-2), such as `US-CA`.
+    #[metastructure(pii_kind = "location", max_chars = "enumlike")]
+    pub subdivision: Annotated<String>,
+
+    /// Postal code of the location.
+    #[metastructure(pii_kind = "location", max_chars = "enumlike")]
+    pub postal_code: Annotated<String>,
+
+    /// Approximate latitude of the location.
+    #[metastructure(pii_kind = "location")]
+    pub latitude: Annotated<f64>,
+
+    /// Approximate longitude of the location.
+    #[metastructure(pii_kind = "location")]
+    pub longitude: Annotated<f64>,
+
+    /// Time zone of the location as IANA identifier, such as `Europe/Vienna`.
+    #[metastructure(max_chars = "enumlike")]
+    pub timezone: Annotated<String>,
+
+    /// Number of the autonomous system that announces the IP address.
+    pub asn: Annotated<u64>,
+
+    /// Name of the organization that operates the autonomous system.
+    #[metastructure(max_chars = "summary")]
+    pub organization: Annotated<String>,
+
     /// Additional arbitrary fields for forwards compatibility.
     #[metastructure(additional_properties)]
     pub other: Object<Value>,
@@ -62,12 +89,26 @@ fn test_geo_roundtrip() {
   "country_code": "US",
   "city": "San Francisco",
   "region": "CA",
+  "subdivision": "US-CA",
+  "postal_code": "94107",
+  "latitude": 37.8,
+  "longitude": -122.4,
+  "timezone": "America/Los_Angeles",
+  "asn": 7018,
+  "organization": "AT&T Services, Inc.",
   "other": "value"
 }"#;
     let geo = Annotated::new(Geo {
         country_code: Annotated::new("US".to_string()),
         city: Annotated::new("San Francisco".to_string()),
         region: Annotated::new("CA".to_string()),
+        subdivision: Annotated::new("US-CA".to_string()),
+        postal_code: Annotated::new("94107".to_string()),
+        latitude: Annotated::new(37.8),
+        longitude: Annotated::new(-122.4),
+        timezone: Annotated::new("America/Los_Angeles".to_string()),
+        asn: Annotated::new(7018),
+        organization: Annotated::new("AT&T Services, Inc.".to_string()),
         other: {
             let mut map = Map::new();
             map.insert(
@@ -89,6 +130,13 @@ fn test_geo_default_values() {
         country_code: Annotated::empty(),
         city: Annotated::empty(),
         region: Annotated::empty(),
+        subdivision: Annotated::empty(),
+        postal_code: Annotated::empty(),
+        latitude: Annotated::empty(),
+        longitude: Annotated::empty(),
+        timezone: Annotated::empty(),
+        asn: Annotated::empty(),
+        organization: Annotated::empty(),
         other: Default::default(),
     });
 
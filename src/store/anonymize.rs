@@ -0,0 +1,294 @@
+use std::net;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::protocol::{Event, IpAddr};
+use crate::store::client_ip::parse_forwarded_ip;
+use crate::types::{Annotated, Remark, RemarkType, Value};
+
+/// The rule id of remarks added when anonymizing IP addresses.
+const RULE_ID: &str = "@ip:anonymize";
+
+/// Headers that contain comma separated lists of client IP addresses.
+///
+/// The `Forwarded` header has a structured syntax and is handled separately.
+const IP_HEADERS: &[&str] = &["X-Forwarded-For", "X-Real-IP"];
+
+/// How IP addresses of the client are stored.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IpAnonymization {
+    /// IP addresses are stored as sent.
+    #[default]
+    Keep,
+    /// IPv4 addresses are truncated to `/24` and IPv6 addresses to `/48` networks.
+    Truncate,
+    /// IP addresses are removed.
+    Remove,
+}
+
+/// Anonymizes a single IP address.
+///
+/// Returns `None` if the address should be removed.  Values that are not valid IP addresses are
+/// always removed, since they cannot be truncated.
+pub fn anonymize_ip(ip: &str, mode: IpAnonymization) -> Option<String> {
+    match mode {
+        IpAnonymization::Keep => Some(ip.to_string()),
+        IpAnonymization::Remove => None,
+        IpAnonymization::Truncate => match ip.trim().parse().ok()? {
+            net::IpAddr::V4(addr) => {
+                let [a, b, c, _] = addr.octets();
+                Some(net::Ipv4Addr::new(a, b, c, 0).to_string())
+            }
+            net::IpAddr::V6(addr) => {
+                let s = addr.segments();
+                Some(net::Ipv6Addr::new(s[0], s[1], s[2], 0, 0, 0, 0, 0).to_string())
+            }
+        },
+    }
+}
+
+/// Anonymizes a comma separated list of IP addresses, such as in `X-Forwarded-For`.
+fn anonymize_ip_list(list: &str, mode: IpAnonymization) -> Option<String> {
+    let ips: Vec<_> = list
+        .split(',')
+        .filter_map(|ip| anonymize_ip(ip, mode))
+        .collect();
+
+    if ips.is_empty() {
+        None
+    } else {
+        Some(ips.join(", "))
+    }
+}
+
+/// Anonymizes the `for` parameters of a `Forwarded` header.
+///
+/// Addresses may be quoted, bracketed or carry a port, which is dropped.  Parameters that are not
+/// valid IP addresses, such as obfuscated identifiers, are removed along with empty elements.
+fn anonymize_forwarded(header: &str, mode: IpAnonymization) -> Option<String> {
+    let elements: Vec<_> = header
+        .split(',')
+        .map(|element| {
+            element
+                .split(';')
+                .map(str::trim)
+                .filter_map(|pair| {
+                    let (key, value) = match pair.find('=') {
+                        Some(index) => (&pair[..index], &pair[index + 1..]),
+                        None => return Some(pair.to_string()),
+                    };
+
+                    if !key.trim().eq_ignore_ascii_case("for") {
+                        return Some(pair.to_string());
+                    }
+
+                    let ip = parse_forwarded_ip(value)?;
+                    match anonymize_ip(&ip.to_string(), mode)?.parse().ok()? {
+                        net::IpAddr::V4(addr) => Some(format!("{}={}", key, addr)),
+                        net::IpAddr::V6(addr) => Some(format!("{}=\"[{}]\"", key, addr)),
+                    }
+                })
+                .filter(|pair| !pair.is_empty())
+                .collect::<Vec<_>>()
+                .join(";")
+        })
+        .filter(|element| !element.is_empty())
+        .collect();
+
+    if elements.is_empty() {
+        None
+    } else {
+        Some(elements.join(", "))
+    }
+}
+
+/// Replaces the value and records a remark if it was changed.
+fn apply<T: PartialEq>(annotated: &mut Annotated<T>, anonymized: Option<T>) {
+    if annotated.value() == anonymized.as_ref() {
+        return;
+    }
+
+    let ty = if anonymized.is_some() {
+        RemarkType::Masked
+    } else {
+        RemarkType::Removed
+    };
+
+    *annotated.value_mut() = anonymized;
+    annotated.meta_mut().add_remark(Remark::new(ty, RULE_ID));
+}
+
+/// Anonymizes the IP addresses of the client in an event.
+///
+/// This covers `user.ip_address`, `REMOTE_ADDR` in the request environment and the forwarding
+/// headers of the request.  Truncated addresses are recorded with a `Masked` remark, removed
+/// addresses with a `Removed` remark.  This must run after the geo lookup, which requires the
+/// full address.
+pub fn anonymize_event_ips(event: &mut Event, mode: IpAnonymization) {
+    if mode == IpAnonymization::Keep {
+        return;
+    }
+
+    if let Some(user) = event.user.value_mut() {
+        let anonymized = user
+            .ip_address
+            .value()
+            .and_then(|ip| anonymize_ip(ip.as_str(), mode))
+            .map(IpAddr);
+        apply(&mut user.ip_address, anonymized);
+    }
+
+    let request = match event.request.value_mut() {
+        Some(request) => request,
+        None => return,
+    };
+
+    if let Some(env) = request.env.value_mut() {
+        if let Some(remote_addr) = env.get_mut("REMOTE_ADDR") {
+            if let Some(Value::String(ip)) = remote_addr.value() {
+                let anonymized = anonymize_ip(ip, mode).map(Value::String);
+                apply(remote_addr, anonymized);
+            }
+        }
+    }
+
+    if let Some(headers) = request.headers.value_mut() {
+        for (name, value) in headers.0.iter_mut() {
+            if name.eq_ignore_ascii_case("Forwarded") {
+                let anonymized = value
+                    .value()
+                    .and_then(|header| anonymize_forwarded(header, mode));
+                apply(value, anonymized);
+            } else if IP_HEADERS.iter().any(|h| name.eq_ignore_ascii_case(h)) {
+                let anonymized = value.value().and_then(|list| anonymize_ip_list(list, mode));
+                apply(value, anonymized);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_anonymize_ip() {
+    assert_eq!(
+        anonymize_ip("192.168.17.42", IpAnonymization::Truncate),
+        Some("192.168.17.0".to_string())
+    );
+    assert_eq!(
+        anonymize_ip("2001:db8:cafe:17::1", IpAnonymization::Truncate),
+        Some("2001:db8:cafe::".to_string())
+    );
+    assert_eq!(anonymize_ip("{{auto}}", IpAnonymization::Truncate), None);
+    assert_eq!(anonymize_ip("192.168.17.42", IpAnonymization::Remove), None);
+    assert_eq!(
+        anonymize_ip_list("203.0.113.7, 10.0.0.2", IpAnonymization::Truncate),
+        Some("203.0.113.0, 10.0.0.0".to_string())
+    );
+}
+
+#[test]
+fn test_anonymize_forwarded() {
+    assert_eq!(
+        anonymize_forwarded(
+            "for=192.0.2.60;proto=http;by=203.0.113.43, for=\"[2001:db8:cafe::17]:4711\"",
+            IpAnonymization::Truncate
+        ),
+        Some("for=192.0.2.0;proto=http;by=203.0.113.43, for=\"[2001:db8:cafe::]\"".to_string())
+    );
+    assert_eq!(
+        anonymize_forwarded(
+            "For=\"192.0.2.60:8080\", for=_hidden",
+            IpAnonymization::Truncate
+        ),
+        Some("For=192.0.2.0".to_string())
+    );
+    assert_eq!(
+        anonymize_forwarded("for=192.0.2.60;proto=https", IpAnonymization::Remove),
+        Some("proto=https".to_string())
+    );
+    assert_eq!(
+        anonymize_forwarded("for=192.0.2.60, for=\"[::1]\"", IpAnonymization::Remove),
+        None
+    );
+}
+
+#[test]
+fn test_anonymize_event_ips() {
+    use crate::protocol::{Headers, Request, User};
+    use crate::types::Object;
+
+    let mut env = Object::new();
+    env.insert(
+        "REMOTE_ADDR".to_string(),
+        Annotated::new(Value::String("203.0.113.7".to_string())),
+    );
+
+    let mut headers = Headers(Object::new());
+    headers.0.insert(
+        "x-forwarded-for".to_string(),
+        Annotated::new("203.0.113.7, 10.0.0.2".to_string()),
+    );
+    headers.0.insert(
+        "Forwarded".to_string(),
+        Annotated::new("for=\"[2001:db8:cafe::17]:4711\";proto=https".to_string()),
+    );
+    headers
+        .0
+        .insert("Accept".to_string(), Annotated::new("*/*".to_string()));
+
+    let mut event = Event {
+        user: Annotated::new(User {
+            ip_address: Annotated::new(IpAddr("203.0.113.7".to_string())),
+            ..Default::default()
+        }),
+        request: Annotated::new(Request {
+            env: Annotated::new(env),
+            headers: Annotated::new(headers),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let mut removed = event.clone();
+
+    anonymize_event_ips(&mut event, IpAnonymization::Truncate);
+
+    let ip_address = &event.user.value().unwrap().ip_address;
+    assert_eq_str!(ip_address.value().unwrap().as_str(), "203.0.113.0");
+    let remark = ip_address.meta().iter_remarks().next().unwrap();
+    assert_eq_dbg!(remark.ty, RemarkType::Masked);
+
+    let request = event.request.value().unwrap();
+    assert_eq_dbg!(
+        request.env.value().unwrap()["REMOTE_ADDR"].value(),
+        Some(&Value::String("203.0.113.0".to_string()))
+    );
+    let headers = request.headers.value().unwrap();
+    assert_eq_str!(
+        headers["x-forwarded-for"].value().unwrap(),
+        "203.0.113.0, 10.0.0.0"
+    );
+    assert_eq_str!(
+        headers["Forwarded"].value().unwrap(),
+        "for=\"[2001:db8:cafe::]\";proto=https"
+    );
+    assert_eq_str!(headers["Accept"].value().unwrap(), "*/*");
+
+    anonymize_event_ips(&mut removed, IpAnonymization::Remove);
+
+    let ip_address = &removed.user.value().unwrap().ip_address;
+    assert_eq_dbg!(ip_address.value(), None);
+    let remark = ip_address.meta().iter_remarks().next().unwrap();
+    assert_eq_dbg!(remark.ty, RemarkType::Removed);
+    let request = removed.request.value().unwrap();
+    assert_eq_dbg!(
+        request.headers.value().unwrap()["x-forwarded-for"].value(),
+        None
+    );
+    assert_eq_str!(
+        request.headers.value().unwrap()["Forwarded"]
+            .value()
+            .unwrap(),
+        "proto=https"
+    );
+}
@@ -0,0 +1,725 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use failure::Fail;
+use lazy_static::lazy_static;
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::protocol::{
+    Addr, AppleDebugImage, Context, Contexts, DebugImage, DebugMeta, DeviceContext, Event,
+    Exception, Frame, Level, MachException, Mechanism, MechanismMeta, OsContext, PosixSignal,
+    RegVal, Stacktrace, SystemSdkInfo, Thread, ThreadId, Values,
+};
+use crate::store::mechanism::{
+    get_mach_exception_number, get_signal_number, normalize_mechanism_meta, OsHint,
+};
+use crate::store::stacktrace::process_non_raw_frame;
+use crate::types::{Annotated, Object};
+
+lazy_static! {
+    static ref HEADER_RE: Regex = Regex::new(r"^(?P<key>[A-Z][A-Za-z /]*?):\s*(?P<value>.*)$").unwrap();
+    static ref THREAD_NAME_RE: Regex = Regex::new(r"^Thread (?P<id>\d+) name:\s*(?P<name>.*)$").unwrap();
+    static ref THREAD_RE: Regex = Regex::new(r"^Thread (?P<id>\d+)(?P<crashed> Crashed)?:\s*$").unwrap();
+    static ref THREAD_STATE_RE: Regex =
+        Regex::new(r"^Thread (?P<id>\d+) crashed with .*Thread State.*:\s*$").unwrap();
+    static ref FRAME_RE: Regex = Regex::new(
+        r"^(?P<index>\d+)\s+(?P<image>\S.*?)\s+(?P<addr>0x[0-9a-fA-F]+)\s+(?P<symbol>.*?)\s*$"
+    ).unwrap();
+    static ref SYMBOL_RE: Regex = Regex::new(
+        r"^(?P<symbol>.+?) \+ (?P<offset>\d+)(?: \((?P<file>[^:()]+):(?P<line>\d+)\))?$"
+    ).unwrap();
+    static ref REGISTER_RE: Regex = Regex::new(r"(?P<name>\w+):\s*(?P<value>0x[0-9a-fA-F]+)").unwrap();
+    static ref IMAGE_RE: Regex = Regex::new(
+        r"(?x)
+        ^\s*(?P<start>0x[0-9a-fA-F]+)\s*-\s*(?P<end>0x[0-9a-fA-F]+)\s+
+        \+?(?P<name>\S+)\s+
+        (?:\([^)]*\)\s+)?
+        (?:(?P<arch>\w+)\s+)?
+        <(?P<uuid>[0-9a-fA-F-]+)>\s*
+        (?P<path>.*?)\s*$"
+    ).unwrap();
+    static ref EXCEPTION_TYPE_RE: Regex =
+        Regex::new(r"^(?P<mach>EXC_\w+)?\s*(?:\((?P<signal>SIG\w+)\))?").unwrap();
+    static ref HEX_RE: Regex = Regex::new(r"0x[0-9a-fA-F]+").unwrap();
+    static ref OS_VERSION_RE: Regex =
+        Regex::new(r"^(?P<name>.+?) (?P<version>\d+(?:\.\d+)*)(?: \((?P<build>[^)]+)\))?$").unwrap();
+}
+
+/// An error returned when parsing an Apple crash report fails.
+#[derive(Debug, Fail)]
+pub enum ParseAppleCrashReportError {
+    /// The crash report uses the JSON based format introduced with iOS 15.
+    #[fail(display = "unsupported crash report format")]
+    UnsupportedFormat,
+
+    /// The text neither contains an exception nor any threads.
+    #[fail(display = "no crash found in crash report")]
+    MissingCrash,
+}
+
+#[derive(Debug, Default)]
+struct ParsedThread {
+    id: u64,
+    name: Option<String>,
+    crashed: bool,
+    /// Frames in crash report order with the most recent call first.
+    frames: Vec<Frame>,
+    registers: Object<RegVal>,
+}
+
+#[derive(Debug, Default)]
+struct ParsedReport {
+    headers: BTreeMap<String, String>,
+    application_specific_information: Vec<String>,
+    threads: Vec<ParsedThread>,
+    images: Vec<AppleDebugImage>,
+}
+
+enum Section {
+    Header,
+    ApplicationSpecificInformation,
+    Thread,
+    ThreadState(u64),
+    BinaryImages,
+}
+
+fn parse_hex(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+fn parse_frame(captures: &regex::Captures) -> Frame {
+    let instruction_addr = parse_hex(&captures["addr"]);
+    let mut frame = Frame {
+        package: Annotated::new(captures["image"].to_string()),
+        instruction_addr: instruction_addr.map_or_else(Annotated::empty, |addr| {
+            Annotated::new(Addr(addr))
+        }),
+        ..Default::default()
+    };
+
+    if let Some(symbol) = SYMBOL_RE.captures(&captures["symbol"]) {
+        let offset: Option<u64> = symbol["offset"].parse().ok();
+        if symbol["symbol"].starts_with("0x") {
+            // Unsymbolicated frames are written as `image_addr + offset`.
+            if let Some(image_addr) = parse_hex(&symbol["symbol"]) {
+                frame.image_addr = Annotated::new(Addr(image_addr));
+            }
+        } else {
+            frame.function = Annotated::new(symbol["symbol"].to_string());
+            if let (Some(addr), Some(offset)) = (instruction_addr, offset) {
+                frame.symbol_addr = Annotated::new(Addr(addr.saturating_sub(offset)));
+            }
+        }
+
+        if let Some(file) = symbol.name("file") {
+            frame.filename = Annotated::new(file.as_str().to_string());
+        }
+        if let Some(line) = symbol.name("line").and_then(|line| line.as_str().parse().ok()) {
+            frame.line = Annotated::new(line);
+        }
+    }
+
+    frame
+}
+
+fn get_cpu_type(arch: &str) -> Option<(u64, u64)> {
+    Some(match arch {
+        "arm64" => (0x0100_000c, 0),
+        "arm64e" => (0x0100_000c, 2),
+        "armv7" => (12, 9),
+        "armv7s" => (12, 11),
+        "x86_64" => (0x0100_0007, 3),
+        "x86_64h" => (0x0100_0007, 8),
+        "i386" | "x86" => (7, 3),
+        _ => return None,
+    })
+}
+
+fn parse_image(captures: &regex::Captures, default_arch: Option<&str>) -> Option<AppleDebugImage> {
+    let start = parse_hex(&captures["start"])?;
+    let end = parse_hex(&captures["end"])?;
+    let uuid = Uuid::parse_str(&captures["uuid"]).ok()?;
+    let arch = captures
+        .name("arch")
+        .map(|arch| arch.as_str())
+        .or(default_arch);
+    let name = match &captures["path"] {
+        "" => &captures["name"],
+        path => path,
+    };
+
+    let mut image = AppleDebugImage {
+        name: Annotated::new(name.to_string()),
+        image_addr: Annotated::new(Addr(start)),
+        image_size: Annotated::new(end.saturating_sub(start) + 1),
+        uuid: Annotated::new(uuid),
+        ..Default::default()
+    };
+
+    if let Some(arch) = arch {
+        image.arch = Annotated::new(arch.to_string());
+        if let Some((cpu_type, cpu_subtype)) = get_cpu_type(arch) {
+            image.cpu_type = Annotated::new(cpu_type);
+            image.cpu_subtype = Annotated::new(cpu_subtype);
+        }
+    }
+
+    Some(image)
+}
+
+fn get_arch(code_type: &str) -> Option<&'static str> {
+    let code_type = code_type.split_whitespace().next().unwrap_or("");
+    Some(match code_type {
+        "ARM-64" => "arm64",
+        "ARM" => "armv7",
+        "X86-64" => "x86_64",
+        "X86" => "x86",
+        _ => return None,
+    })
+}
+
+fn normalize_os_name(name: &str) -> &str {
+    match name {
+        "iPhone OS" | "iOS" => "iOS",
+        "Mac OS X" | "macOS" | "OS X" => "macOS",
+        "Watch OS" | "watchOS" => "watchOS",
+        "Apple TVOS" | "tvOS" => "tvOS",
+        name => name,
+    }
+}
+
+fn parse_lines(text: &str) -> ParsedReport {
+    let mut report = ParsedReport::default();
+    let mut section = Section::Header;
+    let mut thread_names = BTreeMap::new();
+    let mut default_arch = None;
+
+    for line in text.lines() {
+        let line = line.trim_end();
+
+        if let Some(captures) = THREAD_NAME_RE.captures(line) {
+            if let Ok(id) = captures["id"].parse::<u64>() {
+                thread_names.insert(id, captures["name"].to_string());
+            }
+            continue;
+        }
+
+        if let Some(captures) = THREAD_RE.captures(line) {
+            let id = captures["id"].parse().unwrap_or(0);
+            report.threads.push(ParsedThread {
+                id,
+                name: thread_names.remove(&id),
+                crashed: captures.name("crashed").is_some(),
+                ..Default::default()
+            });
+            section = Section::Thread;
+            continue;
+        }
+
+        if let Some(captures) = THREAD_STATE_RE.captures(line) {
+            section = Section::ThreadState(captures["id"].parse().unwrap_or(0));
+            continue;
+        }
+
+        if line.starts_with("Binary Images:") {
+            default_arch = report
+                .headers
+                .get("Code Type")
+                .and_then(|code_type| get_arch(code_type));
+            section = Section::BinaryImages;
+            continue;
+        }
+
+        if line == "Application Specific Information:" {
+            section = Section::ApplicationSpecificInformation;
+            continue;
+        }
+
+        match section {
+            Section::Thread | Section::ThreadState(_) if line.is_empty() => {
+                section = Section::Header;
+            }
+            Section::Header => {
+                if let Some(captures) = HEADER_RE.captures(line) {
+                    report
+                        .headers
+                        .entry(captures["key"].to_string())
+                        .or_insert_with(|| captures["value"].trim().to_string());
+                }
+            }
+            Section::ApplicationSpecificInformation => {
+                if line.is_empty() {
+                    section = Section::Header;
+                } else {
+                    report
+                        .application_specific_information
+                        .push(line.to_string());
+                }
+            }
+            Section::Thread => {
+                if let (Some(captures), Some(thread)) =
+                    (FRAME_RE.captures(line), report.threads.last_mut())
+                {
+                    thread.frames.push(parse_frame(&captures));
+                }
+            }
+            Section::ThreadState(id) => {
+                if let Some(thread) = report.threads.iter_mut().find(|thread| thread.id == id) {
+                    for captures in REGISTER_RE.captures_iter(line) {
+                        if let Some(value) = parse_hex(&captures["value"]) {
+                            thread
+                                .registers
+                                .insert(captures["name"].to_string(), Annotated::new(RegVal(value)));
+                        }
+                    }
+                }
+            }
+            Section::BinaryImages => {
+                if let Some(image) = IMAGE_RE
+                    .captures(line)
+                    .and_then(|captures| parse_image(&captures, default_arch))
+                {
+                    report.images.push(image);
+                }
+            }
+        }
+    }
+
+    report
+}
+
+fn make_exception(report: &ParsedReport) -> Option<Exception> {
+    let exception_type = report.headers.get("Exception Type")?;
+    let captures = EXCEPTION_TYPE_RE.captures(exception_type)?;
+    let mach_name = captures.name("mach").map(|m| m.as_str());
+    let signal_name = captures.name("signal").map(|m| m.as_str());
+
+    let codes = report
+        .headers
+        .get("Exception Subtype")
+        .or_else(|| report.headers.get("Exception Codes"));
+
+    let mut meta = MechanismMeta::default();
+
+    if let Some(number) = mach_name.and_then(get_mach_exception_number) {
+        let codes = report
+            .headers
+            .get("Exception Codes")
+            .map(String::as_str)
+            .unwrap_or("");
+        let mut numbers = HEX_RE.find_iter(codes).filter_map(|m| parse_hex(m.as_str()));
+        let (code, subcode) = if codes.starts_with("KERN_") {
+            let code = match codes.split_whitespace().next() {
+                Some("KERN_INVALID_ADDRESS") => 1,
+                Some("KERN_PROTECTION_FAILURE") => 2,
+                Some("KERN_NO_SPACE") => 3,
+                Some("KERN_INVALID_ARGUMENT") => 4,
+                Some("KERN_FAILURE") => 5,
+                _ => 0,
+            };
+            (code, numbers.next().unwrap_or(0))
+        } else {
+            (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0))
+        };
+
+        meta.mach_exception = Annotated::new(MachException {
+            ty: Annotated::new(number),
+            code: Annotated::new(code),
+            subcode: Annotated::new(subcode),
+            ..Default::default()
+        });
+    }
+
+    if let Some(number) = signal_name.and_then(|name| get_signal_number(name, OsHint::Darwin)) {
+        meta.signal = Annotated::new(PosixSignal {
+            number: Annotated::new(number),
+            ..Default::default()
+        });
+    }
+
+    let mut mechanism = Mechanism {
+        ty: Annotated::new(if mach_name.is_some() { "mach" } else { "signal" }.to_string()),
+        handled: Annotated::new(false),
+        meta: Annotated::new(meta),
+        ..Default::default()
+    };
+    if !report.application_specific_information.is_empty() {
+        mechanism.description =
+            Annotated::new(report.application_specific_information.join("\n"));
+    }
+    normalize_mechanism_meta(&mut mechanism, Some(OsHint::Darwin));
+
+    let crashed_thread = report
+        .headers
+        .get("Triggered by Thread")
+        .or_else(|| report.headers.get("Crashed Thread"))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+        .or_else(|| {
+            report
+                .threads
+                .iter()
+                .find(|thread| thread.crashed)
+                .map(|thread| thread.id)
+        });
+
+    Some(Exception {
+        ty: Annotated::new(mach_name.or(signal_name).unwrap_or(exception_type).to_string()),
+        value: codes.map_or_else(Annotated::empty, |codes| Annotated::new(codes.clone().into())),
+        thread_id: crashed_thread.map_or_else(Annotated::empty, |id| {
+            Annotated::new(ThreadId::Int(id))
+        }),
+        mechanism: Annotated::new(mechanism),
+        ..Default::default()
+    })
+}
+
+fn make_thread(thread: ParsedThread, images: &[AppleDebugImage]) -> Thread {
+    let mut frames: Vec<_> = thread
+        .frames
+        .into_iter()
+        .rev()
+        .map(|mut frame| {
+            // Use the full image path as package if it is known.
+            let path = frame.package.value().and_then(|package| {
+                images
+                    .iter()
+                    .filter_map(|image| image.name.value())
+                    .find(|name| name.rsplit('/').next() == Some(package.as_str()))
+            });
+            if let Some(path) = path.cloned() {
+                frame.package = Annotated::new(path);
+            }
+            Annotated::new(frame)
+        }).collect();
+
+    for frame in &mut frames {
+        process_non_raw_frame(frame);
+    }
+
+    let stacktrace = if frames.is_empty() && thread.registers.is_empty() {
+        Annotated::empty()
+    } else {
+        Annotated::new(Stacktrace {
+            frames: Annotated::new(frames),
+            registers: if thread.registers.is_empty() {
+                Annotated::empty()
+            } else {
+                Annotated::new(thread.registers)
+            },
+            ..Default::default()
+        })
+    };
+
+    Thread {
+        id: Annotated::new(ThreadId::Int(thread.id)),
+        name: thread.name.map_or_else(Annotated::empty, Annotated::new),
+        crashed: Annotated::new(thread.crashed),
+        stacktrace,
+        ..Default::default()
+    }
+}
+
+fn make_contexts(report: &ParsedReport) -> (Contexts, Option<SystemSdkInfo>) {
+    let mut contexts = Object::new();
+    let mut system_sdk = None;
+
+    if let Some(os_version) = report.headers.get("OS Version") {
+        let mut os = OsContext {
+            raw_description: Annotated::new(os_version.clone()),
+            ..Default::default()
+        };
+
+        if let Some(captures) = OS_VERSION_RE.captures(os_version) {
+            let name = normalize_os_name(&captures["name"]).to_string();
+            let mut version = captures["version"]
+                .split('.')
+                .map(|part| part.parse().unwrap_or(0));
+
+            system_sdk = Some(SystemSdkInfo {
+                sdk_name: Annotated::new(name.clone()),
+                version_major: Annotated::new(version.next().unwrap_or(0)),
+                version_minor: Annotated::new(version.next().unwrap_or(0)),
+                version_patchlevel: Annotated::new(version.next().unwrap_or(0)),
+                ..Default::default()
+            });
+
+            os.name = Annotated::new(name);
+            os.version = Annotated::new(captures["version"].to_string());
+            if let Some(build) = captures.name("build") {
+                os.build = Annotated::new(build.as_str().to_string());
+            }
+        }
+
+        contexts.insert("os".to_string(), Annotated::new(Context::Os(Box::new(os))));
+    }
+
+    let model = report.headers.get("Hardware Model");
+    let arch = report
+        .headers
+        .get("Code Type")
+        .and_then(|code_type| get_arch(code_type));
+    if model.is_some() || arch.is_some() {
+        let device = DeviceContext {
+            model: model.map_or_else(Annotated::empty, |model| Annotated::new(model.clone())),
+            arch: arch.map_or_else(Annotated::empty, |arch| Annotated::new(arch.to_string())),
+            ..Default::default()
+        };
+        contexts.insert(
+            "device".to_string(),
+            Annotated::new(Context::Device(Box::new(device))),
+        );
+    }
+
+    (Contexts(contexts), system_sdk)
+}
+
+/// Parses the text of an Apple crash report into an event.
+///
+/// This supports the classic text format of `.crash` files as well as `.ips` files that prefix
+/// the same text with a single line JSON header.  The JSON based format of newer OS versions is
+/// rejected with `UnsupportedFormat`.
+///
+/// The event contains all threads with their backtraces, the registers of the crashed thread,
+/// the binary images as `AppleDebugImage`s and an exception with a `mach` mechanism carrying the
+/// mach exception and POSIX signal.  OS and device information is added to the contexts.
+pub fn parse_apple_crash_report(text: &str) -> Result<Event, ParseAppleCrashReportError> {
+    let mut text = text.trim_start();
+    if text.starts_with('{') {
+        // Skip the JSON header of `.ips` files.
+        text = text.split_once('\n').map_or("", |x| x.1).trim_start();
+        if text.starts_with('{') {
+            return Err(ParseAppleCrashReportError::UnsupportedFormat);
+        }
+    }
+
+    let mut report = parse_lines(text);
+    let exception = make_exception(&report);
+    if exception.is_none() && report.threads.is_empty() {
+        return Err(ParseAppleCrashReportError::MissingCrash);
+    }
+
+    let (contexts, system_sdk) = make_contexts(&report);
+    let timestamp = report
+        .headers
+        .get("Date/Time")
+        .and_then(|date| DateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S%.f %z").ok())
+        .map(|date| date.with_timezone(&Utc));
+
+    let images = std::mem::take(&mut report.images);
+    let threads = report
+        .threads
+        .into_iter()
+        .map(|thread| Annotated::new(make_thread(thread, &images)))
+        .collect();
+
+    Ok(Event {
+        platform: Annotated::new("cocoa".to_string()),
+        level: Annotated::new(Level::Fatal),
+        timestamp: timestamp.map_or_else(Annotated::empty, Annotated::new),
+        exceptions: exception.map_or_else(Annotated::empty, |exception| {
+            Annotated::new(Values::new(vec![Annotated::new(exception)]))
+        }),
+        threads: Annotated::new(Values::new(threads)),
+        contexts: Annotated::new(contexts),
+        debug_meta: Annotated::new(DebugMeta {
+            system_sdk: system_sdk.map_or_else(Annotated::empty, Annotated::new),
+            images: Annotated::new(
+                images
+                    .into_iter()
+                    .map(|image| Annotated::new(DebugImage::Apple(Box::new(image))))
+                    .collect(),
+            ),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+const IOS_CRASH_REPORT: &str = r#"Incident Identifier: 5C1D8E4A-1B6F-4A8E-9E5A-2B8F0E2A1C3D
+Hardware Model:      iPhone10,3
+Process:             MyApp [1234]
+Identifier:          com.example.MyApp
+Version:             1.0 (42)
+Code Type:           ARM-64 (Native)
+
+Date/Time:           2019-01-09 17:44:22.4355 +0100
+OS Version:          iPhone OS 12.1.2 (16C101)
+Report Version:      104
+
+Exception Type:  EXC_BAD_ACCESS (SIGSEGV)
+Exception Subtype: KERN_INVALID_ADDRESS at 0x0000000000000010
+Exception Codes: KERN_INVALID_ADDRESS at 0x0000000000000010
+Triggered by Thread:  0
+
+Application Specific Information:
+objc_msgSend() selector name: release
+
+Thread 0 name:  Dispatch queue: com.apple.main-thread
+Thread 0 Crashed:
+0   libobjc.A.dylib               	0x00000001b1c4bb9c 0x1b1c30000 + 113564
+1   MyApp                         	0x0000000100f6c2a0 main + 112 (main.m:10)
+
+Thread 1:
+0   libsystem_kernel.dylib        	0x00000001b1d0cb9c __workq_kernreturn + 8
+
+Thread 0 crashed with ARM Thread State (64-bit):
+    x0: 0x0000000000000000   x1: 0x00000001c0e6f4a8   x2: 0x0000000000000010
+    fp: 0x000000016ee2f7d0   lr: 0x0000000100f6c2a0
+    sp: 0x000000016ee2f7b0   pc: 0x00000001b1c4bb9c cpsr: 0x60000000
+
+Binary Images:
+0x100f64000 - 0x100f6ffff MyApp arm64  <6f0f7b0e7a3d3a5bbc7d8e0f2b3c4d5e> /var/containers/Bundle/Application/0000/MyApp.app/MyApp
+0x1b1c30000 - 0x1b1c5ffff libobjc.A.dylib arm64e  <a6a17b3b0e1e3a7c9a4e5f6a7b8c9d0e> /usr/lib/libobjc.A.dylib
+"#;
+
+#[test]
+fn test_parse_ios_crash_report() {
+    let event = parse_apple_crash_report(IOS_CRASH_REPORT).unwrap();
+
+    assert_eq_dbg!(event.platform.value(), Some(&"cocoa".to_string()));
+    assert_eq_dbg!(
+        event.timestamp.value().map(|date| date.to_rfc3339()),
+        Some("2019-01-09T16:44:22.435500+00:00".to_string())
+    );
+
+    let exception = event.exceptions.value().unwrap().values.value().unwrap()[0]
+        .value()
+        .unwrap();
+    assert_eq_dbg!(exception.ty.value(), Some(&"EXC_BAD_ACCESS".to_string()));
+    assert_eq_dbg!(exception.thread_id.value(), Some(&ThreadId::Int(0)));
+
+    let mechanism = exception.mechanism.value().unwrap();
+    assert_eq_dbg!(mechanism.ty.value(), Some(&"mach".to_string()));
+    assert_eq_dbg!(
+        mechanism.description.value(),
+        Some(&"objc_msgSend() selector name: release".to_string())
+    );
+    let meta = mechanism.meta.value().unwrap();
+    assert_eq_dbg!(
+        meta.mach_exception.value(),
+        Some(&MachException {
+            ty: Annotated::new(1),
+            code: Annotated::new(1),
+            subcode: Annotated::new(0x10),
+            name: Annotated::new("EXC_BAD_ACCESS".to_string()),
+        })
+    );
+    let signal = meta.signal.value().unwrap();
+    assert_eq_dbg!(signal.number.value(), Some(&11));
+    assert_eq_dbg!(signal.name.value(), Some(&"SIGSEGV".to_string()));
+
+    let threads = event.threads.value().unwrap().values.value().unwrap();
+    assert_eq!(threads.len(), 2);
+    let crashed = threads[0].value().unwrap();
+    assert_eq_dbg!(crashed.crashed.value(), Some(&true));
+    assert_eq_dbg!(
+        crashed.name.value(),
+        Some(&"Dispatch queue: com.apple.main-thread".to_string())
+    );
+
+    let stacktrace = crashed.stacktrace.value().unwrap();
+    let registers = stacktrace.registers.value().unwrap();
+    assert_eq!(registers.len(), 8);
+    assert_eq_dbg!(
+        registers.get("pc"),
+        Some(&Annotated::new(RegVal(0x1_b1c4_bb9c)))
+    );
+
+    let frames = stacktrace.frames.value().unwrap();
+    let main = frames[0].value().unwrap();
+    assert_eq_dbg!(main.function.value(), Some(&"main".to_string()));
+    assert_eq_dbg!(main.filename.value(), Some(&"main.m".to_string()));
+    assert_eq_dbg!(main.line.value(), Some(&10));
+    assert_eq_dbg!(main.symbol_addr.value(), Some(&Addr(0x1_00f6_c230)));
+    assert_eq_dbg!(
+        main.package.value(),
+        Some(&"/var/containers/Bundle/Application/0000/MyApp.app/MyApp".to_string())
+    );
+    let top = frames[1].value().unwrap();
+    assert_eq_dbg!(top.function.value(), None);
+    assert_eq_dbg!(top.image_addr.value(), Some(&Addr(0x1_b1c3_0000)));
+    assert_eq_dbg!(top.instruction_addr.value(), Some(&Addr(0x1_b1c4_bb9c)));
+
+    assert_eq_dbg!(threads[1].value().unwrap().crashed.value(), Some(&false));
+
+    let debug_meta = event.debug_meta.value().unwrap();
+    assert_eq_dbg!(
+        debug_meta.system_sdk.value().unwrap().sdk_name.value(),
+        Some(&"iOS".to_string())
+    );
+    let images = debug_meta.images.value().unwrap();
+    assert_eq!(images.len(), 2);
+    match images[1].value() {
+        Some(DebugImage::Apple(image)) => {
+            assert_eq_dbg!(image.name.value(), Some(&"/usr/lib/libobjc.A.dylib".to_string()));
+            assert_eq_dbg!(image.arch.value(), Some(&"arm64e".to_string()));
+            assert_eq_dbg!(image.cpu_subtype.value(), Some(&2));
+            assert_eq_dbg!(image.image_size.value(), Some(&0x30000));
+            assert_eq_dbg!(
+                image.uuid.value().map(|uuid| uuid.to_string()),
+                Some("a6a17b3b-0e1e-3a7c-9a4e-5f6a7b8c9d0e".to_string())
+            );
+        }
+        other => panic!("unexpected image {:?}", other),
+    }
+
+    match event.contexts.value().unwrap().get("os").and_then(|os| os.value()) {
+        Some(Context::Os(os)) => {
+            assert_eq_dbg!(os.name.value(), Some(&"iOS".to_string()));
+            assert_eq_dbg!(os.version.value(), Some(&"12.1.2".to_string()));
+            assert_eq_dbg!(os.build.value(), Some(&"16C101".to_string()));
+        }
+        other => panic!("unexpected context {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_macos_binary_image() {
+    let text = "Exception Type:        EXC_CRASH (SIGABRT)
+Exception Codes:       0x0000000000000000, 0x0000000000000000
+Code Type:             X86-64 (Native)
+
+Binary Images:
+       0x10e0c0000 -        0x10e0c3fff +com.example.app (1.0 - 1) <D9B7B6C1-5C8A-3A5E-9F3C-1E2A3B4C5D6E> /Applications/Example.app/Contents/MacOS/Example
+";
+
+    let event = parse_apple_crash_report(text).unwrap();
+    let images = event.debug_meta.value().unwrap().images.value().unwrap();
+    match images[0].value() {
+        Some(DebugImage::Apple(image)) => {
+            assert_eq_dbg!(
+                image.name.value(),
+                Some(&"/Applications/Example.app/Contents/MacOS/Example".to_string())
+            );
+            assert_eq_dbg!(image.arch.value(), Some(&"x86_64".to_string()));
+            assert_eq_dbg!(image.cpu_type.value(), Some(&0x0100_0007));
+        }
+        other => panic!("unexpected image {:?}", other),
+    }
+
+    let exception = event.exceptions.value().unwrap().values.value().unwrap()[0]
+        .value()
+        .unwrap();
+    let meta = exception.mechanism.value().unwrap().meta.value().unwrap();
+    assert_eq_dbg!(
+        meta.signal.value().unwrap().name.value(),
+        Some(&"SIGABRT".to_string())
+    );
+}
+
+#[test]
+fn test_parse_unsupported() {
+    let text = "{\"app_name\":\"MyApp\",\"bug_type\":\"309\"}\n{\n  \"uptime\" : 100\n}";
+    match parse_apple_crash_report(text) {
+        Err(ParseAppleCrashReportError::UnsupportedFormat) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match parse_apple_crash_report("hello world") {
+        Err(ParseAppleCrashReportError::MissingCrash) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
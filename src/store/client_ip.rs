@@ -0,0 +1,321 @@
+use std::fmt;
+use std::net;
+use std::str::FromStr;
+
+use failure::Fail;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use crate::protocol::Headers;
+
+/// A range of IP addresses in CIDR notation, such as `10.0.0.0/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    addr: net::IpAddr,
+    prefix: u8,
+}
+
+/// An error returned when parsing an IP network fails.
+#[derive(Debug, Fail)]
+#[fail(display = "invalid ip network")]
+pub struct ParseIpNetworkError;
+
+impl FromStr for IpNetwork {
+    type Err = ParseIpNetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.find('/') {
+            Some(index) => (&s[..index], Some(&s[index + 1..])),
+            None => (s, None),
+        };
+
+        let addr: net::IpAddr = addr.parse().map_err(|_| ParseIpNetworkError)?;
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse().map_err(|_| ParseIpNetworkError)?,
+            None => max_prefix,
+        };
+
+        if prefix > max_prefix {
+            return Err(ParseIpNetworkError);
+        }
+
+        Ok(IpNetwork { addr, prefix })
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl Serialize for IpNetwork {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNetwork {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        string.parse().map_err(de::Error::custom)
+    }
+}
+
+impl IpNetwork {
+    /// Returns the length of the network prefix in bits.
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Returns whether the address lies within this network.
+    ///
+    /// IPv4 addresses mapped into IPv6, such as `::ffff:10.0.0.1`, are compared as IPv4.
+    pub fn contains(&self, addr: net::IpAddr) -> bool {
+        let addr = match addr {
+            net::IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(addr, net::IpAddr::V4),
+            net::IpAddr::V4(_) => addr,
+        };
+
+        let (network, addr, bits) = match (self.addr, addr) {
+            (net::IpAddr::V4(network), net::IpAddr::V4(addr)) => (
+                u128::from(u32::from(network)),
+                u128::from(u32::from(addr)),
+                32,
+            ),
+            (net::IpAddr::V6(network), net::IpAddr::V6(addr)) => {
+                (u128::from(network), u128::from(addr), 128)
+            }
+            _ => return false,
+        };
+
+        let shift = bits - u32::from(self.prefix);
+        shift >= bits || network >> shift == addr >> shift
+    }
+}
+
+/// The source from which the client IP address of an event was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientIpSource {
+    /// The address of the peer that sent the request.
+    RemoteAddr,
+    /// The `Forwarded` header.
+    Forwarded,
+    /// The `X-Forwarded-For` header.
+    XForwardedFor,
+    /// The `X-Real-IP` header.
+    XRealIp,
+}
+
+impl fmt::Display for ClientIpSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ClientIpSource::RemoteAddr => write!(f, "remote-addr"),
+            ClientIpSource::Forwarded => write!(f, "forwarded"),
+            ClientIpSource::XForwardedFor => write!(f, "x-forwarded-for"),
+            ClientIpSource::XRealIp => write!(f, "x-real-ip"),
+        }
+    }
+}
+
+/// Parses an address from a forwarding header, which may be quoted, bracketed or have a port.
+pub(crate) fn parse_forwarded_ip(value: &str) -> Option<net::IpAddr> {
+    let value = value.trim().trim_matches('"');
+
+    if let Ok(addr) = value.parse() {
+        return Some(addr);
+    }
+
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest[..rest.find(']')?].parse().ok();
+    }
+
+    // An IPv4 address with a port.  IPv6 addresses with ports must be bracketed.
+    match value.rfind(':') {
+        Some(index) if value.matches(':').count() == 1 => value[..index].parse().ok(),
+        _ => None,
+    }
+}
+
+/// Returns the addresses of the `for` parameters in a `Forwarded` header.
+fn parse_forwarded_header(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.split_at(pair.find('=')?);
+                if key.trim().eq_ignore_ascii_case("for") {
+                    Some(&value[1..])
+                } else {
+                    None
+                }
+            })
+        })
+        .collect()
+}
+
+fn get_header<'a>(headers: &'a Headers, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))?
+        .1
+        .value()
+        .map(String::as_str)
+}
+
+/// Walks a proxy chain from the closest proxy to the client and returns the first address that
+/// is not a trusted proxy.
+///
+/// Returns `None` if the chain contains an invalid address before the client was found.  If all
+/// addresses are trusted, the address farthest from the server is returned.
+fn walk_chain(chain: &[&str], trusted_proxies: &[IpNetwork]) -> Option<net::IpAddr> {
+    let mut client = None;
+
+    for value in chain.iter().rev() {
+        let addr = parse_forwarded_ip(value)?;
+        client = Some(addr);
+        if !trusted_proxies.iter().any(|network| network.contains(addr)) {
+            break;
+        }
+    }
+
+    client
+}
+
+/// Derives the IP address of the client that sent an event.
+///
+/// `remote_addr` is the address of the peer that connected to the server.  Forwarding headers
+/// are only considered if this peer is a trusted proxy.  In this case, the `Forwarded`,
+/// `X-Forwarded-For` and `X-Real-IP` headers are checked in this order.  Address chains are
+/// walked from the end, skipping all trusted proxies.
+pub fn get_client_ip(
+    remote_addr: &str,
+    headers: Option<&Headers>,
+    trusted_proxies: &[IpNetwork],
+) -> (String, ClientIpSource) {
+    let fallback = (remote_addr.to_string(), ClientIpSource::RemoteAddr);
+
+    let is_trusted = match remote_addr.parse() {
+        Ok(addr) => trusted_proxies.iter().any(|network| network.contains(addr)),
+        Err(_) => false,
+    };
+
+    let headers = match headers {
+        Some(headers) if is_trusted => headers,
+        _ => return fallback,
+    };
+
+    if let Some(value) = get_header(headers, "Forwarded") {
+        let chain = parse_forwarded_header(value);
+        if let Some(addr) = walk_chain(&chain, trusted_proxies) {
+            return (addr.to_string(), ClientIpSource::Forwarded);
+        }
+    }
+
+    if let Some(value) = get_header(headers, "X-Forwarded-For") {
+        let chain: Vec<_> = value.split(',').collect();
+        if let Some(addr) = walk_chain(&chain, trusted_proxies) {
+            return (addr.to_string(), ClientIpSource::XForwardedFor);
+        }
+    }
+
+    if let Some(addr) = get_header(headers, "X-Real-IP").and_then(parse_forwarded_ip) {
+        return (addr.to_string(), ClientIpSource::XRealIp);
+    }
+
+    fallback
+}
+
+#[cfg(test)]
+fn make_headers(headers: &[(&str, &str)]) -> Headers {
+    use crate::types::Annotated;
+
+    Headers(
+        headers
+            .iter()
+            .map(|&(key, value)| (key.to_string(), Annotated::new(value.to_string())))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_ip_network() {
+    let network: IpNetwork = "10.0.0.0/8".parse().unwrap();
+    assert!(network.contains("10.1.2.3".parse().unwrap()));
+    assert!(!network.contains("11.0.0.1".parse().unwrap()));
+    assert!(!network.contains("::1".parse().unwrap()));
+
+    let network: IpNetwork = "2001:db8::/32".parse().unwrap();
+    assert!(network.contains("2001:db8:cafe::17".parse().unwrap()));
+    assert!(!network.contains("2001:db9::1".parse().unwrap()));
+
+    let network: IpNetwork = "192.168.0.1".parse().unwrap();
+    assert!(network.contains("192.168.0.1".parse().unwrap()));
+    assert!(!network.contains("192.168.0.2".parse().unwrap()));
+
+    let network: IpNetwork = "0.0.0.0/0".parse().unwrap();
+    assert!(network.contains("8.8.8.8".parse().unwrap()));
+
+    let network: IpNetwork = "10.0.0.0/8".parse().unwrap();
+    assert!(network.contains("::ffff:10.0.0.1".parse().unwrap()));
+    assert!(!network.contains("::ffff:11.0.0.1".parse().unwrap()));
+
+    assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+    assert!("example.com/8".parse::<IpNetwork>().is_err());
+}
+
+#[test]
+fn test_parse_forwarded() {
+    assert_eq!(
+        parse_forwarded_header(
+            r#"for=192.0.2.60;proto=http;by=203.0.113.43, For="[2001:db8:cafe::17]:4711""#
+        ),
+        vec!["192.0.2.60", r#""[2001:db8:cafe::17]:4711""#]
+    );
+    assert_eq!(
+        parse_forwarded_ip(r#""[2001:db8:cafe::17]:4711""#),
+        Some("2001:db8:cafe::17".parse().unwrap())
+    );
+    assert_eq!(
+        parse_forwarded_ip("192.0.2.60:8080"),
+        Some("192.0.2.60".parse().unwrap())
+    );
+    assert_eq!(parse_forwarded_ip("unknown"), None);
+}
+
+#[test]
+fn test_get_client_ip() {
+    let trusted: Vec<IpNetwork> = vec!["10.0.0.0/8".parse().unwrap()];
+
+    let headers = make_headers(&[("X-Forwarded-For", "203.0.113.7, 198.51.100.1, 10.0.0.2")]);
+    assert_eq!(
+        get_client_ip("10.0.0.1", Some(&headers), &trusted),
+        ("198.51.100.1".to_string(), ClientIpSource::XForwardedFor)
+    );
+
+    // Headers sent by untrusted peers could be spoofed.
+    assert_eq!(
+        get_client_ip("198.51.100.1", Some(&headers), &trusted),
+        ("198.51.100.1".to_string(), ClientIpSource::RemoteAddr)
+    );
+    assert_eq!(
+        get_client_ip("10.0.0.1", Some(&headers), &[]),
+        ("10.0.0.1".to_string(), ClientIpSource::RemoteAddr)
+    );
+
+    let headers = make_headers(&[
+        ("Forwarded", "for=192.0.2.60, for=10.0.0.3"),
+        ("X-Forwarded-For", "198.51.100.1"),
+    ]);
+    assert_eq!(
+        get_client_ip("10.0.0.1", Some(&headers), &trusted),
+        ("192.0.2.60".to_string(), ClientIpSource::Forwarded)
+    );
+
+    let headers = make_headers(&[("X-Forwarded-For", "garbage"), ("X-Real-IP", "192.0.2.1")]);
+    assert_eq!(
+        get_client_ip("10.0.0.1", Some(&headers), &trusted),
+        ("192.0.2.1".to_string(), ClientIpSource::XRealIp)
+    );
+}
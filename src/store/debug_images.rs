@@ -0,0 +1,332 @@
+use crate::processor::{ProcessValue, ProcessingState, Processor};
+use crate::protocol::{debug_id_from_code_id, Addr, DebugImage, DebugMeta, Event, Frame};
+use crate::types::Annotated;
+
+/// The address range of a single debug image.
+#[derive(Debug, Clone, PartialEq)]
+struct ImageRange {
+    start: u64,
+    end: u64,
+    name: Option<String>,
+}
+
+/// A sorted index of debug images for looking up images by address.
+///
+/// Proguard and unknown images do not carry address information and are skipped.  Images with a
+/// size of zero and images that overlap with an image at a lower address are not indexed, and an
+/// error is recorded for them instead.
+#[derive(Debug, Default)]
+pub struct ImageIndex {
+    ranges: Vec<ImageRange>,
+    /// Errors to attach to images, by their position in `DebugMeta.images`.
+    errors: Vec<(usize, String)>,
+}
+
+impl ImageIndex {
+    /// Builds an index from the list of debug images.
+    pub fn new(images: &[Annotated<DebugImage>]) -> Self {
+        let mut index = ImageIndex::default();
+        let mut candidates = vec![];
+
+        for (position, image) in images.iter().enumerate() {
+            let (image_addr, image_size, name) = match image.value() {
+                Some(DebugImage::Apple(image)) => {
+                    (&image.image_addr, &image.image_size, &image.name)
+                }
+                Some(DebugImage::Symbolic(image)) => {
+                    (&image.image_addr, &image.image_size, &image.name)
+                }
+                Some(DebugImage::Elf(image))
+                | Some(DebugImage::Pe(image))
+                | Some(DebugImage::Wasm(image)) => {
+                    (&image.image_addr, &image.image_size, &image.code_file)
+                }
+                _ => continue,
+            };
+
+            let (start, size) = match (image_addr.value(), image_size.value()) {
+                (Some(&Addr(start)), Some(&size)) => (start, size),
+                _ => continue,
+            };
+
+            if size == 0 {
+                let error = "image size must be greater than zero".to_string();
+                index.errors.push((position, error));
+                continue;
+            }
+
+            candidates.push((position, start, size, name.value().cloned()));
+        }
+
+        // The sort is stable, so images at the same address are resolved in list order.
+        candidates.sort_by_key(|&(_, start, _, _)| start);
+
+        let mut last: Option<(usize, u64)> = None;
+        for (position, start, size, name) in candidates {
+            if let Some((last_position, last_end)) = last {
+                if start < last_end {
+                    let error = format!("image overlaps with image {}", last_position);
+                    index.errors.push((position, error));
+                    continue;
+                }
+            }
+
+            let end = start.saturating_add(size);
+            index.ranges.push(ImageRange { start, end, name });
+            last = Some((position, end));
+        }
+
+        index
+    }
+
+    /// Returns whether the index contains no images.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the load address and name of the image containing the given address.
+    pub fn lookup(&self, addr: u64) -> Option<(Addr, Option<&str>)> {
+        let position = self.ranges.partition_point(|range| range.start <= addr);
+        let range = &self.ranges[position.checked_sub(1)?];
+        if addr < range.end {
+            Some((Addr(range.start), range.name.as_deref()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Fills identifiers of a debug image that can be derived from other fields.
+///
+/// ELF images sent with only a GNU build id in `code_id` receive the matching `debug_id`.
+pub fn normalize_debug_image(image: &mut DebugImage) {
+    if let DebugImage::Elf(ref mut image) = image {
+        if image.debug_id.value().is_none() && !image.debug_id.meta().has_errors() {
+            if let Some(debug_id) = image
+                .code_id
+                .value()
+                .and_then(|id| debug_id_from_code_id(id))
+            {
+                image.debug_id = Annotated::new(debug_id);
+            }
+        }
+    }
+}
+
+/// Processor that maps native frames to the debug images containing their instruction address.
+///
+/// For every frame with an `instruction_addr`, this fills `image_addr` and `package` from the
+/// containing image unless they have been sent by the client.  Invalid images are flagged with
+/// errors in `DebugMeta.images`.
+pub struct ImageMappingProcessor {
+    index: ImageIndex,
+}
+
+impl ImageMappingProcessor {
+    /// Creates a processor from the images in the given debug meta.
+    pub fn new(debug_meta: &DebugMeta) -> Self {
+        let images = debug_meta.images.value().map_or(&[][..], Vec::as_slice);
+        ImageMappingProcessor {
+            index: ImageIndex::new(images),
+        }
+    }
+
+    /// Creates a processor for an event, unless the event has no debug images.
+    pub fn from_event(event: &Annotated<Event>) -> Option<Self> {
+        let debug_meta = event.value()?.debug_meta.value()?;
+        let processor = Self::new(debug_meta);
+        if processor.index.is_empty() && processor.index.errors.is_empty() {
+            None
+        } else {
+            Some(processor)
+        }
+    }
+}
+
+impl Processor for ImageMappingProcessor {
+    fn process_frame(
+        &mut self,
+        frame: Annotated<Frame>,
+        state: ProcessingState,
+    ) -> Annotated<Frame> {
+        let mut frame = ProcessValue::process_child_values(frame, self, state);
+
+        if let Some(ref mut frame) = frame.0 {
+            let image = frame
+                .instruction_addr
+                .value()
+                .and_then(|addr| self.index.lookup(addr.0));
+
+            if let Some((image_addr, name)) = image {
+                if frame.image_addr.value().is_none() {
+                    frame.image_addr = Annotated::new(image_addr);
+                }
+
+                if let (None, Some(name)) = (frame.package.value(), name) {
+                    frame.package = Annotated::new(name.to_string());
+                }
+            }
+        }
+
+        frame
+    }
+
+    fn process_debug_meta(
+        &mut self,
+        mut debug_meta: Annotated<DebugMeta>,
+        _state: ProcessingState,
+    ) -> Annotated<DebugMeta> {
+        let images = debug_meta
+            .value_mut()
+            .as_mut()
+            .and_then(|debug_meta| debug_meta.images.value_mut().as_mut());
+
+        if let Some(images) = images {
+            for (position, error) in self.index.errors.drain(..) {
+                if let Some(image) = images.get_mut(position) {
+                    image.meta_mut().add_error(error, None);
+                }
+            }
+        }
+
+        debug_meta
+    }
+}
+
+#[cfg(test)]
+fn make_image(name: &str, image_addr: u64, image_size: u64) -> Annotated<DebugImage> {
+    use crate::protocol::SymbolicDebugImage;
+
+    Annotated::new(DebugImage::Symbolic(Box::new(SymbolicDebugImage {
+        name: Annotated::new(name.to_string()),
+        image_addr: Annotated::new(Addr(image_addr)),
+        image_size: Annotated::new(image_size),
+        id: Annotated::new("494f3aea-88fa-4296-9644-fa8ef5d139b6".parse().unwrap()),
+        ..Default::default()
+    })))
+}
+
+#[test]
+fn test_image_index_lookup() {
+    let index = ImageIndex::new(&[
+        make_image("libc.so", 0x7000, 0x1000),
+        make_image("app", 0x1000, 0x2000),
+    ]);
+
+    assert_eq!(index.lookup(0x0fff), None);
+    assert_eq!(index.lookup(0x1000), Some((Addr(0x1000), Some("app"))));
+    assert_eq!(index.lookup(0x2fff), Some((Addr(0x1000), Some("app"))));
+    assert_eq!(index.lookup(0x3000), None);
+    assert_eq!(index.lookup(0x7800), Some((Addr(0x7000), Some("libc.so"))));
+    assert_eq!(index.lookup(0x8000), None);
+}
+
+#[test]
+fn test_invalid_images() {
+    let mut debug_meta = Annotated::new(DebugMeta {
+        images: Annotated::new(vec![
+            make_image("app", 0x1000, 0x2000),
+            make_image("empty", 0x4000, 0),
+            make_image("overlapping", 0x2000, 0x1000),
+        ]),
+        ..Default::default()
+    });
+
+    let mut processor = ImageMappingProcessor::new(debug_meta.value().unwrap());
+    assert_eq!(
+        processor.index.lookup(0x2800),
+        Some((Addr(0x1000), Some("app")))
+    );
+
+    debug_meta = processor.process_debug_meta(debug_meta, ProcessingState::root());
+    let images = debug_meta.value().unwrap().images.value().unwrap();
+    assert!(images[0].is_valid());
+    assert_eq_dbg!(
+        images[1].meta().iter_errors().collect::<Vec<_>>(),
+        vec!["image size must be greater than zero"]
+    );
+    assert_eq_dbg!(
+        images[2].meta().iter_errors().collect::<Vec<_>>(),
+        vec!["image overlaps with image 0"]
+    );
+}
+
+#[test]
+fn test_map_frames_to_images() {
+    use crate::protocol::Stacktrace;
+
+    let frame = |instruction_addr: u64| {
+        Annotated::new(Frame {
+            instruction_addr: Annotated::new(Addr(instruction_addr)),
+            ..Default::default()
+        })
+    };
+
+    let event = Annotated::new(Event {
+        stacktrace: Annotated::new(Stacktrace {
+            frames: Annotated::new(vec![
+                frame(0x1010),
+                frame(0x9000),
+                Annotated::new(Frame {
+                    instruction_addr: Annotated::new(Addr(0x1020)),
+                    package: Annotated::new("custom".to_string()),
+                    ..Default::default()
+                }),
+            ]),
+            ..Default::default()
+        }),
+        debug_meta: Annotated::new(DebugMeta {
+            images: Annotated::new(vec![make_image("app", 0x1000, 0x2000)]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    let mut processor = ImageMappingProcessor::from_event(&event).unwrap();
+    let event = processor.process_event(event, ProcessingState::root());
+    let frames = event
+        .value()
+        .and_then(|event| event.stacktrace.value())
+        .and_then(|stacktrace| stacktrace.frames.value())
+        .unwrap();
+
+    let frame = frames[0].value().unwrap();
+    assert_eq_dbg!(frame.image_addr.value(), Some(&Addr(0x1000)));
+    assert_eq_dbg!(frame.package.value(), Some(&"app".to_string()));
+
+    let frame = frames[1].value().unwrap();
+    assert_eq_dbg!(frame.image_addr.value(), None);
+    assert_eq_dbg!(frame.package.value(), None);
+
+    let frame = frames[2].value().unwrap();
+    assert_eq_dbg!(frame.image_addr.value(), Some(&Addr(0x1000)));
+    assert_eq_dbg!(frame.package.value(), Some(&"custom".to_string()));
+}
+
+#[test]
+fn test_normalize_elf_debug_id() {
+    use crate::protocol::NativeDebugImage;
+
+    let mut image = DebugImage::Elf(Box::new(NativeDebugImage {
+        code_file: Annotated::new("/lib/libc.so.6".to_string()),
+        code_id: Annotated::new("dfb85de42daffd09640c8fe377d572de3e168920".to_string()),
+        image_addr: Annotated::new(Addr(0x1000)),
+        image_size: Annotated::new(0x1000),
+        ..Default::default()
+    }));
+
+    normalize_debug_image(&mut image);
+    match image {
+        DebugImage::Elf(ref image) => assert_eq_dbg!(
+            image.debug_id.value(),
+            Some(&"e45db8df-af2d-09fd-640c-8fe377d572de".parse().unwrap())
+        ),
+        _ => unreachable!(),
+    }
+
+    let index = ImageIndex::new(&[Annotated::new(image)]);
+    assert_eq!(
+        index.lookup(0x1800),
+        Some((Addr(0x1000), Some("/lib/libc.so.6")))
+    );
+}
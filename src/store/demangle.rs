@@ -0,0 +1,466 @@
+//! Demangling of native symbols.
+//!
+//! Rust symbols are demangled with `rustc-demangle`, C++ symbols with `cpp_demangle` for the
+//! Itanium ABI and with `msvc-demangler` for the MSVC ABI.  Swift symbols are demangled with the
+//! demangler of the Swift runtime that is bundled in `symbolic-demangle`.  Symbols that cannot be
+//! demangled are left untouched.
+//!
+//! Demangled C++ and Swift functions are printed with their argument list but without the return
+//! type, for instance `std::vector<int>::push_back(int const&)` or `Foo.bar()`.
+
+use cpp_demangle::DemangleOptions as CppDemangleOptions;
+use lazy_static::lazy_static;
+use msvc_demangler::DemangleFlags;
+use regex::Regex;
+use symbolic_common::{Language, Name, NameMangling};
+use symbolic_demangle::{Demangle, DemangleOptions as SwiftDemangleOptions};
+
+use crate::protocol::Frame;
+use crate::types::Annotated;
+
+lazy_static! {
+    /// The hash suffix of legacy Rust symbols: `17h0123456789abcdefE`.
+    static ref RUST_HASH_SYMBOL_RE: Regex = Regex::new(r"17h[0-9a-f]{16}E(?:\..*)?$").unwrap();
+    /// The hash suffix of a demangled legacy Rust symbol: `::h0123456789abcdef`.
+    static ref RUST_HASH_NAME_RE: Regex = Regex::new(r"::h[0-9a-f]{16}$").unwrap();
+}
+
+/// The maximum length of MSVC symbols that are demangled.
+///
+/// `msvc-demangler` recurses into nested types without a limit, so it could overflow the stack
+/// on long symbols.  Longer symbols are left mangled.
+const MAX_MSVC_SYMBOL_LEN: usize = 1024;
+
+/// The mangling scheme of a native symbol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManglingScheme {
+    /// Legacy or v0 Rust mangling.
+    Rust,
+    /// C++ mangling of the Itanium ABI used by GCC and Clang.
+    Itanium,
+    /// C++ mangling of the Microsoft Visual C++ compiler.
+    Msvc,
+    /// Swift 4 and 5 mangling.
+    Swift,
+}
+
+/// Detects the mangling scheme of a symbol from its prefix.
+///
+/// Ordinary names can share these prefixes, for instance `_Render`.  A symbol is only known to be
+/// mangled once it has been demangled successfully.
+pub fn detect_mangling_scheme(symbol: &str) -> Option<ManglingScheme> {
+    // macOS prefixes all symbols with an additional underscore.
+    let unprefixed = match symbol.strip_prefix('_') {
+        Some(rest) if rest.starts_with('_') || rest.starts_with('$') => rest,
+        _ => symbol,
+    };
+
+    if unprefixed.starts_with("_R")
+        || unprefixed.starts_with("_ZN") && RUST_HASH_SYMBOL_RE.is_match(unprefixed)
+    {
+        Some(ManglingScheme::Rust)
+    } else if unprefixed.starts_with("_Z") {
+        Some(ManglingScheme::Itanium)
+    } else if symbol.starts_with('?') {
+        Some(ManglingScheme::Msvc)
+    } else if unprefixed.starts_with("$s")
+        || unprefixed.starts_with("$S")
+        || unprefixed.starts_with("$e")
+        || unprefixed.starts_with("_T0")
+    {
+        Some(ManglingScheme::Swift)
+    } else {
+        None
+    }
+}
+
+/// Returns whether frames of the given platform carry native symbols.
+pub fn is_native_platform(platform: &str) -> bool {
+    matches!(platform, "native" | "cocoa" | "objc" | "swift" | "c")
+}
+
+/// Demangles a C++ type name encoded according to the Itanium C++ ABI.
+///
+/// This accepts the output of `typeid(T).name()` as well as type info names with a `_ZTS` prefix,
+/// for instance `St13runtime_error` or `N5boost10filesystem16filesystem_errorE`.  Returns `None`
+/// if the name is not a valid mangled type.
+pub fn demangle_cpp_type(mangled: &str) -> Option<String> {
+    let mangled = mangled.strip_prefix("_ZTS").unwrap_or(mangled);
+    let symbol = cpp_demangle::Symbol::new(format!("_ZTS{}", mangled)).ok()?;
+    let demangled = symbol.demangle(&CppDemangleOptions::new()).ok()?;
+    demangled
+        .strip_prefix("typeinfo name for ")
+        .map(str::to_string)
+}
+
+fn demangle_itanium(symbol: &str) -> Option<String> {
+    let symbol = cpp_demangle::Symbol::new(symbol).ok()?;
+    symbol
+        .demangle(&CppDemangleOptions::new().no_return_type())
+        .ok()
+}
+
+fn demangle_msvc(symbol: &str) -> Option<String> {
+    if symbol.len() > MAX_MSVC_SYMBOL_LEN {
+        return None;
+    }
+
+    let flags = DemangleFlags::NO_FUNCTION_RETURNS
+        | DemangleFlags::NO_ACCESS_SPECIFIERS
+        | DemangleFlags::NO_MEMBER_TYPE
+        | DemangleFlags::NO_MS_KEYWORDS
+        | DemangleFlags::NO_MS_THISTYPE
+        | DemangleFlags::SPACE_AFTER_COMMA
+        | DemangleFlags::HUG_TYPE;
+
+    msvc_demangler::demangle(symbol, flags).ok()
+}
+
+fn demangle_swift(symbol: &str, simplify: bool) -> Option<String> {
+    let name = Name::new(symbol, NameMangling::Mangled, Language::Swift);
+    let demangled = name.demangle(SwiftDemangleOptions::name_only().parameters(!simplify))?;
+
+    // The Swift demangler returns symbols it does not understand verbatim.
+    if demangled == symbol {
+        None
+    } else {
+        Some(demangled)
+    }
+}
+
+fn demangle_rust(symbol: &str) -> Option<String> {
+    let demangled = rustc_demangle::try_demangle(symbol).ok()?;
+    // The alternate format omits the hash of legacy symbols.
+    Some(format!("{:#}", demangled))
+}
+
+/// Strips template arguments, argument lists and qualifiers from a demangled C++ name.
+///
+/// For instance, `std::vector<int>::push_back(int const&)` becomes `std::vector::push_back`.
+/// Operators, anonymous namespaces and lambdas are preserved.
+pub fn simplify_cpp_name(name: &str) -> String {
+    let name = match name.find(" [clone ") {
+        Some(index) => &name[..index],
+        None => name,
+    };
+
+    let mut result = String::with_capacity(name.len());
+    let mut depth = 0usize;
+    let mut index = 0;
+
+    while let Some(c) = name[index..].chars().next() {
+        let rest = &name[index..];
+
+        if depth == 0 && result.ends_with("operator") {
+            let len = if rest.starts_with("()") || rest.starts_with("[]") {
+                2
+            } else {
+                rest.find(|c: char| !"<>=!+-*/%^&|~,".contains(c))
+                    .unwrap_or(rest.len())
+            };
+            if len > 0 {
+                result.push_str(&rest[..len]);
+                index += len;
+                continue;
+            }
+        }
+
+        if depth == 0 && rest.starts_with("(anonymous namespace)") {
+            result.push_str("(anonymous namespace)");
+            index += "(anonymous namespace)".len();
+            continue;
+        }
+
+        if depth == 0 && c == '{' {
+            let len = rest.find('}').map_or(rest.len(), |end| end + 1);
+            result.push_str(&rest[..len]);
+            index += len;
+            continue;
+        }
+
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 => result.push(c),
+            _ => {}
+        }
+        index += c.len_utf8();
+    }
+
+    let qualifiers = [" const", " volatile", " restrict", " &&", " &"];
+    while let Some(qualifier) = qualifiers.iter().find(|q| result.ends_with(*q)) {
+        let len = result.len() - qualifier.len();
+        result.truncate(len);
+    }
+
+    result.trim().to_string()
+}
+
+/// Demangles a native symbol.
+///
+/// If `simplify` is set, template arguments and argument lists are removed from C++ names and
+/// argument lists from Swift names.  Returns `None` if the symbol is not mangled or cannot be
+/// demangled.
+pub fn demangle_symbol(symbol: &str, simplify: bool) -> Option<String> {
+    let scheme = detect_mangling_scheme(symbol)?;
+    let demangled = match scheme {
+        ManglingScheme::Rust => demangle_rust(symbol),
+        ManglingScheme::Itanium => demangle_itanium(symbol),
+        ManglingScheme::Msvc => demangle_msvc(symbol),
+        ManglingScheme::Swift => demangle_swift(symbol, simplify),
+    }?;
+
+    match scheme {
+        ManglingScheme::Itanium | ManglingScheme::Msvc if simplify => {
+            Some(simplify_cpp_name(&demangled))
+        }
+        _ => Some(demangled),
+    }
+}
+
+/// Fills the function name of a native frame by demangling its symbol.
+///
+/// The symbol is demangled if the function is missing or equal to the symbol.  Function names sent
+/// without a symbol are demangled as well and moved to the symbol if this succeeds, so that the
+/// raw symbol is always retained.  Hashes of legacy Rust symbols are stripped from function names
+/// demangled by SDKs.
+pub fn demangle_frame(frame: &mut Frame, simplify: bool) {
+    let symbol = match (frame.function.value(), frame.symbol.value()) {
+        (None, Some(symbol)) => symbol.clone(),
+        (Some(function), Some(symbol)) if function == symbol => symbol.clone(),
+        (Some(function), None) if detect_mangling_scheme(function).is_some() => function.clone(),
+        _ => {
+            if let Some(function) = frame.function.value_mut().as_mut() {
+                if let Some(start) = RUST_HASH_NAME_RE.find(function).map(|m| m.start()) {
+                    function.truncate(start);
+                }
+            }
+            return;
+        }
+    };
+
+    if let Some(function) = demangle_symbol(&symbol, simplify) {
+        if frame.symbol.value().is_none() {
+            frame.symbol = Annotated::new(symbol);
+        }
+        frame.function = Annotated::new(function);
+    }
+}
+
+#[test]
+fn test_demangle_cpp_type() {
+    let cases = [
+        ("St13runtime_error", "std::runtime_error"),
+        ("_ZTSSt9bad_alloc", "std::bad_alloc"),
+        (
+            "N5boost10filesystem16filesystem_errorE",
+            "boost::filesystem::filesystem_error",
+        ),
+        ("NSt3__112system_errorE", "std::__1::system_error"),
+        ("N12_GLOBAL__N_15ErrorE", "(anonymous namespace)::Error"),
+        ("PKc", "char const*"),
+        ("i", "int"),
+        (
+            "St6vectorIiSaIiEE",
+            "std::vector<int, std::allocator<int> >",
+        ),
+        ("N3foo3BarIS_Lb1EEE", "foo::Bar<foo, true>"),
+        ("St13runtime_errorX", ""),
+        ("Z3foo", ""),
+    ];
+
+    for &(mangled, demangled) in &cases {
+        let expected = if demangled.is_empty() {
+            None
+        } else {
+            Some(demangled.to_string())
+        };
+        assert_eq!(demangle_cpp_type(mangled), expected, "{}", mangled);
+    }
+}
+
+#[test]
+fn test_detect_mangling_scheme() {
+    let cases = [
+        (
+            "_ZN4core9panicking5panic17h0123456789abcdefE",
+            Some(ManglingScheme::Rust),
+        ),
+        ("_RNvCs1234_7mycrate3foo", Some(ManglingScheme::Rust)),
+        ("_ZN3foo3barEv", Some(ManglingScheme::Itanium)),
+        ("__ZN3foo3barEv", Some(ManglingScheme::Itanium)),
+        ("?foo@@YAHH@Z", Some(ManglingScheme::Msvc)),
+        ("$s4main3FooV3baryyF", Some(ManglingScheme::Swift)),
+        ("_$s4main3FooV3baryyF", Some(ManglingScheme::Swift)),
+        ("main", None),
+    ];
+
+    for &(symbol, scheme) in &cases {
+        assert_eq!(detect_mangling_scheme(symbol), scheme, "{}", symbol);
+    }
+}
+
+#[test]
+fn test_demangle_symbol() {
+    let cases = [
+        // Rust
+        (
+            "_ZN4core9panicking5panic17h0123456789abcdefE",
+            "core::panicking::panic",
+        ),
+        ("_RNvCs1234_7mycrate3foo", "mycrate::foo"),
+        // Itanium
+        ("_ZN3foo3barEv", "foo::bar()"),
+        ("__ZN3foo3BarC2ERKS0_", "foo::Bar::Bar(foo::Bar const&)"),
+        ("_ZN3foo3BarD1Ev", "foo::Bar::~Bar()"),
+        ("_ZNK3foo3Bar3getEi", "foo::Bar::get(int) const"),
+        ("_Z3maxIiET_S0_S0_", "max<int>(int, int)"),
+        (
+            "_ZNSt6vectorIiSaIiEE9push_backERKi",
+            "std::vector<int, std::allocator<int> >::push_back(int const&)",
+        ),
+        (
+            "_ZN3fooplERKS_S1_",
+            "foo::operator+(foo const&, foo const&)",
+        ),
+        ("_Z8registerPFviEPv", "register(void (*)(int), void*)"),
+        (
+            "_ZZ4mainENKUlvE_clEv",
+            "main::{lambda()#1}::operator()() const",
+        ),
+        ("_ZZ4mainENK3$_0clEv", "main::$_0::operator()() const"),
+        (
+            "_ZN12_GLOBAL__N_13runEv.cold",
+            "(anonymous namespace)::run() [clone .cold]",
+        ),
+        ("_Z1fIRiEvOT_", "f<int&>(int&)"),
+        (
+            "_ZSt4moveIRiEONSt16remove_referenceIT_E4typeEOS2_",
+            "std::move<int&>(int&)",
+        ),
+        // MSVC
+        ("?foo@@YAHH@Z", "foo(int)"),
+        (
+            "?bar@Foo@ns@@QEBAXPEBD@Z",
+            "ns::Foo::bar(char const*) const",
+        ),
+        ("??0Foo@@QEAA@XZ", "Foo::Foo(void)"),
+        ("??1Foo@@UEAA@XZ", "Foo::~Foo(void)"),
+        (
+            "?push@?$Stack@H@@QEAAXAEBH@Z",
+            "Stack<int>::push(int const&)",
+        ),
+        ("?g_counter@@3HA", "g_counter"),
+        // Swift
+        ("$s4main3FooV3baryyF", "Foo.bar()"),
+        ("$s4main3FooVACycfC", "Foo.init()"),
+        ("$s4main4TestC5countSivg", "Test.count.getter"),
+        ("$s8MyModule0A5ClassC3runyyF", "MyClass.run()"),
+    ];
+
+    for &(symbol, demangled) in &cases {
+        assert_eq!(
+            demangle_symbol(symbol, false),
+            Some(demangled.to_string()),
+            "{}",
+            symbol
+        );
+    }
+
+    assert_eq!(
+        demangle_symbol("$s4main3FooV3baryyF", true),
+        Some("Foo.bar".to_string())
+    );
+
+    assert_eq!(demangle_symbol("_ZN3foo", false), None);
+    assert_eq!(demangle_symbol("_ZoomIn", false), None);
+    assert_eq!(demangle_symbol("_Render", false), None);
+    assert_eq!(demangle_symbol("$s4mainXX", false), None);
+    assert_eq!(demangle_symbol("main", false), None);
+}
+
+#[test]
+fn test_demangle_deeply_nested() {
+    let itanium = format!("_Z1fP{}i", "P".repeat(200_000));
+    assert_eq!(demangle_symbol(&itanium, false), None);
+    let msvc = format!("?f@@YAX{}H@Z", "PEA".repeat(100_000));
+    assert_eq!(demangle_symbol(&msvc, false), None);
+    assert_eq!(demangle_cpp_type(&format!("{}i", "P".repeat(50_000))), None);
+
+    let nested = format!("_Z1f{}iE", "N1a1bIN1cI".repeat(10_000));
+    assert_eq!(demangle_symbol(&nested, false), None);
+}
+
+#[test]
+fn test_simplify_cpp_name() {
+    let cases = [
+        (
+            "std::vector<int, std::allocator<int> >::push_back(int const&)",
+            "std::vector::push_back",
+        ),
+        ("foo::Bar::get(int) const", "foo::Bar::get"),
+        (
+            "foo::operator<<(std::ostream&, foo const&)",
+            "foo::operator<<",
+        ),
+        ("Foo::operator()(int)", "Foo::operator()"),
+        (
+            "main::{lambda()#1}::operator()() const",
+            "main::{lambda()#1}::operator()",
+        ),
+        (
+            "(anonymous namespace)::run() [clone .cold]",
+            "(anonymous namespace)::run",
+        ),
+    ];
+
+    for &(name, simplified) in &cases {
+        assert_eq!(simplify_cpp_name(name), simplified, "{}", name);
+    }
+
+    assert_eq!(
+        demangle_symbol("_ZNSt6vectorIiSaIiEE9push_backERKi", true),
+        Some("std::vector::push_back".to_string())
+    );
+}
+
+#[test]
+fn test_demangle_frame() {
+    let mut frame = Frame {
+        symbol: Annotated::new("_ZN3foo3barEv".to_string()),
+        ..Default::default()
+    };
+    demangle_frame(&mut frame, false);
+    assert_eq_dbg!(frame.function.value(), Some(&"foo::bar()".to_string()));
+    assert_eq_dbg!(frame.symbol.value(), Some(&"_ZN3foo3barEv".to_string()));
+
+    // Mangled function names are moved to the symbol.
+    let mut frame = Frame {
+        function: Annotated::new("_ZN3foo3barEv".to_string()),
+        ..Default::default()
+    };
+    demangle_frame(&mut frame, true);
+    assert_eq_dbg!(frame.function.value(), Some(&"foo::bar".to_string()));
+    assert_eq_dbg!(frame.symbol.value(), Some(&"_ZN3foo3barEv".to_string()));
+
+    // Function names that only look mangled are left alone.
+    let mut frame = Frame {
+        function: Annotated::new("_Render".to_string()),
+        ..Default::default()
+    };
+    demangle_frame(&mut frame, false);
+    assert_eq_dbg!(frame.function.value(), Some(&"_Render".to_string()));
+    assert_eq_dbg!(frame.symbol.value(), None);
+
+    // Existing function names are kept, but Rust hashes are stripped.
+    let mut frame = Frame {
+        function: Annotated::new("core::panicking::panic::h0123456789abcdef".to_string()),
+        symbol: Annotated::new("_ZN4core9panicking5panic17h0123456789abcdefE".to_string()),
+        ..Default::default()
+    };
+    demangle_frame(&mut frame, false);
+    assert_eq_dbg!(
+        frame.function.value(),
+        Some(&"core::panicking::panic".to_string())
+    );
+}
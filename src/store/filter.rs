@@ -0,0 +1,620 @@
+//! Inbound filters that drop events before normalization.
+//!
+//! Filters are configured per project with a [`FilterConfig`](struct.FilterConfig.html).  When
+//! an event matches a filter, [`should_filter`](fn.should_filter.html) returns the reason so that
+//! callers can count dropped events by outcome.
+use std::collections::BTreeSet;
+use std::fmt;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+use url::Url;
+
+use crate::protocol::{Event, Exception};
+
+lazy_static! {
+    static ref EXTENSION_MESSAGES_RE: Regex = Regex::new(
+        r#"(?ix)
+        # Random plugins and extensions
+        top\.GLOBALS|
+        originalCreateNotification|
+        canvas\.contentDocument|
+        MyApp_RemoveAllHighlights|
+        http://tt\.epicplay\.com|
+        Can't\ find\ variable:\ ZiteReader|
+        jigsaw\ is\ not\ defined|
+        ComboSearch\ is\ not\ defined|
+        http://loading\.retry\.widdit\.com/|
+        atomicFindClose|
+        # Facebook borked
+        fb_xd_fragment|
+        # ISP "optimizing" proxy
+        bmi_SafeAddOnload|
+        EBCallBackMessageReceived|
+        # See http://toolbar.conduit.com/Developer/HtmlAndGadget/Methods/JSInjection.aspx
+        conduitPage|
+        # Chrome on iOS and Safari extensions
+        __gCrWeb|
+        __firefox__|
+        # Generic error caused by cross-origin extension scripts
+        ^Script\ error\.?$
+        "#
+    )
+    .unwrap();
+    static ref EXTENSION_SOURCES_RE: Regex = Regex::new(
+        r#"(?ix)
+        # Browser extension schemes
+        ^(?:chrome|chrome-extension|moz-extension|safari-extension|safari-web-extension|ms-browser-extension|resource)://|
+        # Facebook flakiness
+        graph\.facebook\.com|
+        # Facebook blocked
+        connect\.facebook\.net|
+        # Woopra flakiness
+        eatdifferent\.com\.woopra-ns\.com|
+        static\.woopra\.com/js/woopra\.js|
+        # Other plugins
+        127\.0\.0\.1:4001/isrunning|
+        webappstoolbarba\.texthelp\.com/|
+        metrics\.itunes\.apple\.com\.edgesuite\.net/
+        "#
+    )
+    .unwrap();
+    static ref WEB_CRAWLERS_RE: Regex = Regex::new(
+        r#"(?ix)
+        Mediapartners-Google|
+        AdsBot-Google|
+        Googlebot|
+        FeedFetcher-Google|
+        BingBot|
+        BingPreview|
+        Baiduspider|
+        Slurp|
+        Sogou|
+        facebook|
+        ia_archiver|
+        bots?[/\s\);]|
+        spider[/\s\);]|
+        Slack|
+        Calypso\ AppCrawler|
+        pingdom|
+        lyticsbot|
+        AWS\ Security\ Scanner|
+        HubSpot\ Crawler
+        "#
+    )
+    .unwrap();
+    static ref OPERA_MINI_RE: Regex = Regex::new(r"Opera Mini/(?P<major>\d+)").unwrap();
+    static ref OPERA_RE: Regex =
+        Regex::new(r"(?:^Opera/.*Version/(?P<version>\d+)|^Opera/(?P<major>\d+)|OPR/(?P<opr>\d+))")
+            .unwrap();
+    static ref IE_RE: Regex =
+        Regex::new(r"(?:MSIE (?P<major>\d+)|Trident/\d+\.\d+;.*rv:(?P<rv>\d+))").unwrap();
+    static ref MODERN_BROWSER_RE: Regex =
+        Regex::new(r"(?:Chrome|CriOS|Firefox|FxiOS|Edge|Edg)/").unwrap();
+    static ref ANDROID_RE: Regex = Regex::new(r"Android (?P<major>\d+)").unwrap();
+    static ref SAFARI_RE: Regex =
+        Regex::new(r"Version/(?P<major>\d+)[\d.]* (?:Mobile/\S+ )?Safari/").unwrap();
+}
+
+/// The reason why an event was filtered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FilterReason {
+    /// The event originates from localhost.
+    Localhost,
+    /// The error is caused by a browser extension.
+    BrowserExtensions,
+    /// The event was sent by a legacy browser.
+    LegacyBrowsers,
+    /// The event was sent by a web crawler.
+    WebCrawlers,
+    /// The error message matches a configured pattern.
+    ErrorMessages,
+    /// The release matches a configured pattern.
+    Releases,
+}
+
+impl fmt::Display for FilterReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FilterReason::Localhost => write!(f, "localhost"),
+            FilterReason::BrowserExtensions => write!(f, "browser-extensions"),
+            FilterReason::LegacyBrowsers => write!(f, "legacy-browsers"),
+            FilterReason::WebCrawlers => write!(f, "web-crawlers"),
+            FilterReason::ErrorMessages => write!(f, "error-message"),
+            FilterReason::Releases => write!(f, "release-version"),
+        }
+    }
+}
+
+/// A group of legacy browsers that can be filtered.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum LegacyBrowser {
+    /// All groups except Internet Explorer 11.
+    Default,
+    /// Internet Explorer 8 and older.
+    IePre9,
+    /// Internet Explorer 9.
+    Ie9,
+    /// Internet Explorer 10.
+    Ie10,
+    /// Internet Explorer 11.
+    Ie11,
+    /// Opera 14 and older.
+    OperaPre15,
+    /// Opera Mini 7 and older.
+    OperaMiniPre8,
+    /// The stock browser of Android 3 and older.
+    AndroidPre4,
+    /// Safari 5 and older.
+    SafariPre6,
+}
+
+impl LegacyBrowser {
+    fn matches(self, browser: Browser, major: u32) -> bool {
+        match self {
+            LegacyBrowser::Default => [
+                LegacyBrowser::IePre9,
+                LegacyBrowser::Ie9,
+                LegacyBrowser::Ie10,
+                LegacyBrowser::OperaPre15,
+                LegacyBrowser::OperaMiniPre8,
+                LegacyBrowser::AndroidPre4,
+                LegacyBrowser::SafariPre6,
+            ]
+            .iter()
+            .any(|legacy| legacy.matches(browser, major)),
+            LegacyBrowser::IePre9 => browser == Browser::Ie && major < 9,
+            LegacyBrowser::Ie9 => browser == Browser::Ie && major == 9,
+            LegacyBrowser::Ie10 => browser == Browser::Ie && major == 10,
+            LegacyBrowser::Ie11 => browser == Browser::Ie && major == 11,
+            LegacyBrowser::OperaPre15 => browser == Browser::Opera && major < 15,
+            LegacyBrowser::OperaMiniPre8 => browser == Browser::OperaMini && major < 8,
+            LegacyBrowser::AndroidPre4 => browser == Browser::Android && major < 4,
+            LegacyBrowser::SafariPre6 => browser == Browser::Safari && major < 6,
+        }
+    }
+}
+
+/// The inbound filters of a project.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct FilterConfig {
+    /// Drops events from `localhost` and loopback addresses.
+    pub localhost: bool,
+    /// Drops errors caused by browser extensions.
+    pub browser_extensions: bool,
+    /// Drops events sent by web crawlers.
+    pub web_crawlers: bool,
+    /// Drops events sent by the given groups of legacy browsers.
+    pub legacy_browsers: BTreeSet<LegacyBrowser>,
+    /// Drops errors whose `type: value` matches one of these case-insensitive glob patterns.
+    pub error_messages: Vec<String>,
+    /// Drops events whose release matches one of these glob patterns.
+    pub releases: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Browser {
+    Ie,
+    Opera,
+    OperaMini,
+    Android,
+    Safari,
+}
+
+fn parse_major(captures: &regex::Captures, names: &[&str]) -> Option<u32> {
+    names
+        .iter()
+        .filter_map(|name| captures.name(name))
+        .next()?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+/// Detects the browsers relevant to legacy browser filters and their major version.
+fn detect_browser(user_agent: &str) -> Option<(Browser, u32)> {
+    if let Some(captures) = OPERA_MINI_RE.captures(user_agent) {
+        return Some((Browser::OperaMini, parse_major(&captures, &["major"])?));
+    }
+
+    if let Some(captures) = OPERA_RE.captures(user_agent) {
+        let major = parse_major(&captures, &["version", "major", "opr"])?;
+        return Some((Browser::Opera, major));
+    }
+
+    if let Some(captures) = IE_RE.captures(user_agent) {
+        return Some((Browser::Ie, parse_major(&captures, &["major", "rv"])?));
+    }
+
+    if MODERN_BROWSER_RE.is_match(user_agent) {
+        return None;
+    }
+
+    if let Some(captures) = ANDROID_RE.captures(user_agent) {
+        return Some((Browser::Android, parse_major(&captures, &["major"])?));
+    }
+
+    if let Some(captures) = SAFARI_RE.captures(user_agent) {
+        return Some((Browser::Safari, parse_major(&captures, &["major"])?));
+    }
+
+    None
+}
+
+/// Matches a glob pattern where `*` matches any sequence and `?` matches a single character.
+pub(crate) fn glob_match(pattern: &str, text: &str, case_insensitive: bool) -> bool {
+    let normalize = |s: &str| -> Vec<char> {
+        if case_insensitive {
+            s.to_lowercase().chars().collect()
+        } else {
+            s.chars().collect()
+        }
+    };
+
+    let pattern = normalize(pattern);
+    let text = normalize(text);
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn get_user_agent(event: &Event) -> Option<&str> {
+    let headers = event.request.value()?.headers.value()?;
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("user-agent"))?
+        .1
+        .value()
+        .map(String::as_str)
+}
+
+fn get_exceptions(event: &Event) -> impl Iterator<Item = &Exception> {
+    event
+        .exceptions
+        .value()
+        .and_then(|exceptions| exceptions.values.value())
+        .map_or(&[][..], Vec::as_slice)
+        .iter()
+        .filter_map(|exception| exception.value())
+}
+
+fn is_localhost(event: &Event) -> bool {
+    let ip_address = event.user.value().and_then(|user| user.ip_address.value());
+
+    if let Some(ip_address) = ip_address {
+        if ip_address.as_str() == "127.0.0.1" || ip_address.as_str() == "::1" {
+            return true;
+        }
+    }
+
+    let url = event
+        .request
+        .value()
+        .and_then(|request| request.url.value())
+        .and_then(|url| Url::parse(url).ok());
+
+    if let Some(host) = url.as_ref().and_then(Url::host_str) {
+        return matches!(host, "localhost" | "127.0.0.1" | "[::1]");
+    }
+
+    false
+}
+
+fn is_browser_extension(event: &Event) -> bool {
+    for exception in get_exceptions(event) {
+        let value = exception.value.value().map(|value| value.as_str());
+        if value.is_some_and(|value| EXTENSION_MESSAGES_RE.is_match(value)) {
+            return true;
+        }
+
+        // The frame of the most recent call is the last frame.
+        let abs_path = exception
+            .stacktrace
+            .value()
+            .and_then(|stacktrace| stacktrace.frames.value())
+            .and_then(|frames| frames.last())
+            .and_then(|frame| frame.value())
+            .and_then(|frame| frame.abs_path.value());
+
+        if abs_path.is_some_and(|abs_path| EXTENSION_SOURCES_RE.is_match(abs_path)) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn is_legacy_browser(event: &Event, browsers: &BTreeSet<LegacyBrowser>) -> bool {
+    match get_user_agent(event).and_then(detect_browser) {
+        Some((browser, major)) => browsers.iter().any(|legacy| legacy.matches(browser, major)),
+        None => false,
+    }
+}
+
+fn is_web_crawler(event: &Event) -> bool {
+    get_user_agent(event).is_some_and(|user_agent| WEB_CRAWLERS_RE.is_match(user_agent))
+}
+
+fn matches_error_messages(event: &Event, patterns: &[String]) -> bool {
+    get_exceptions(event).any(|exception| {
+        let ty = exception.ty.value();
+        let value = exception.value.value().map(|value| value.as_str());
+        let message = match (ty, value) {
+            (Some(ty), Some(value)) => format!("{}: {}", ty, value),
+            (Some(ty), None) => ty.clone(),
+            (None, Some(value)) => value.to_string(),
+            (None, None) => return false,
+        };
+
+        patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, &message, true))
+    })
+}
+
+fn matches_releases(event: &Event, patterns: &[String]) -> bool {
+    event.release.value().is_some_and(|release| {
+        patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, release, false))
+    })
+}
+
+/// Checks whether an event should be dropped according to the inbound filters.
+///
+/// This is meant to run on the raw event before normalization.  Filters are checked in a fixed
+/// order and the first matching filter is returned as error.
+pub fn should_filter(event: &Event, config: &FilterConfig) -> Result<(), FilterReason> {
+    if config.localhost && is_localhost(event) {
+        return Err(FilterReason::Localhost);
+    }
+
+    if config.browser_extensions && is_browser_extension(event) {
+        return Err(FilterReason::BrowserExtensions);
+    }
+
+    if !config.legacy_browsers.is_empty() && is_legacy_browser(event, &config.legacy_browsers) {
+        return Err(FilterReason::LegacyBrowsers);
+    }
+
+    if config.web_crawlers && is_web_crawler(event) {
+        return Err(FilterReason::WebCrawlers);
+    }
+
+    if !config.error_messages.is_empty() && matches_error_messages(event, &config.error_messages) {
+        return Err(FilterReason::ErrorMessages);
+    }
+
+    if !config.releases.is_empty() && matches_releases(event, &config.releases) {
+        return Err(FilterReason::Releases);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+fn make_event_with_user_agent(user_agent: &str) -> Event {
+    use crate::protocol::{Headers, Request};
+    use crate::types::Annotated;
+
+    let mut headers = std::collections::BTreeMap::new();
+    headers.insert(
+        "User-Agent".to_string(),
+        Annotated::new(user_agent.to_string()),
+    );
+
+    Event {
+        request: Annotated::new(Request {
+            headers: Annotated::new(Headers(headers)),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+fn make_event_with_exception(ty: &str, value: &str, abs_path: &str) -> Event {
+    use crate::protocol::{Frame, JsonLenientString, Stacktrace, Values};
+    use crate::types::Annotated;
+
+    let exception = Exception {
+        ty: Annotated::new(ty.to_string()),
+        value: Annotated::new(JsonLenientString(value.to_string())),
+        stacktrace: Annotated::new(Stacktrace {
+            frames: Annotated::new(vec![Annotated::new(Frame {
+                abs_path: Annotated::new(abs_path.to_string()),
+                ..Default::default()
+            })]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    Event {
+        exceptions: Annotated::new(Values::new(vec![Annotated::new(exception)])),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("*", "", false));
+    assert!(glob_match("1.*", "1.0.2", false));
+    assert!(glob_match("*error*", "TypeError: foo", true));
+    assert!(glob_match("?.0", "1.0", false));
+    assert!(glob_match("a*b*c", "aXbYbZc", false));
+    assert!(!glob_match("1.*", "2.0", false));
+    assert!(!glob_match("?.0", "10.0", false));
+    assert!(!glob_match("Foo", "foo", false));
+}
+
+#[test]
+fn test_detect_browser() {
+    assert_eq!(
+        detect_browser("Mozilla/4.0 (compatible; MSIE 8.0; Windows NT 6.1; Trident/4.0)"),
+        Some((Browser::Ie, 8))
+    );
+    assert_eq!(
+        detect_browser("Mozilla/5.0 (Windows NT 6.3; Trident/7.0; rv:11.0) like Gecko"),
+        Some((Browser::Ie, 11))
+    );
+    assert_eq!(
+        detect_browser("Opera/9.80 (Windows NT 6.1; U; en) Presto/2.10.229 Version/11.62"),
+        Some((Browser::Opera, 11))
+    );
+    assert_eq!(
+        detect_browser("Opera/9.80 (J2ME/MIDP; Opera Mini/7.1.32052/29.3417; U; en) Presto/2.8.119 Version/11.10"),
+        Some((Browser::OperaMini, 7))
+    );
+    assert_eq!(
+        detect_browser("Mozilla/5.0 (Linux; U; Android 2.3.5; en-us; HTC Vision Build/GRI40) AppleWebKit/533.1 (KHTML, like Gecko) Version/4.0 Mobile Safari/533.1"),
+        Some((Browser::Android, 2))
+    );
+    assert_eq!(
+        detect_browser("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_6_8) AppleWebKit/534.59.10 (KHTML, like Gecko) Version/5.1.9 Safari/534.59.10"),
+        Some((Browser::Safari, 5))
+    );
+    assert_eq!(
+        detect_browser("Mozilla/5.0 (Linux; Android 9; Pixel 3) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/74.0.3729.136 Mobile Safari/537.36"),
+        None
+    );
+}
+
+#[test]
+fn test_filter_localhost() {
+    use crate::protocol::{IpAddr, Request, User};
+    use crate::types::Annotated;
+
+    let config = FilterConfig {
+        localhost: true,
+        ..Default::default()
+    };
+
+    let event = Event {
+        user: Annotated::new(User {
+            ip_address: Annotated::new(IpAddr("127.0.0.1".to_string())),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(should_filter(&event, &config), Err(FilterReason::Localhost));
+    assert_eq!(should_filter(&event, &FilterConfig::default()), Ok(()));
+
+    let event = Event {
+        request: Annotated::new(Request {
+            url: Annotated::new("http://localhost:8080/index.html".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(should_filter(&event, &config), Err(FilterReason::Localhost));
+
+    let event = Event {
+        request: Annotated::new(Request {
+            url: Annotated::new("https://example.com/".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(should_filter(&event, &config), Ok(()));
+}
+
+#[test]
+fn test_filter_browsers() {
+    let config = FilterConfig {
+        browser_extensions: true,
+        web_crawlers: true,
+        legacy_browsers: vec![LegacyBrowser::Default].into_iter().collect(),
+        ..Default::default()
+    };
+
+    let event = make_event_with_exception("Error", "Script error.", "https://example.com/app.js");
+    assert_eq!(
+        should_filter(&event, &config),
+        Err(FilterReason::BrowserExtensions)
+    );
+
+    let event = make_event_with_exception(
+        "TypeError",
+        "undefined is not a function",
+        "chrome-extension://abcdef/content.js",
+    );
+    assert_eq!(
+        should_filter(&event, &config),
+        Err(FilterReason::BrowserExtensions)
+    );
+
+    let event = make_event_with_user_agent("Mozilla/5.0 (compatible; MSIE 9.0; Windows NT 6.1)");
+    assert_eq!(
+        should_filter(&event, &config),
+        Err(FilterReason::LegacyBrowsers)
+    );
+
+    let event =
+        make_event_with_user_agent("Mozilla/5.0 (Windows NT 6.3; Trident/7.0; rv:11.0) like Gecko");
+    assert_eq!(should_filter(&event, &config), Ok(()));
+
+    let event = make_event_with_user_agent(
+        "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+    );
+    assert_eq!(
+        should_filter(&event, &config),
+        Err(FilterReason::WebCrawlers)
+    );
+}
+
+#[test]
+fn test_filter_patterns() {
+    use crate::types::Annotated;
+
+    let config = FilterConfig {
+        error_messages: vec!["*ConnectionError: timeout*".to_string()],
+        releases: vec!["1.0.*-beta".to_string()],
+        ..Default::default()
+    };
+
+    let event = make_event_with_exception(
+        "ConnectionError",
+        "Timeout after 30s",
+        "https://example.com/app.js",
+    );
+    assert_eq!(
+        should_filter(&event, &config),
+        Err(FilterReason::ErrorMessages)
+    );
+
+    let mut event = make_event_with_exception("ValueError", "bad", "https://example.com/app.js");
+    assert_eq!(should_filter(&event, &config), Ok(()));
+
+    event.release = Annotated::new("1.0.3-beta".to_string());
+    assert_eq!(should_filter(&event, &config), Err(FilterReason::Releases));
+
+    event.release = Annotated::new("1.0.3".to_string());
+    assert_eq!(should_filter(&event, &config), Ok(()));
+}
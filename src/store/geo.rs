@@ -1,49 +1,143 @@
 use std::fmt;
+use std::net;
+
+use failure::Fail;
 
 use crate::protocol::Geo;
+use crate::store::client_ip::IpNetwork;
 use crate::types::Annotated;
 
-pub struct GeoIpLookup(maxminddb::OwnedReader<'static>);
+/// Coordinates are rounded to this many decimal places, which is roughly 10 kilometers.
+const COORDINATE_PRECISION: i32 = 1;
+
+/// An error returned by a `GeoProvider`.
+#[derive(Debug, Fail)]
+#[fail(display = "geoip lookup failed: {}", _0)]
+pub struct GeoLookupError(String);
+
+impl From<maxminddb::MaxMindDBError> for GeoLookupError {
+    fn from(error: maxminddb::MaxMindDBError) -> Self {
+        // The `Display` implementation of the error omits the message.
+        GeoLookupError(match error {
+            maxminddb::MaxMindDBError::AddressNotFoundError(msg)
+            | maxminddb::MaxMindDBError::InvalidDatabaseError(msg)
+            | maxminddb::MaxMindDBError::IoError(msg)
+            | maxminddb::MaxMindDBError::MapError(msg)
+            | maxminddb::MaxMindDBError::DecodingError(msg) => msg,
+        })
+    }
+}
+
+/// Resolves IP addresses to geographical locations.
+pub trait GeoProvider {
+    /// Looks up the location of an IP address.
+    ///
+    /// Returns `Ok(None)` if the address is not known.
+    fn lookup(&self, ip_address: net::IpAddr) -> Result<Option<Geo>, GeoLookupError>;
+}
+
+/// Rounds the coordinates of a location, so that it does not identify a single address.
+pub fn reduce_precision(geo: &mut Geo) {
+    let factor = 10f64.powi(COORDINATE_PRECISION);
+    for coordinate in &mut [&mut geo.latitude, &mut geo.longitude] {
+        if let Some(value) = coordinate.value_mut() {
+            *value = (*value * factor).round() / factor;
+        }
+    }
+}
+
+fn get_name(names: &Option<std::collections::BTreeMap<String, String>>) -> Option<String> {
+    Some(names.as_ref()?.get("en")?.to_owned())
+}
+
+/// A `GeoProvider` backed by MaxMind GeoIP2 or GeoLite2 databases.
+pub struct GeoIpLookup {
+    city: maxminddb::OwnedReader<'static>,
+    asn: Option<maxminddb::OwnedReader<'static>>,
+}
 
 impl GeoIpLookup {
+    /// Opens a City database.
     pub fn open(path: &str) -> Result<Self, maxminddb::MaxMindDBError> {
-        Ok(GeoIpLookup(maxminddb::Reader::open(path)?))
+        Ok(GeoIpLookup {
+            city: maxminddb::Reader::open(path)?,
+            asn: None,
+        })
     }
 
-    pub fn lookup(&self, ip_address: &str) -> Result<Option<Geo>, maxminddb::MaxMindDBError> {
-        // XXX: Why do we parse the IP again after deserializing?
-        let ip_address = match ip_address.parse() {
-            Ok(x) => x,
-            Err(_) => return Ok(None),
-        };
+    /// Additionally opens an ASN database to resolve autonomous systems.
+    pub fn with_asn_database(mut self, path: &str) -> Result<Self, maxminddb::MaxMindDBError> {
+        self.asn = Some(maxminddb::Reader::open(path)?);
+        Ok(self)
+    }
+}
 
-        let city: maxminddb::geoip2::City = match self.0.lookup(ip_address) {
+impl GeoProvider for GeoIpLookup {
+    fn lookup(&self, ip_address: net::IpAddr) -> Result<Option<Geo>, GeoLookupError> {
+        let city: maxminddb::geoip2::City = match self.city.lookup(ip_address) {
             Ok(x) => x,
             Err(maxminddb::MaxMindDBError::AddressNotFoundError(_)) => return Ok(None),
-            Err(e) => return Err(e),
+            Err(e) => return Err(e.into()),
         };
 
-        Ok(Some(Geo {
-            country_code: Annotated(
-                city.country
-                    .as_ref()
-                    .and_then(|country| Some(country.iso_code.as_ref()?.to_string())),
-                Default::default(),
-            ),
+        let country_code = city
+            .country
+            .as_ref()
+            .and_then(|country| country.iso_code.clone());
+        let subdivision = city
+            .subdivisions
+            .as_ref()
+            .and_then(|subdivisions| subdivisions.first());
+        let location = city.location.as_ref();
+
+        let mut geo = Geo {
             city: Annotated(
-                city.city
-                    .as_ref()
-                    .and_then(|city| Some(city.names.as_ref()?.get("en")?.to_owned())),
+                city.city.as_ref().and_then(|city| get_name(&city.names)),
                 Default::default(),
             ),
             region: Annotated(
-                city.country
-                    .as_ref()
-                    .and_then(|country| Some(country.names.as_ref()?.get("en")?.to_owned())),
+                subdivision.and_then(|s| get_name(&s.names)),
+                Default::default(),
+            ),
+            subdivision: Annotated(
+                match (&country_code, subdivision) {
+                    (Some(country), Some(subdivision)) => subdivision
+                        .iso_code
+                        .as_ref()
+                        .map(|code| format!("{}-{}", country, code)),
+                    _ => None,
+                },
+                Default::default(),
+            ),
+            postal_code: Annotated(
+                city.postal.as_ref().and_then(|p| p.code.clone()),
+                Default::default(),
+            ),
+            latitude: Annotated(location.and_then(|l| l.latitude), Default::default()),
+            longitude: Annotated(location.and_then(|l| l.longitude), Default::default()),
+            timezone: Annotated(
+                location.and_then(|l| l.time_zone.clone()),
                 Default::default(),
             ),
+            country_code: Annotated(country_code, Default::default()),
             ..Default::default()
-        }))
+        };
+
+        if let Some(ref asn) = self.asn {
+            let isp: maxminddb::geoip2::Isp = match asn.lookup(ip_address) {
+                Ok(x) => x,
+                Err(maxminddb::MaxMindDBError::AddressNotFoundError(_)) => return Ok(Some(geo)),
+                Err(e) => return Err(e.into()),
+            };
+
+            geo.asn = Annotated(
+                isp.autonomous_system_number.map(u64::from),
+                Default::default(),
+            );
+            geo.organization = Annotated(isp.autonomous_system_organization, Default::default());
+        }
+
+        Ok(Some(geo))
     }
 }
 
@@ -52,3 +146,75 @@ impl fmt::Debug for GeoIpLookup {
         f.debug_struct("GeoIpLookup").finish()
     }
 }
+
+/// A `GeoProvider` that resolves addresses from a table of networks held in memory.
+///
+/// If multiple networks contain an address, the most specific one wins.
+#[derive(Debug, Default, Clone)]
+pub struct GeoTable {
+    entries: Vec<(IpNetwork, Geo)>,
+}
+
+impl GeoTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        GeoTable::default()
+    }
+
+    /// Adds the location of all addresses in a network.
+    pub fn insert(&mut self, network: IpNetwork, geo: Geo) {
+        self.entries.push((network, geo));
+    }
+}
+
+impl GeoProvider for GeoTable {
+    fn lookup(&self, ip_address: net::IpAddr) -> Result<Option<Geo>, GeoLookupError> {
+        Ok(self
+            .entries
+            .iter()
+            .filter(|(network, _)| network.contains(ip_address))
+            .max_by_key(|(network, _)| network.prefix())
+            .map(|(_, geo)| geo.clone()))
+    }
+}
+
+#[test]
+fn test_geo_table() {
+    let mut table = GeoTable::new();
+    table.insert(
+        "10.0.0.0/8".parse().unwrap(),
+        Geo {
+            country_code: Annotated::new("AT".to_string()),
+            ..Default::default()
+        },
+    );
+    table.insert(
+        "10.1.0.0/16".parse().unwrap(),
+        Geo {
+            country_code: Annotated::new("AT".to_string()),
+            city: Annotated::new("Vienna".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let geo = table.lookup("10.1.2.3".parse().unwrap()).unwrap().unwrap();
+    assert_eq_str!(geo.city.value().unwrap(), "Vienna");
+
+    let geo = table.lookup("10.2.3.4".parse().unwrap()).unwrap().unwrap();
+    assert_eq_dbg!(geo.city.value(), None);
+
+    assert_eq_dbg!(table.lookup("::1".parse().unwrap()).unwrap(), None);
+}
+
+#[test]
+fn test_reduce_precision() {
+    let mut geo = Geo {
+        latitude: Annotated::new(48.208_49),
+        longitude: Annotated::new(16.372_08),
+        ..Default::default()
+    };
+
+    reduce_precision(&mut geo);
+    assert_eq_dbg!(geo.latitude.value(), Some(&48.2));
+    assert_eq_dbg!(geo.longitude.value(), Some(&16.4));
+}
@@ -0,0 +1,276 @@
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::protocol::{Exception, Frame, Stacktrace};
+use crate::store::stacktrace::process_non_raw_frame;
+use crate::types::{Annotated, Meta, Value};
+
+lazy_static! {
+    /// V8 frames: `at fn (location)` or `at location`.
+    static ref V8_FRAME_RE: Regex =
+        Regex::new(r"^\s*at (?:(?P<function>.+?) \((?P<location>.*)\)|(?P<bare_location>.+?))\s*$")
+            .unwrap();
+    /// The innermost location of a V8 eval frame: `eval at fn (file:1:2), <anonymous>:1:1`.
+    static ref V8_EVAL_RE: Regex = Regex::new(r"\((?P<location>[^()]+?:\d+:\d+)\)").unwrap();
+    /// SpiderMonkey and JavaScriptCore frames: `fn@location`.
+    static ref GECKO_FRAME_RE: Regex =
+        Regex::new(r"^\s*(?P<function>[^@\s]*(?:\s[^@\s]+)*)@(?P<location>\S.*?)\s*$").unwrap();
+    /// SpiderMonkey eval frames: `file line 10 > eval:1:5`.
+    static ref GECKO_EVAL_RE: Regex =
+        Regex::new(r"^(?P<filename>\S+) line (?P<line>\d+)(?: > \w+ line \d+)* > (?:eval|Function)")
+            .unwrap();
+    /// A location with optional line and column numbers.
+    static ref LOCATION_RE: Regex =
+        Regex::new(r"^(?P<filename>.+?)(?::(?P<line>\d+))?(?::(?P<column>\d+))?$").unwrap();
+}
+
+/// Locations that are valid without line numbers.
+fn is_special_location(location: &str) -> bool {
+    location == "native" || location == "<anonymous>" || location == "[native code]"
+}
+
+fn has_line_number(location: &str) -> bool {
+    LOCATION_RE
+        .captures(location)
+        .and_then(|captures| captures.name("line"))
+        .is_some()
+}
+
+fn normalize_function(function: &str) -> Option<String> {
+    let function = function.trim();
+    let function = function.trim_start_matches("async ");
+
+    match function {
+        "" | "?" | "<anonymous>" => None,
+        function => Some(function.to_string()),
+    }
+}
+
+fn make_frame(function: Option<String>, location: &str) -> Frame {
+    let mut frame = Frame {
+        function: Annotated(function, Meta::default()),
+        ..Default::default()
+    };
+
+    if is_special_location(location) {
+        return frame;
+    }
+
+    if let Some(captures) = GECKO_EVAL_RE.captures(location) {
+        frame.abs_path = Annotated::new(captures["filename"].to_string());
+        frame.line = Annotated(captures["line"].parse().ok(), Meta::default());
+        return frame;
+    }
+
+    let location = match V8_EVAL_RE.captures(location) {
+        Some(ref captures) if location.starts_with("eval at ") => {
+            captures.name("location").map_or(location, |m| m.as_str())
+        }
+        _ => location,
+    };
+
+    if let Some(captures) = LOCATION_RE.captures(location) {
+        frame.abs_path = Annotated::new(captures["filename"].to_string());
+        frame.line = Annotated(
+            captures.name("line").and_then(|m| m.as_str().parse().ok()),
+            Meta::default(),
+        );
+        frame.column = Annotated(
+            captures.name("column").and_then(|m| m.as_str().parse().ok()),
+            Meta::default(),
+        );
+    }
+
+    frame
+}
+
+/// Parses a single line of a JavaScript stack trace.
+///
+/// Returns `None` if the line is not a frame (e.g. the error message).
+fn parse_frame(line: &str) -> Option<Frame> {
+    if let Some(captures) = V8_FRAME_RE.captures(line) {
+        if let Some(location) = captures.name("location") {
+            let function = normalize_function(&captures["function"]);
+            return Some(make_frame(function, location.as_str()));
+        }
+
+        let location = &captures["bare_location"];
+        if is_special_location(location) || has_line_number(location) {
+            return Some(make_frame(None, location));
+        }
+
+        return None;
+    }
+
+    if let Some(captures) = GECKO_FRAME_RE.captures(line) {
+        let location = &captures["location"];
+        if is_special_location(location)
+            || GECKO_EVAL_RE.is_match(location)
+            || has_line_number(location)
+        {
+            let function = normalize_function(&captures["function"]);
+            return Some(make_frame(function, location));
+        }
+    }
+
+    None
+}
+
+/// Parses a JavaScript `Error.stack` string into a stack trace.
+///
+/// Supports the V8 format (`at fn (file:line:col)`), the SpiderMonkey and JavaScriptCore format
+/// (`fn@file:line:col`) as well as eval and anonymous frames.  Lines that are not frames, such as
+/// the error message, are skipped.  The frames are returned in Sentry order with the most recent
+/// call last.  Returns `None` if no frames were found.
+pub fn parse_js_stacktrace(stack: &str) -> Option<Stacktrace> {
+    let mut frames: Vec<_> = stack
+        .lines()
+        .filter_map(parse_frame)
+        .map(Annotated::new)
+        .collect();
+
+    if frames.is_empty() {
+        return None;
+    }
+
+    frames.reverse();
+    for frame in &mut frames {
+        process_non_raw_frame(frame);
+    }
+
+    Some(Stacktrace {
+        frames: Annotated::new(frames),
+        ..Default::default()
+    })
+}
+
+/// Fills the stack trace of an exception from a raw JavaScript stack string.
+///
+/// The stack string is taken from the `stack` attribute or, if that is missing, from the
+/// exception value.  In the latter case, the value is reduced to the error message.  Exceptions
+/// that already have a stack trace are left untouched.
+pub fn process_js_stack(exception: &mut Exception) {
+    if exception.stacktrace.0.is_some() {
+        return;
+    }
+
+    if let Some(Annotated(Some(Value::String(ref stack)), _)) = exception.other.get("stack") {
+        if let Some(stacktrace) = parse_js_stacktrace(stack) {
+            exception.stacktrace = Annotated::new(stacktrace);
+            return;
+        }
+    }
+
+    let (message, stacktrace) = match exception.value.0 {
+        Some(ref value) if value.contains('\n') => {
+            let message = value
+                .lines()
+                .take_while(|line| parse_frame(line).is_none())
+                .join("\n");
+            (message.trim().to_string(), parse_js_stacktrace(value))
+        }
+        _ => return,
+    };
+
+    if let Some(stacktrace) = stacktrace {
+        exception.stacktrace = Annotated::new(stacktrace);
+        exception.value = if message.is_empty() {
+            Annotated::empty()
+        } else {
+            Annotated::new(message.into())
+        };
+    }
+}
+
+#[cfg(test)]
+fn frame_locations(stacktrace: &Stacktrace) -> Vec<(Option<&str>, Option<&str>, Option<u64>)> {
+    stacktrace
+        .frames
+        .value()
+        .unwrap()
+        .iter()
+        .map(|frame| {
+            let frame = frame.value().unwrap();
+            (
+                frame.function.value().map(String::as_str),
+                frame.filename.value().map(String::as_str),
+                frame.line.value().cloned(),
+            )
+        }).collect()
+}
+
+#[test]
+fn test_parse_v8() {
+    let stack = "TypeError: Cannot read property 'foo' of undefined
+    at Object.render (http://example.com/static/app.js:10:15)
+    at async loadData (webpack:///./src/data.js:3:7)
+    at http://example.com/static/vendor.js:1:200
+    at new Promise (<anonymous>)
+    at eval (eval at compile (http://example.com/static/app.js:50:3), <anonymous>:1:1)
+    at Array.forEach (native)";
+
+    let stacktrace = parse_js_stacktrace(stack).unwrap();
+    assert_eq_dbg!(
+        frame_locations(&stacktrace),
+        vec![
+            (Some("Array.forEach"), None, None),
+            (Some("eval"), Some("/static/app.js"), Some(50)),
+            (Some("new Promise"), None, None),
+            (None, Some("/static/vendor.js"), Some(1)),
+            (Some("loadData"), Some("webpack:///./src/data.js"), Some(3)),
+            (Some("Object.render"), Some("/static/app.js"), Some(10)),
+        ]
+    );
+
+    let frame = stacktrace.frames.value().unwrap()[5].value().unwrap();
+    assert_eq!(
+        frame.abs_path.value().map(String::as_str),
+        Some("http://example.com/static/app.js")
+    );
+    assert_eq!(frame.column.value(), Some(&15));
+}
+
+#[test]
+fn test_parse_gecko() {
+    let stack = "render@http://example.com/static/app.js:10:15
+loadData/<@http://example.com/static/data.js:3:7
+@http://example.com/static/vendor.js:1:200
+compile@http://example.com/static/app.js line 50 > eval:1:1
+forEach@[native code]";
+
+    let stacktrace = parse_js_stacktrace(stack).unwrap();
+    assert_eq_dbg!(
+        frame_locations(&stacktrace),
+        vec![
+            (Some("forEach"), None, None),
+            (Some("compile"), Some("/static/app.js"), Some(50)),
+            (None, Some("/static/vendor.js"), Some(1)),
+            (Some("loadData/<"), Some("/static/data.js"), Some(3)),
+            (Some("render"), Some("/static/app.js"), Some(10)),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_no_frames() {
+    assert_eq_dbg!(parse_js_stacktrace("Error: at least one failed"), None);
+    assert_eq_dbg!(parse_js_stacktrace("mail to foo@example.com failed"), None);
+}
+
+#[test]
+fn test_process_stack_in_value() {
+    let mut exception = Exception {
+        ty: Annotated::new("Error".to_string()),
+        value: Annotated::new("boom\n    at foo (http://example.com/app.js:1:2)".to_string().into()),
+        ..Default::default()
+    };
+
+    process_js_stack(&mut exception);
+
+    assert_eq!(exception.value.value().map(|value| value.as_str()), Some("boom"));
+    assert_eq_dbg!(
+        frame_locations(exception.stacktrace.value().unwrap()),
+        vec![(Some("foo"), Some("/app.js"), Some(1))]
+    );
+}
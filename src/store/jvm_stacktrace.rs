@@ -0,0 +1,351 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::protocol::{Exception, Frame, Stacktrace, ThreadId, Values};
+use crate::store::stacktrace::process_non_raw_frame;
+use crate::types::Annotated;
+
+lazy_static! {
+    /// The first line of an exception: `Exception in thread "main" a.b.C: message`.
+    static ref HEADER_RE: Regex = Regex::new(
+        r#"^(?:Exception in thread "(?P<thread>[^"]*)" )?(?P<ty>[a-zA-Z_$][\w$]*(?:\.[a-zA-Z_$][\w$]*)*)(?::\s?(?P<value>.*))?$"#
+    ).unwrap();
+    /// A frame: `at module/pkg.Class.method(File.java:123)`.
+    static ref FRAME_RE: Regex =
+        Regex::new(r"^at (?:[\w.-]*(?:@[^/\s]*)?/)*(?P<symbol>[^\s(]+)\((?P<location>[^)]*)\)")
+            .unwrap();
+    /// Frames omitted because they are shared with the enclosing trace: `... 5 more`.
+    static ref MORE_RE: Regex = Regex::new(r"^\.\.\. (?P<count>\d+) more").unwrap();
+}
+
+#[derive(Debug)]
+struct ParsedException {
+    indent: usize,
+    enclosing: Option<usize>,
+    exception: Exception,
+    message: Option<String>,
+    /// Frames in JVM order with the most recent call first.
+    frames: Vec<Frame>,
+}
+
+fn get_indent(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn split_qualified_name(name: &str) -> (Option<String>, String) {
+    match name.rfind('.') {
+        Some(index) => (
+            Some(name[..index].to_string()),
+            name[index + 1..].to_string(),
+        ),
+        None => (None, name.to_string()),
+    }
+}
+
+fn parse_header(line: &str, require_qualified: bool) -> Option<(Exception, Option<String>)> {
+    let captures = HEADER_RE.captures(line)?;
+    let thread = captures.name("thread");
+    let ty = &captures["ty"];
+    if require_qualified && thread.is_none() && !ty.contains('.') {
+        return None;
+    }
+
+    let (module, ty) = split_qualified_name(ty);
+    let exception = Exception {
+        ty: Annotated::new(ty),
+        module: module.map_or_else(Annotated::empty, Annotated::new),
+        thread_id: thread.map_or_else(Annotated::empty, |thread| {
+            Annotated::new(ThreadId::String(thread.as_str().to_string()))
+        }),
+        ..Default::default()
+    };
+
+    let message = captures
+        .name("value")
+        .map(|value| value.as_str().trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    Some((exception, message))
+}
+
+fn parse_frame(line: &str) -> Option<Frame> {
+    let captures = FRAME_RE.captures(line)?;
+    let (module, function) = split_qualified_name(&captures["symbol"]);
+
+    let mut frame = Frame {
+        module: module.map_or_else(Annotated::empty, Annotated::new),
+        function: Annotated::new(function),
+        ..Default::default()
+    };
+
+    match &captures["location"] {
+        "Native Method" | "Unknown Source" | "" => {}
+        location => {
+            let (filename, line) = match location.rfind(':') {
+                Some(index) => (&location[..index], location[index + 1..].parse().ok()),
+                None => (location, None),
+            };
+
+            if filename != "Unknown Source" {
+                frame.filename = Annotated::new(filename.to_string());
+            }
+            if let Some(line) = line {
+                frame.line = Annotated::new(line);
+            }
+        }
+    }
+
+    Some(frame)
+}
+
+fn into_exception(parsed: ParsedException) -> Exception {
+    let mut exception = parsed.exception;
+    if let Some(message) = parsed.message {
+        exception.value = Annotated::new(message.into());
+    }
+
+    if !parsed.frames.is_empty() {
+        let mut frames: Vec<_> = parsed.frames.into_iter().rev().map(Annotated::new).collect();
+        for frame in &mut frames {
+            process_non_raw_frame(frame);
+        }
+
+        exception.stacktrace = Annotated::new(Stacktrace {
+            frames: Annotated::new(frames),
+            ..Default::default()
+        });
+    }
+
+    exception
+}
+
+/// Parses JVM stack trace text into a list of exceptions.
+///
+/// This understands the output of `Throwable.printStackTrace` on Java and Kotlin, including
+/// `Caused by:` and `Suppressed:` chains, `Native Method` and `Unknown Source` locations and frames
+/// omitted with `... N more`, which are restored from the enclosing trace.  Text before the first
+/// exception is skipped and parsing stops at the next top-level `Exception in thread` line.
+///
+/// The exceptions are returned in Sentry order with the outermost exception last.  Returns `None`
+/// if the text does not contain an exception.
+pub fn parse_jvm_exceptions(text: &str) -> Option<Values<Exception>> {
+    let mut parsed: Vec<ParsedException> = vec![];
+
+    for line in text.lines() {
+        let indent = get_indent(line);
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let current = parsed.len().checked_sub(1);
+
+        if let Some(current) = current {
+            if let Some(frame) = parse_frame(line) {
+                parsed[current].frames.push(frame);
+                continue;
+            }
+
+            if let Some(captures) = MORE_RE.captures(line) {
+                let count: usize = captures["count"].parse().unwrap_or(0);
+                if let Some(enclosing) = parsed[current].enclosing {
+                    let frames = &parsed[enclosing].frames;
+                    let omitted = frames[frames.len().saturating_sub(count)..].to_vec();
+                    parsed[current].frames.extend(omitted);
+                }
+                continue;
+            }
+        }
+
+        let (header, enclosing) = if let Some(header) = line.strip_prefix("Caused by: ") {
+            let enclosing = parsed.iter().rposition(|parsed| parsed.indent <= indent);
+            (parse_header(header, false), enclosing)
+        } else if let Some(header) = line.strip_prefix("Suppressed: ") {
+            let enclosing = parsed.iter().rposition(|parsed| parsed.indent < indent);
+            (parse_header(header, false), enclosing)
+        } else if current.is_none() {
+            (parse_header(line, true), None)
+        } else if line.starts_with("Exception in thread ") {
+            break;
+        } else {
+            // Continuation of a multi-line message.
+            if let Some(current) = current.map(|current| &mut parsed[current]) {
+                if current.frames.is_empty() {
+                    let message = current.message.get_or_insert_with(String::new);
+                    if !message.is_empty() {
+                        message.push('\n');
+                    }
+                    message.push_str(line);
+                }
+            }
+            continue;
+        };
+
+        if let Some((exception, message)) = header {
+            parsed.push(ParsedException {
+                indent,
+                enclosing,
+                exception,
+                message,
+                frames: vec![],
+            });
+        }
+    }
+
+    if parsed.is_empty() {
+        return None;
+    }
+
+    let exceptions = parsed
+        .into_iter()
+        .rev()
+        .map(into_exception)
+        .map(Annotated::new)
+        .collect();
+
+    Some(Values::new(exceptions))
+}
+
+#[cfg(test)]
+fn frame_symbols(exception: &Exception) -> Vec<String> {
+    exception
+        .stacktrace
+        .value()
+        .and_then(|stacktrace| stacktrace.frames.value())
+        .map(|frames| {
+            frames
+                .iter()
+                .filter_map(Annotated::value)
+                .map(|frame| {
+                    format!(
+                        "{}.{} {}:{}",
+                        frame.module.value().map_or("?", String::as_str),
+                        frame.function.value().map_or("?", String::as_str),
+                        frame.filename.value().map_or("?", String::as_str),
+                        frame.line.value().map_or(0, |line| *line),
+                    )
+                }).collect()
+        }).unwrap_or_default()
+}
+
+#[test]
+fn test_parse_caused_by_chain() {
+    let text = r#"Exception in thread "main" java.lang.IllegalStateException: could not start
+	at com.example.App.start(App.java:20)
+	at com.example.App.main(App.java:5)
+Caused by: java.lang.NullPointerException
+	at com.example.Config.load(Config.java:12)
+	at java.base/jdk.internal.reflect.NativeMethodAccessorImpl.invoke0(Native Method)
+	at com.example.App$$Lambda$1/0x0000000800b8c840.run(Unknown Source)
+	at com.example.App.start(App.java:18)
+	... 1 more
+"#;
+
+    let values = parse_jvm_exceptions(text).unwrap();
+    let exceptions: Vec<_> = values
+        .values
+        .value()
+        .unwrap()
+        .iter()
+        .map(|exception| exception.value().unwrap())
+        .collect();
+    assert_eq!(exceptions.len(), 2);
+
+    let cause = exceptions[0];
+    assert_eq_dbg!(cause.ty.value(), Some(&"NullPointerException".to_string()));
+    assert_eq_dbg!(cause.module.value(), Some(&"java.lang".to_string()));
+    assert_eq_dbg!(cause.value.value(), None);
+    assert_eq_dbg!(cause.thread_id.value(), None);
+    assert_eq_dbg!(
+        frame_symbols(cause),
+        vec![
+            "com.example.App.main App.java:5",
+            "com.example.App.start App.java:18",
+            "com.example.App$$Lambda$1/0x0000000800b8c840.run ?:0",
+            "jdk.internal.reflect.NativeMethodAccessorImpl.invoke0 ?:0",
+            "com.example.Config.load Config.java:12",
+        ]
+    );
+
+    let outer = exceptions[1];
+    assert_eq_dbg!(outer.ty.value(), Some(&"IllegalStateException".to_string()));
+    assert_eq_dbg!(
+        outer.value.value().map(|value| value.as_str()),
+        Some("could not start")
+    );
+    assert_eq_dbg!(
+        outer.thread_id.value(),
+        Some(&ThreadId::String("main".to_string()))
+    );
+    assert_eq_dbg!(
+        frame_symbols(outer),
+        vec![
+            "com.example.App.main App.java:5",
+            "com.example.App.start App.java:20",
+        ]
+    );
+}
+
+#[test]
+fn test_parse_suppressed() {
+    let text = "java.io.IOException: write failed
+\tat com.example.Writer.write(Writer.kt:7)
+\tat com.example.Main.run(Main.kt:3)
+\tSuppressed: java.io.IOException: close failed
+\t\tat com.example.Writer.close(Writer.kt:11)
+\t\t... 1 more
+\tCaused by: java.net.SocketException: Broken pipe
+\t\t... 2 more
+";
+
+    let values = parse_jvm_exceptions(text).unwrap();
+    let exceptions: Vec<_> = values
+        .values
+        .value()
+        .unwrap()
+        .iter()
+        .map(|exception| exception.value().unwrap())
+        .collect();
+
+    assert_eq!(exceptions.len(), 3);
+    assert_eq_dbg!(exceptions[0].ty.value(), Some(&"SocketException".to_string()));
+    assert_eq_dbg!(
+        frame_symbols(exceptions[0]),
+        vec![
+            "com.example.Main.run Main.kt:3",
+            "com.example.Writer.close Writer.kt:11",
+        ]
+    );
+    assert_eq_dbg!(
+        frame_symbols(exceptions[1]),
+        vec![
+            "com.example.Main.run Main.kt:3",
+            "com.example.Writer.close Writer.kt:11",
+        ]
+    );
+    assert_eq_dbg!(
+        exceptions[2].value.value().map(|value| value.as_str()),
+        Some("write failed")
+    );
+}
+
+#[test]
+fn test_parse_multiline_message() {
+    let text = "Some log prefix
+com.example.ValidationException: first line
+second line
+\tat com.example.Validator.check(Validator.java:3)
+";
+
+    let values = parse_jvm_exceptions(text).unwrap();
+    let exception = values.values.value().unwrap()[0].value().unwrap();
+    assert_eq_dbg!(
+        exception.value.value().map(|value| value.as_str()),
+        Some("first line\nsecond line")
+    );
+    assert_eq!(parse_jvm_exceptions("nothing to see here"), None);
+}
@@ -1,9 +1,12 @@
 use crate::types::Annotated;
 
-use crate::protocol::{Context, Event, Mechanism};
+use crate::protocol::{Context, Event, Exception, Mechanism};
+use crate::store::demangle::demangle_cpp_type;
 
 #[cfg(test)]
-use crate::protocol::{CError, MachException, MechanismMeta, PosixSignal};
+use crate::protocol::{
+    CError, CppException, HResult, MachException, MechanismMeta, NtStatus, PosixSignal,
+};
 
 fn get_errno_name(errno: i64, os_hint: OsHint) -> Option<&'static str> {
     Some(match os_hint {
@@ -453,84 +456,152 @@ fn get_signal_name(signo: i64, os_hint: OsHint) -> Option<&'static str> {
     })
 }
 
-fn get_signal_code_name(signo: i64, codeno: i64) -> Option<&'static str> {
-    // Codes for Darwin `si_code`
-    Some(match signo {
-        // Codes for SIGILL
-        4 => match codeno {
-            0 => "ILL_NOOP",   // if only I knew...
-            1 => "ILL_ILLOPC", // [XSI] illegal opcode
-            2 => "ILL_ILLTRP", // [XSI] illegal trap
-            3 => "ILL_PRVOPC", // [XSI] privileged opcode
-            4 => "ILL_ILLOPN", // [XSI] illegal operand -NOTIMP
-            5 => "ILL_ILLADR", // [XSI] illegal addressing mode -NOTIMP
-            6 => "ILL_PRVREG", // [XSI] privileged register -NOTIMP
-            7 => "ILL_COPROC", // [XSI] coprocessor error -NOTIMP
-            8 => "ILL_BADSTK", // [XSI] internal stack error -NOTIMP
-            _ => return None,
-        },
-
-        // Codes for SIGFPE
-        8 => match codeno {
-            0 => "FPE_NOOP",   // if only I knew...
-            1 => "FPE_FLTDIV", // [XSI] floating point divide by zero
-            2 => "FPE_FLTOVF", // [XSI] floating point overflow
-            3 => "FPE_FLTUND", // [XSI] floating point underflow
-            4 => "FPE_FLTRES", // [XSI] floating point inexact result
-            5 => "FPE_FLTINV", // [XSI] invalid floating point operation
-            6 => "FPE_FLTSUB", // [XSI] subscript out of range -NOTIMP
-            7 => "FPE_INTDIV", // [XSI] integer divide by zero
-            8 => "FPE_INTOVF", // [XSI] integer overflow
-            _ => return None,
-        },
-
-        // Codes for SIGSEGV
-        11 => match codeno {
-            0 => "SEGV_NOOP",   // if only I knew...
-            1 => "SEGV_MAPERR", // [XSI] address not mapped to object
-            2 => "SEGV_ACCERR", // [XSI] invalid permission for mapped object
-            _ => return None,
-        },
-
-        // Codes for SIGBUS
-        10 => match codeno {
-            0 => "BUS_NOOP",   // if only I knew...
-            1 => "BUS_ADRALN", // [XSI] Invalid address alignment
-            2 => "BUS_ADRERR", // [XSI] Nonexistent physical address -NOTIMP
-            3 => "BUS_OBJERR", // [XSI] Object-specific HW error - NOTIMP
-            _ => return None,
-        },
-
-        // Codes for SIGTRAP
-        5 => match codeno {
-            1 => "TRAP_BRKPT", // [XSI] Process breakpoint -NOTIMP
-            2 => "TRAP_TRACE", // [XSI] Process trace trap -NOTIMP
-            _ => return None,
-        },
-
-        // Codes for SIGCHLD
-        20 => match codeno {
-            0 => "CLD_NOOP",      // if only I knew...
-            1 => "CLD_EXITED",    // [XSI] child has exited
-            2 => "CLD_KILLED",    // [XSI] terminated abnormally, no core file
-            3 => "CLD_DUMPED",    // [XSI] terminated abnormally, core file
-            4 => "CLD_TRAPPED",   // [XSI] traced child has trapped
-            5 => "CLD_STOPPED",   // [XSI] child has stopped
-            6 => "CLD_CONTINUED", // [XSI] stopped child has continued
+fn get_signal_code_name(signo: i64, codeno: i64, os_hint: OsHint) -> Option<&'static str> {
+    // Linux codes have been taken from <uapi/asm-generic/siginfo.h>
+    Some(match os_hint {
+        OsHint::Linux => match (signo, codeno) {
+            // Codes for all signals
+            (_, 0) => "SI_USER",      // sent by kill, sigsend, raise
+            (_, 0x80) => "SI_KERNEL", // sent by the kernel from somewhere
+            (_, -1) => "SI_QUEUE",    // sent by sigqueue
+            (_, -2) => "SI_TIMER",    // sent by timer expiration
+            (_, -3) => "SI_MESGQ",    // sent by real time mesq state change
+            (_, -4) => "SI_ASYNCIO",  // sent by AIO completion
+            (_, -5) => "SI_SIGIO",    // sent by queued SIGIO
+            (_, -6) => "SI_TKILL",    // sent by tkill system call
+            (_, -7) => "SI_DETHREAD", // sent by execve() killing subsidiary threads
+            (_, -60) => "SI_ASYNCNL", // sent by glibc async name lookup completion
+
+            // Codes for SIGILL
+            (4, 1) => "ILL_ILLOPC",   // illegal opcode
+            (4, 2) => "ILL_ILLOPN",   // illegal operand
+            (4, 3) => "ILL_ILLADR",   // illegal addressing mode
+            (4, 4) => "ILL_ILLTRP",   // illegal trap
+            (4, 5) => "ILL_PRVOPC",   // privileged opcode
+            (4, 6) => "ILL_PRVREG",   // privileged register
+            (4, 7) => "ILL_COPROC",   // coprocessor error
+            (4, 8) => "ILL_BADSTK",   // internal stack error
+            (4, 9) => "ILL_BADIADDR", // unimplemented instruction address
+
+            // Codes for SIGTRAP
+            (5, 1) => "TRAP_BRKPT",  // process breakpoint
+            (5, 2) => "TRAP_TRACE",  // process trace trap
+            (5, 3) => "TRAP_BRANCH", // process taken branch trap
+            (5, 4) => "TRAP_HWBKPT", // hardware breakpoint/watchpoint
+            (5, 5) => "TRAP_UNK",    // undiagnosed trap
+            (5, 6) => "TRAP_PERF",   // perf event with sigtrap=1
+
+            // Codes for SIGBUS
+            (7, 1) => "BUS_ADRALN",    // invalid address alignment
+            (7, 2) => "BUS_ADRERR",    // non-existent physical address
+            (7, 3) => "BUS_OBJERR",    // object specific hardware error
+            (7, 4) => "BUS_MCEERR_AR", // hardware memory error consumed on a machine check
+            (7, 5) => "BUS_MCEERR_AO", // hardware memory error detected in process but not consumed
+
+            // Codes for SIGFPE
+            (8, 1) => "FPE_INTDIV",    // integer divide by zero
+            (8, 2) => "FPE_INTOVF",    // integer overflow
+            (8, 3) => "FPE_FLTDIV",    // floating point divide by zero
+            (8, 4) => "FPE_FLTOVF",    // floating point overflow
+            (8, 5) => "FPE_FLTUND",    // floating point underflow
+            (8, 6) => "FPE_FLTRES",    // floating point inexact result
+            (8, 7) => "FPE_FLTINV",    // floating point invalid operation
+            (8, 8) => "FPE_FLTSUB",    // subscript out of range
+            (8, 14) => "FPE_FLTUNK",   // undiagnosed floating-point exception
+            (8, 15) => "FPE_CONDTRAP", // trap on condition
+
+            // Codes for SIGSEGV
+            (11, 1) => "SEGV_MAPERR",  // address not mapped to object
+            (11, 2) => "SEGV_ACCERR",  // invalid permissions for mapped object
+            (11, 3) => "SEGV_BNDERR",  // failed address bound checks
+            (11, 4) => "SEGV_PKUERR",  // failed protection key checks
+            (11, 5) => "SEGV_ACCADI",  // ADI not enabled for mapped object
+            (11, 6) => "SEGV_ADIDERR", // disrupting MCD error
+            (11, 7) => "SEGV_ADIPERR", // precise MCD exception
+            (11, 8) => "SEGV_MTEAERR", // asynchronous ARM MTE error
+            (11, 9) => "SEGV_MTESERR", // synchronous ARM MTE exception
             _ => return None,
         },
 
-        // Codes for SIGPOLL
-        7 => match codeno {
-            1 => "POLL_IN",  // [XSR] Data input available
-            2 => "POLL_OUT", // [XSR] Output buffers available
-            3 => "POLL_MSG", // [XSR] Input message available
-            4 => "POLL_ERR", // [XSR] I/O error
-            5 => "POLL_PRI", // [XSR] High priority input available
-            6 => "POLL_HUP", // [XSR] Device disconnected
+        // Codes for Darwin `si_code`
+        OsHint::Darwin => match signo {
+            // Codes for SIGILL
+            4 => match codeno {
+                0 => "ILL_NOOP",   // if only I knew...
+                1 => "ILL_ILLOPC", // [XSI] illegal opcode
+                2 => "ILL_ILLTRP", // [XSI] illegal trap
+                3 => "ILL_PRVOPC", // [XSI] privileged opcode
+                4 => "ILL_ILLOPN", // [XSI] illegal operand -NOTIMP
+                5 => "ILL_ILLADR", // [XSI] illegal addressing mode -NOTIMP
+                6 => "ILL_PRVREG", // [XSI] privileged register -NOTIMP
+                7 => "ILL_COPROC", // [XSI] coprocessor error -NOTIMP
+                8 => "ILL_BADSTK", // [XSI] internal stack error -NOTIMP
+                _ => return None,
+            },
+
+            // Codes for SIGFPE
+            8 => match codeno {
+                0 => "FPE_NOOP",   // if only I knew...
+                1 => "FPE_FLTDIV", // [XSI] floating point divide by zero
+                2 => "FPE_FLTOVF", // [XSI] floating point overflow
+                3 => "FPE_FLTUND", // [XSI] floating point underflow
+                4 => "FPE_FLTRES", // [XSI] floating point inexact result
+                5 => "FPE_FLTINV", // [XSI] invalid floating point operation
+                6 => "FPE_FLTSUB", // [XSI] subscript out of range -NOTIMP
+                7 => "FPE_INTDIV", // [XSI] integer divide by zero
+                8 => "FPE_INTOVF", // [XSI] integer overflow
+                _ => return None,
+            },
+
+            // Codes for SIGSEGV
+            11 => match codeno {
+                0 => "SEGV_NOOP",   // if only I knew...
+                1 => "SEGV_MAPERR", // [XSI] address not mapped to object
+                2 => "SEGV_ACCERR", // [XSI] invalid permission for mapped object
+                _ => return None,
+            },
+
+            // Codes for SIGBUS
+            10 => match codeno {
+                0 => "BUS_NOOP",   // if only I knew...
+                1 => "BUS_ADRALN", // [XSI] Invalid address alignment
+                2 => "BUS_ADRERR", // [XSI] Nonexistent physical address -NOTIMP
+                3 => "BUS_OBJERR", // [XSI] Object-specific HW error - NOTIMP
+                _ => return None,
+            },
+
+            // Codes for SIGTRAP
+            5 => match codeno {
+                1 => "TRAP_BRKPT", // [XSI] Process breakpoint -NOTIMP
+                2 => "TRAP_TRACE", // [XSI] Process trace trap -NOTIMP
+                _ => return None,
+            },
+
+            // Codes for SIGCHLD
+            20 => match codeno {
+                0 => "CLD_NOOP",      // if only I knew...
+                1 => "CLD_EXITED",    // [XSI] child has exited
+                2 => "CLD_KILLED",    // [XSI] terminated abnormally, no core file
+                3 => "CLD_DUMPED",    // [XSI] terminated abnormally, core file
+                4 => "CLD_TRAPPED",   // [XSI] traced child has trapped
+                5 => "CLD_STOPPED",   // [XSI] child has stopped
+                6 => "CLD_CONTINUED", // [XSI] stopped child has continued
+                _ => return None,
+            },
+
+            // Codes for SIGPOLL
+            7 => match codeno {
+                1 => "POLL_IN",  // [XSR] Data input available
+                2 => "POLL_OUT", // [XSR] Output buffers available
+                3 => "POLL_MSG", // [XSR] Input message available
+                4 => "POLL_ERR", // [XSR] I/O error
+                5 => "POLL_PRI", // [XSR] High priority input available
+                6 => "POLL_HUP", // [XSR] Device disconnected
+                _ => return None,
+            },
             _ => return None,
         },
-        _ => return None,
+        OsHint::Windows => return None,
     })
 }
 
@@ -554,6 +625,189 @@ fn get_mach_exception_name(number: i64) -> Option<&'static str> {
     })
 }
 
+fn get_ntstatus_name(number: u32) -> Option<&'static str> {
+    // Status codes have been taken from <ntstatus.h>
+    Some(match number {
+        0x0000_0000 => "STATUS_SUCCESS",
+        0x0000_0102 => "STATUS_TIMEOUT",
+        0x0000_0103 => "STATUS_PENDING",
+        0x4000_0015 => "STATUS_FATAL_APP_EXIT",
+        0x4000_001E => "STATUS_WX86_BREAKPOINT",
+        0x8000_0001 => "STATUS_GUARD_PAGE_VIOLATION",
+        0x8000_0002 => "STATUS_DATATYPE_MISALIGNMENT",
+        0x8000_0003 => "STATUS_BREAKPOINT",
+        0x8000_0004 => "STATUS_SINGLE_STEP",
+        0x8000_0005 => "STATUS_BUFFER_OVERFLOW",
+        0xC000_0001 => "STATUS_UNSUCCESSFUL",
+        0xC000_0002 => "STATUS_NOT_IMPLEMENTED",
+        0xC000_0005 => "STATUS_ACCESS_VIOLATION",
+        0xC000_0006 => "STATUS_IN_PAGE_ERROR",
+        0xC000_0008 => "STATUS_INVALID_HANDLE",
+        0xC000_000D => "STATUS_INVALID_PARAMETER",
+        0xC000_0017 => "STATUS_NO_MEMORY",
+        0xC000_001D => "STATUS_ILLEGAL_INSTRUCTION",
+        0xC000_0022 => "STATUS_ACCESS_DENIED",
+        0xC000_0025 => "STATUS_NONCONTINUABLE_EXCEPTION",
+        0xC000_0026 => "STATUS_INVALID_DISPOSITION",
+        0xC000_008C => "STATUS_ARRAY_BOUNDS_EXCEEDED",
+        0xC000_008D => "STATUS_FLOAT_DENORMAL_OPERAND",
+        0xC000_008E => "STATUS_FLOAT_DIVIDE_BY_ZERO",
+        0xC000_008F => "STATUS_FLOAT_INEXACT_RESULT",
+        0xC000_0090 => "STATUS_FLOAT_INVALID_OPERATION",
+        0xC000_0091 => "STATUS_FLOAT_OVERFLOW",
+        0xC000_0092 => "STATUS_FLOAT_STACK_CHECK",
+        0xC000_0093 => "STATUS_FLOAT_UNDERFLOW",
+        0xC000_0094 => "STATUS_INTEGER_DIVIDE_BY_ZERO",
+        0xC000_0095 => "STATUS_INTEGER_OVERFLOW",
+        0xC000_0096 => "STATUS_PRIVILEGED_INSTRUCTION",
+        0xC000_00FD => "STATUS_STACK_OVERFLOW",
+        0xC000_0135 => "STATUS_DLL_NOT_FOUND",
+        0xC000_0138 => "STATUS_ORDINAL_NOT_FOUND",
+        0xC000_0139 => "STATUS_ENTRYPOINT_NOT_FOUND",
+        0xC000_013A => "STATUS_CONTROL_C_EXIT",
+        0xC000_0142 => "STATUS_DLL_INIT_FAILED",
+        0xC000_0194 => "STATUS_POSSIBLE_DEADLOCK",
+        0xC000_02B4 => "STATUS_FLOAT_MULTIPLE_FAULTS",
+        0xC000_02B5 => "STATUS_FLOAT_MULTIPLE_TRAPS",
+        0xC000_02C5 => "STATUS_DATATYPE_MISALIGNMENT_ERROR",
+        0xC000_0374 => "STATUS_HEAP_CORRUPTION",
+        0xC000_0409 => "STATUS_STACK_BUFFER_OVERRUN",
+        0xC000_0417 => "STATUS_INVALID_CRUNTIME_PARAMETER",
+        0xC000_0420 => "STATUS_ASSERTION_FAILURE",
+        0xC000_0602 => "STATUS_FAIL_FAST_EXCEPTION",
+        0xC000_070A => "STATUS_THREADPOOL_HANDLE_EXCEPTION",
+        0xE043_4352 => "EXCEPTION_COMPLUS", // Managed .NET exception
+        0xE06D_7363 => "EXCEPTION_MSVC",    // C++ exception raised with `throw`
+        _ => return None,
+    })
+}
+
+fn get_exception_code_name(number: u32) -> Option<&'static str> {
+    // Exception codes that have an alias in <winbase.h>
+    Some(match number {
+        0x8000_0001 => "EXCEPTION_GUARD_PAGE",
+        0x8000_0002 => "EXCEPTION_DATATYPE_MISALIGNMENT",
+        0x8000_0003 => "EXCEPTION_BREAKPOINT",
+        0x8000_0004 => "EXCEPTION_SINGLE_STEP",
+        0xC000_0005 => "EXCEPTION_ACCESS_VIOLATION",
+        0xC000_0006 => "EXCEPTION_IN_PAGE_ERROR",
+        0xC000_0008 => "EXCEPTION_INVALID_HANDLE",
+        0xC000_001D => "EXCEPTION_ILLEGAL_INSTRUCTION",
+        0xC000_0025 => "EXCEPTION_NONCONTINUABLE_EXCEPTION",
+        0xC000_0026 => "EXCEPTION_INVALID_DISPOSITION",
+        0xC000_008C => "EXCEPTION_ARRAY_BOUNDS_EXCEEDED",
+        0xC000_008D => "EXCEPTION_FLT_DENORMAL_OPERAND",
+        0xC000_008E => "EXCEPTION_FLT_DIVIDE_BY_ZERO",
+        0xC000_008F => "EXCEPTION_FLT_INEXACT_RESULT",
+        0xC000_0090 => "EXCEPTION_FLT_INVALID_OPERATION",
+        0xC000_0091 => "EXCEPTION_FLT_OVERFLOW",
+        0xC000_0092 => "EXCEPTION_FLT_STACK_CHECK",
+        0xC000_0093 => "EXCEPTION_FLT_UNDERFLOW",
+        0xC000_0094 => "EXCEPTION_INT_DIVIDE_BY_ZERO",
+        0xC000_0095 => "EXCEPTION_INT_OVERFLOW",
+        0xC000_0096 => "EXCEPTION_PRIV_INSTRUCTION",
+        0xC000_00FD => "EXCEPTION_STACK_OVERFLOW",
+        0xC000_0194 => "EXCEPTION_POSSIBLE_DEADLOCK",
+        _ => return None,
+    })
+}
+
+fn get_hresult_name(number: u32) -> Option<&'static str> {
+    // Common COM error codes from <winerror.h>
+    Some(match number {
+        0x0000_0000 => "S_OK",
+        0x0000_0001 => "S_FALSE",
+        0x8000_000A => "E_PENDING",
+        0x8000_000B => "E_BOUNDS",
+        0x8000_000C => "E_CHANGED_STATE",
+        0x8000_000E => "E_ILLEGAL_METHOD_CALL",
+        0x8000_4001 => "E_NOTIMPL",
+        0x8000_4002 => "E_NOINTERFACE",
+        0x8000_4003 => "E_POINTER",
+        0x8000_4004 => "E_ABORT",
+        0x8000_4005 => "E_FAIL",
+        0x8000_FFFF => "E_UNEXPECTED",
+        0x8001_0108 => "RPC_E_DISCONNECTED",
+        0x8001_010E => "RPC_E_WRONG_THREAD",
+        0x8002_0003 => "DISP_E_MEMBERNOTFOUND",
+        0x8002_0005 => "DISP_E_TYPEMISMATCH",
+        0x8002_0009 => "DISP_E_EXCEPTION",
+        0x8004_0110 => "CLASS_E_NOAGGREGATION",
+        0x8004_0154 => "REGDB_E_CLASSNOTREG",
+        0x8004_01F0 => "CO_E_NOTINITIALIZED",
+        0x8007_0005 => "E_ACCESSDENIED",
+        0x8007_0006 => "E_HANDLE",
+        0x8007_000E => "E_OUTOFMEMORY",
+        0x8007_0057 => "E_INVALIDARG",
+        _ => return None,
+    })
+}
+
+fn get_win32_error_name(number: u32) -> Option<&'static str> {
+    // System error codes from <winerror.h>
+    Some(match number {
+        0 => "ERROR_SUCCESS",
+        1 => "ERROR_INVALID_FUNCTION",
+        2 => "ERROR_FILE_NOT_FOUND",
+        3 => "ERROR_PATH_NOT_FOUND",
+        4 => "ERROR_TOO_MANY_OPEN_FILES",
+        5 => "ERROR_ACCESS_DENIED",
+        6 => "ERROR_INVALID_HANDLE",
+        8 => "ERROR_NOT_ENOUGH_MEMORY",
+        14 => "ERROR_OUTOFMEMORY",
+        15 => "ERROR_INVALID_DRIVE",
+        21 => "ERROR_NOT_READY",
+        32 => "ERROR_SHARING_VIOLATION",
+        33 => "ERROR_LOCK_VIOLATION",
+        38 => "ERROR_HANDLE_EOF",
+        50 => "ERROR_NOT_SUPPORTED",
+        80 => "ERROR_FILE_EXISTS",
+        87 => "ERROR_INVALID_PARAMETER",
+        109 => "ERROR_BROKEN_PIPE",
+        112 => "ERROR_DISK_FULL",
+        122 => "ERROR_INSUFFICIENT_BUFFER",
+        126 => "ERROR_MOD_NOT_FOUND",
+        127 => "ERROR_PROC_NOT_FOUND",
+        183 => "ERROR_ALREADY_EXISTS",
+        203 => "ERROR_ENVVAR_NOT_FOUND",
+        234 => "ERROR_MORE_DATA",
+        259 => "ERROR_NO_MORE_ITEMS",
+        995 => "ERROR_OPERATION_ABORTED",
+        997 => "ERROR_IO_PENDING",
+        998 => "ERROR_NOACCESS",
+        1223 => "ERROR_CANCELLED",
+        1460 => "ERROR_TIMEOUT",
+        _ => return None,
+    })
+}
+
+/// Resolves the name of an HRESULT.
+///
+/// HRESULTs in `FACILITY_WIN32` wrap a system error code and are named after it, e.g.
+/// `HRESULT_FROM_WIN32(ERROR_FILE_NOT_FOUND)`.
+fn get_hresult_display_name(number: u32) -> Option<String> {
+    if let Some(name) = get_hresult_name(number) {
+        return Some(name.to_owned());
+    }
+
+    if number & 0xFFFF_0000 == 0x8007_0000 {
+        let name = get_win32_error_name(number & 0xFFFF)?;
+        return Some(format!("HRESULT_FROM_WIN32({})", name));
+    }
+
+    None
+}
+
+/// Resolves the number of a mach exception from its name (e.g. `EXC_BAD_ACCESS`).
+pub fn get_mach_exception_number(name: &str) -> Option<i64> {
+    (1..=13).find(|&number| get_mach_exception_name(number) == Some(name))
+}
+
+/// Resolves the number of a POSIX signal from its name (e.g. `SIGSEGV`).
+pub fn get_signal_number(name: &str, os_hint: OsHint) -> Option<i64> {
+    (1..=64).find(|&signo| get_signal_name(signo, os_hint) == Some(name))
+}
+
 /// Internal utility trait to indicate the OS.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum OsHint {
@@ -582,7 +836,7 @@ impl OsHint {
 
 fn normalize_sdk_name(name: &Option<String>) -> Option<OsHint> {
     if let Some(ref name) = name {
-        match &**name {
+        match &*name.to_lowercase() {
             "ios" | "watchos" | "tvos" | "macos" => Some(OsHint::Darwin),
             "linux" | "android" => Some(OsHint::Linux),
             "windows" => Some(OsHint::Windows),
@@ -613,9 +867,11 @@ pub fn normalize_mechanism_meta(mechanism: &mut Mechanism, os_hint: Option<OsHin
                         }
                     }
 
-                    if os_hint == OsHint::Darwin && signal.code_name.0.is_none() {
-                        if let Some(code_name) =
-                            signal.code.0.and_then(|x| get_signal_code_name(signo, x))
+                    if signal.code_name.0.is_none() {
+                        if let Some(code_name) = signal
+                            .code
+                            .0
+                            .and_then(|x| get_signal_code_name(signo, x, os_hint))
                         {
                             signal.code_name = Annotated::new(code_name.to_owned());
                         }
@@ -633,6 +889,63 @@ pub fn normalize_mechanism_meta(mechanism: &mut Mechanism, os_hint: Option<OsHin
                 }
             }
         }
+
+        // NTSTATUS and HRESULT codes are unique to Windows, so they do not need an OS hint.
+        if let Some(ref mut ntstatus) = meta.ntstatus.0 {
+            if let Some(number) = ntstatus.number.0 {
+                // Codes are 32-bit but may have been sent sign-extended.
+                let number = number as u32;
+                if ntstatus.name.0.is_none() {
+                    if let Some(name) = get_ntstatus_name(number) {
+                        ntstatus.name = Annotated::new(name.to_owned());
+                    }
+                }
+
+                if ntstatus.exception_name.0.is_none() {
+                    if let Some(name) = get_exception_code_name(number) {
+                        ntstatus.exception_name = Annotated::new(name.to_owned());
+                    }
+                }
+            }
+        }
+
+        if let Some(ref mut hresult) = meta.hresult.0 {
+            if let Some(number) = hresult.number.0 {
+                if hresult.name.0.is_none() {
+                    if let Some(name) = get_hresult_display_name(number as u32) {
+                        hresult.name = Annotated::new(name);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The maximum length of mangled C++ exception types that are demangled.
+const MAX_MANGLED_TYPE_LEN: usize = 256;
+
+/// Fills a missing exception type from the C++ exception in the mechanism meta.
+///
+/// The mangled type name is demangled if possible, otherwise it is used verbatim.  This runs
+/// after the name has been trimmed, and longer names are never demangled.
+pub fn normalize_exception_type(exception: &mut Exception) {
+    if exception.ty.0.is_some() {
+        return;
+    }
+
+    let name = exception
+        .mechanism
+        .value()
+        .and_then(|mechanism| mechanism.meta.value())
+        .and_then(|meta| meta.cpp_exception.value())
+        .and_then(|cpp_exception| cpp_exception.name.value());
+
+    if let Some(name) = name {
+        let ty = Some(name)
+            .filter(|name| name.len() <= MAX_MANGLED_TYPE_LEN)
+            .and_then(|name| demangle_cpp_type(name))
+            .unwrap_or_else(|| name.clone());
+        exception.ty = Annotated::new(ty);
     }
 }
 
@@ -943,3 +1256,192 @@ fn test_normalize_mach_fail() {
         }
     );
 }
+
+#[test]
+fn test_reverse_lookups() {
+    assert_eq!(get_mach_exception_number("EXC_BAD_ACCESS"), Some(1));
+    assert_eq!(get_mach_exception_number("EXC_NONSENSE"), None);
+    assert_eq!(get_signal_number("SIGSEGV", OsHint::Darwin), Some(11));
+    assert_eq!(get_signal_number("SIGBUS", OsHint::Linux), Some(7));
+    assert_eq!(get_signal_number("SIGNONSENSE", OsHint::Darwin), None);
+}
+
+#[test]
+fn test_os_hint_case_insensitive() {
+    assert_eq!(
+        normalize_sdk_name(&Some("iOS".to_string())),
+        Some(OsHint::Darwin)
+    );
+    assert_eq!(
+        normalize_sdk_name(&Some("Windows".to_string())),
+        Some(OsHint::Windows)
+    );
+}
+
+#[test]
+fn test_normalize_ntstatus() {
+    let mut mechanism = Mechanism {
+        ty: Annotated::new("generic".to_string()),
+        meta: Annotated::new(MechanismMeta {
+            ntstatus: Annotated::new(NtStatus {
+                number: Annotated::new(0xC000_00FD),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    normalize_mechanism_meta(&mut mechanism, None);
+
+    let ntstatus = mechanism.meta.0.unwrap().ntstatus.0.unwrap();
+    assert_eq!(
+        ntstatus,
+        NtStatus {
+            number: Annotated::new(0xC000_00FD),
+            name: Annotated::new("STATUS_STACK_OVERFLOW".to_string()),
+            exception_name: Annotated::new("EXCEPTION_STACK_OVERFLOW".to_string()),
+        }
+    );
+}
+
+#[test]
+fn test_normalize_ntstatus_signed() {
+    let mut mechanism = Mechanism {
+        ty: Annotated::new("generic".to_string()),
+        meta: Annotated::new(MechanismMeta {
+            ntstatus: Annotated::new(NtStatus {
+                number: Annotated::new(-1_073_740_791), // 0xC0000409
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    normalize_mechanism_meta(&mut mechanism, Some(OsHint::Windows));
+
+    let ntstatus = mechanism.meta.0.unwrap().ntstatus.0.unwrap();
+    assert_eq!(
+        ntstatus.name.0,
+        Some("STATUS_STACK_BUFFER_OVERRUN".to_string())
+    );
+    assert_eq!(ntstatus.exception_name.0, None);
+}
+
+#[test]
+fn test_normalize_hresult() {
+    let mut mechanism = Mechanism {
+        ty: Annotated::new("generic".to_string()),
+        meta: Annotated::new(MechanismMeta {
+            hresult: Annotated::new(HResult {
+                number: Annotated::new(0x8007_0002),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    normalize_mechanism_meta(&mut mechanism, Some(OsHint::Windows));
+
+    let hresult = mechanism.meta.0.unwrap().hresult.0.unwrap();
+    assert_eq!(
+        hresult.name.0,
+        Some("HRESULT_FROM_WIN32(ERROR_FILE_NOT_FOUND)".to_string())
+    );
+    assert_eq!(get_hresult_display_name(0x8000_4005), Some("E_FAIL".to_string()));
+    assert_eq!(get_hresult_display_name(0x8007_FFFF), None);
+}
+
+#[test]
+fn test_normalize_errno_windows() {
+    let mut mechanism = Mechanism {
+        ty: Annotated::new("generic".to_string()),
+        meta: Annotated::new(MechanismMeta {
+            errno: Annotated::new(CError {
+                number: Annotated::new(80),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    normalize_mechanism_meta(&mut mechanism, Some(OsHint::Windows));
+
+    let errno = mechanism.meta.0.unwrap().errno.0.unwrap();
+    assert_eq!(errno.name.0, Some("STRUNCATE".to_string()));
+}
+
+#[test]
+fn test_normalize_signal_linux() {
+    let mut mechanism = Mechanism {
+        ty: Annotated::new("generic".to_string()),
+        meta: Annotated::new(MechanismMeta {
+            signal: Annotated::new(PosixSignal {
+                number: Annotated::new(11),
+                code: Annotated::new(2),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    normalize_mechanism_meta(&mut mechanism, Some(OsHint::Linux));
+
+    let signal = mechanism.meta.0.unwrap().signal.0.unwrap();
+    assert_eq!(
+        signal,
+        PosixSignal {
+            number: Annotated::new(11),
+            code: Annotated::new(2),
+            name: Annotated::new("SIGSEGV".to_string()),
+            code_name: Annotated::new("SEGV_ACCERR".to_string()),
+        }
+    );
+}
+
+#[test]
+fn test_signal_code_names_linux() {
+    assert_eq!(get_signal_code_name(11, 1, OsHint::Linux), Some("SEGV_MAPERR"));
+    assert_eq!(get_signal_code_name(7, 2, OsHint::Linux), Some("BUS_ADRERR"));
+    assert_eq!(get_signal_code_name(8, 1, OsHint::Linux), Some("FPE_INTDIV"));
+    assert_eq!(get_signal_code_name(6, -6, OsHint::Linux), Some("SI_TKILL"));
+    assert_eq!(get_signal_code_name(11, 0x80, OsHint::Linux), Some("SI_KERNEL"));
+    assert_eq!(get_signal_code_name(11, 42, OsHint::Linux), None);
+
+    // Darwin numbers its codes differently.
+    assert_eq!(get_signal_code_name(8, 1, OsHint::Darwin), Some("FPE_FLTDIV"));
+    assert_eq!(get_signal_code_name(11, 1, OsHint::Windows), None);
+}
+
+#[test]
+fn test_normalize_cpp_exception_type() {
+    let mechanism = Mechanism {
+        ty: Annotated::new("cpp_exception".to_string()),
+        meta: Annotated::new(MechanismMeta {
+            cpp_exception: Annotated::new(CppException {
+                name: Annotated::new("NSt3__112system_errorE".to_string()),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let mut exception = Exception {
+        mechanism: Annotated::new(mechanism.clone()),
+        ..Default::default()
+    };
+    normalize_exception_type(&mut exception);
+    assert_eq_dbg!(exception.ty.value(), Some(&"std::__1::system_error".to_string()));
+
+    let mut exception = Exception {
+        ty: Annotated::new("CustomError".to_string()),
+        mechanism: Annotated::new(mechanism),
+        ..Default::default()
+    };
+    normalize_exception_type(&mut exception);
+    assert_eq_dbg!(exception.ty.value(), Some(&"CustomError".to_string()));
+}
@@ -0,0 +1,867 @@
+//! Reads the structure of minidump files.
+//!
+//! Minidumps are written by crash reporters such as Breakpad, Crashpad and Windows Error
+//! Reporting.  This module extracts threads with their CPU context, loaded modules, system
+//! information and the crash reason from the core streams of a minidump.  Stacks are not walked
+//! and no symbolication takes place.
+
+use chrono::{TimeZone, Utc};
+use debugid::DebugId;
+use failure::Fail;
+
+use crate::protocol::{
+    Addr, AppContext, Context, Contexts, DebugImage, DebugMeta, DeviceContext, Event,
+    Exception, Frame, Level, MachException, Mechanism, MechanismMeta, NtStatus, OsContext,
+    PosixSignal, RegVal, Stacktrace, SymbolicDebugImage, Thread, ThreadId, Values,
+};
+use crate::store::mechanism::{normalize_mechanism_meta, OsHint};
+use crate::store::registers::CpuFamily;
+use crate::types::{Annotated, Object, Value};
+
+const MINIDUMP_SIGNATURE: u32 = 0x504d_444d; // "MDMP"
+const MINIDUMP_VERSION: u32 = 0xa793;
+
+const THREAD_LIST_STREAM: u32 = 3;
+const MODULE_LIST_STREAM: u32 = 4;
+const EXCEPTION_STREAM: u32 = 6;
+const SYSTEM_INFO_STREAM: u32 = 7;
+const MISC_INFO_STREAM: u32 = 15;
+
+const CODEVIEW_PDB70_SIGNATURE: u32 = 0x5344_5352; // "RSDS"
+const CODEVIEW_ELF_SIGNATURE: u32 = 0x4270_454c; // "BpEL"
+
+const MISC_INFO_PROCESS_ID: u32 = 0x1;
+const MISC_INFO_PROCESS_TIMES: u32 = 0x2;
+
+/// An error returned when reading a minidump fails.
+#[derive(Debug, Fail)]
+pub enum ParseMinidumpError {
+    /// The file does not start with the minidump signature.
+    #[fail(display = "invalid minidump signature")]
+    InvalidSignature,
+
+    /// The minidump was written with an unsupported format version.
+    #[fail(display = "unsupported minidump version")]
+    UnsupportedVersion,
+
+    /// A stream or record points outside of the file.
+    #[fail(display = "minidump is truncated")]
+    Truncated,
+}
+
+/// The CPU architecture of the crashed process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuArch {
+    /// 32-bit x86.
+    X86,
+    /// 64-bit x86.
+    Amd64,
+    /// 32-bit ARM.
+    Arm,
+    /// 64-bit ARM.
+    Arm64,
+    /// An architecture that is not supported.
+    Unknown(u16),
+}
+
+impl CpuArch {
+    fn from_processor_architecture(value: u16) -> Self {
+        match value {
+            0 => CpuArch::X86,
+            5 => CpuArch::Arm,
+            9 => CpuArch::Amd64,
+            12 | 0x8003 => CpuArch::Arm64,
+            other => CpuArch::Unknown(other),
+        }
+    }
+
+    /// Returns the name of the architecture as used in Sentry events.
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self {
+            CpuArch::X86 => "x86",
+            CpuArch::Amd64 => "x86_64",
+            CpuArch::Arm => "arm",
+            CpuArch::Arm64 => "arm64",
+            CpuArch::Unknown(_) => return None,
+        })
+    }
+
+    /// Returns the name of the register holding the instruction pointer.
+    fn instruction_pointer(self) -> Option<&'static str> {
+        CpuFamily::from_arch(self.name()?).ip_register_name()
+    }
+}
+
+fn get_os_name(platform_id: u32) -> Option<&'static str> {
+    Some(match platform_id {
+        2 => "Windows",
+        0x8101 => "macOS",
+        0x8102 => "iOS",
+        0x8201 => "Linux",
+        0x8202 => "Solaris",
+        0x8203 => "Android",
+        0x8205 => "NaCl",
+        _ => return None,
+    })
+}
+
+fn get_os_hint(platform_id: u32) -> Option<OsHint> {
+    match platform_id {
+        2 => Some(OsHint::Windows),
+        0x8101 | 0x8102 => Some(OsHint::Darwin),
+        0x8201 | 0x8203 => Some(OsHint::Linux),
+        _ => None,
+    }
+}
+
+/// Register names and their byte offsets within the CPU context of each architecture.
+fn get_register_layout(arch: CpuArch) -> Vec<(String, usize, usize)> {
+    match arch {
+        CpuArch::X86 => [
+            ("edi", 156),
+            ("esi", 160),
+            ("ebx", 164),
+            ("edx", 168),
+            ("ecx", 172),
+            ("eax", 176),
+            ("ebp", 180),
+            ("eip", 184),
+            ("eflags", 192),
+            ("esp", 196),
+        ]
+            .iter()
+            .map(|&(name, offset)| (name.to_string(), offset, 4))
+            .collect(),
+        CpuArch::Amd64 => [
+            ("rax", 120),
+            ("rcx", 128),
+            ("rdx", 136),
+            ("rbx", 144),
+            ("rsp", 152),
+            ("rbp", 160),
+            ("rsi", 168),
+            ("rdi", 176),
+            ("r8", 184),
+            ("r9", 192),
+            ("r10", 200),
+            ("r11", 208),
+            ("r12", 216),
+            ("r13", 224),
+            ("r14", 232),
+            ("r15", 240),
+            ("rip", 248),
+        ]
+            .iter()
+            .map(|&(name, offset)| (name.to_string(), offset, 8))
+            .collect(),
+        CpuArch::Arm => (0..16)
+            .map(|index| {
+                let name = match index {
+                    13 => "sp".to_string(),
+                    14 => "lr".to_string(),
+                    15 => "pc".to_string(),
+                    index => format!("r{}", index),
+                };
+                (name, 4 + index * 4, 4)
+            }).collect(),
+        CpuArch::Arm64 => (0..33)
+            .map(|index| {
+                let name = match index {
+                    29 => "fp".to_string(),
+                    30 => "lr".to_string(),
+                    31 => "sp".to_string(),
+                    32 => "pc".to_string(),
+                    index => format!("x{}", index),
+                };
+                (name, 8 + index * 8, 8)
+            }).collect(),
+        CpuArch::Unknown(_) => vec![],
+    }
+}
+
+/// A bounds checked little endian reader over the minidump file.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], ParseMinidumpError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(ParseMinidumpError::Truncated)
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, ParseMinidumpError> {
+        let bytes = self.bytes(offset, 2)?;
+        Ok(u16::from(bytes[0]) | u16::from(bytes[1]) << 8)
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, ParseMinidumpError> {
+        let bytes = self.bytes(offset, 4)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | u32::from(byte)))
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, ParseMinidumpError> {
+        let low = u64::from(self.u32(offset)?);
+        let high = u64::from(self.u32(offset + 4)?);
+        Ok(high << 32 | low)
+    }
+
+    /// Reads a `MINIDUMP_STRING`, which is a length prefixed UTF-16 string.
+    fn string(&self, offset: usize) -> Result<String, ParseMinidumpError> {
+        let len = self.u32(offset)? as usize;
+        let bytes = self.bytes(offset + 4, len)?;
+        let units: Vec<u16> = bytes
+            .chunks(2)
+            .filter(|chunk| chunk.len() == 2)
+            .map(|chunk| u16::from(chunk[0]) | u16::from(chunk[1]) << 8)
+            .collect();
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    /// Reads a `MINIDUMP_LOCATION_DESCRIPTOR` and returns the referenced bytes.
+    fn location(&self, offset: usize) -> Result<&'a [u8], ParseMinidumpError> {
+        let size = self.u32(offset)? as usize;
+        let rva = self.u32(offset + 4)? as usize;
+        self.bytes(rva, size)
+    }
+}
+
+#[derive(Debug, Default)]
+struct SystemInfo {
+    arch: Option<CpuArch>,
+    platform_id: u32,
+    version: Option<String>,
+    csd_version: Option<String>,
+}
+
+#[derive(Debug)]
+struct ParsedException {
+    thread_id: u32,
+    code: u32,
+    flags: u32,
+    address: u64,
+    registers: Option<Object<RegVal>>,
+}
+
+fn read_registers(context: &[u8], arch: CpuArch) -> Object<RegVal> {
+    let reader = Reader { data: context };
+    let mut registers = Object::new();
+    for (name, offset, size) in get_register_layout(arch) {
+        let value = match size {
+            4 => reader.u32(offset).map(u64::from),
+            _ => reader.u64(offset),
+        };
+        if let Ok(value) = value {
+            registers.insert(name, Annotated::new(RegVal(value)));
+        }
+    }
+    registers
+}
+
+fn read_system_info(reader: &Reader, offset: usize) -> Result<SystemInfo, ParseMinidumpError> {
+    let arch = CpuArch::from_processor_architecture(reader.u16(offset)?);
+    let major = reader.u32(offset + 8)?;
+    let minor = reader.u32(offset + 12)?;
+    let build = reader.u32(offset + 16)?;
+    let platform_id = reader.u32(offset + 20)?;
+    let csd_rva = reader.u32(offset + 24)? as usize;
+
+    let csd_version = if csd_rva != 0 {
+        Some(reader.string(csd_rva)?).filter(|csd| !csd.is_empty())
+    } else {
+        None
+    };
+
+    Ok(SystemInfo {
+        arch: Some(arch),
+        platform_id,
+        version: Some(format!("{}.{}.{}", major, minor, build)),
+        csd_version,
+    })
+}
+
+fn read_threads(
+    reader: &Reader,
+    offset: usize,
+    arch: Option<CpuArch>,
+) -> Result<Vec<(u32, Object<RegVal>)>, ParseMinidumpError> {
+    let count = reader.u32(offset)? as usize;
+    let mut threads = Vec::with_capacity(count.min(4096));
+
+    for index in 0..count {
+        let thread = offset + 4 + index * 48;
+        let id = reader.u32(thread)?;
+        let registers = match arch {
+            Some(arch) => read_registers(reader.location(thread + 40)?, arch),
+            None => Object::new(),
+        };
+        threads.push((id, registers));
+    }
+
+    Ok(threads)
+}
+
+fn read_debug_id(codeview: &[u8]) -> Option<DebugId> {
+    let reader = Reader { data: codeview };
+    match reader.u32(0).ok()? {
+        CODEVIEW_PDB70_SIGNATURE => {
+            let guid = reader.bytes(4, 16).ok()?;
+            let age = reader.u32(20).ok()?;
+            DebugId::from_guid_age(guid, age).ok()
+        }
+        CODEVIEW_ELF_SIGNATURE => {
+            // Breakpad stores the first 16 bytes of the build id in GUID byte order.
+            let mut guid = [0; 16];
+            let build_id = reader.bytes(4, codeview.len().saturating_sub(4)).ok()?;
+            let len = build_id.len().min(16);
+            guid[..len].copy_from_slice(&build_id[..len]);
+            DebugId::from_guid_age(&guid, 0).ok()
+        }
+        _ => None,
+    }
+}
+
+fn read_modules(
+    reader: &Reader,
+    offset: usize,
+    arch: Option<&str>,
+) -> Result<Vec<SymbolicDebugImage>, ParseMinidumpError> {
+    let count = reader.u32(offset)? as usize;
+    let mut modules = Vec::with_capacity(count.min(4096));
+
+    for index in 0..count {
+        let module = offset + 4 + index * 108;
+        let base = reader.u64(module)?;
+        let size = reader.u32(module + 8)?;
+        let name = reader.string(reader.u32(module + 20)? as usize)?;
+        let id = read_debug_id(reader.location(module + 76)?);
+
+        modules.push(SymbolicDebugImage {
+            name: Annotated::new(name),
+            arch: arch.map_or_else(Annotated::empty, |arch| Annotated::new(arch.to_string())),
+            image_addr: Annotated::new(Addr(base)),
+            image_size: Annotated::new(u64::from(size)),
+            id: id.map_or_else(Annotated::empty, Annotated::new),
+            ..Default::default()
+        });
+    }
+
+    Ok(modules)
+}
+
+fn read_exception(
+    reader: &Reader,
+    offset: usize,
+    arch: Option<CpuArch>,
+) -> Result<ParsedException, ParseMinidumpError> {
+    let registers = match arch {
+        Some(arch) => Some(read_registers(reader.location(offset + 160)?, arch)),
+        None => None,
+    };
+
+    Ok(ParsedException {
+        thread_id: reader.u32(offset)?,
+        code: reader.u32(offset + 8)?,
+        flags: reader.u32(offset + 12)?,
+        address: reader.u64(offset + 24)?,
+        registers,
+    })
+}
+
+fn make_mechanism(exception: &ParsedException, os_hint: Option<OsHint>) -> Mechanism {
+    let mut meta = MechanismMeta::default();
+    let mut data = Object::new();
+
+    match os_hint {
+        Some(OsHint::Linux) => {
+            meta.signal = Annotated::new(PosixSignal {
+                number: Annotated::new(i64::from(exception.code)),
+                code: Annotated::new(i64::from(exception.flags)),
+                ..Default::default()
+            });
+        }
+        Some(OsHint::Darwin) => {
+            meta.mach_exception = Annotated::new(MachException {
+                ty: Annotated::new(i64::from(exception.code)),
+                code: Annotated::new(u64::from(exception.flags)),
+                subcode: Annotated::new(exception.address),
+                ..Default::default()
+            });
+        }
+        Some(OsHint::Windows) => {
+            meta.ntstatus = Annotated::new(NtStatus {
+                number: Annotated::new(i64::from(exception.code)),
+                ..Default::default()
+            });
+        }
+        None => {
+            data.insert(
+                "code".to_string(),
+                Annotated::new(Value::String(format!("0x{:x}", exception.code))),
+            );
+        }
+    }
+
+    data.insert(
+        "address".to_string(),
+        Annotated::new(Value::String(format!("0x{:x}", exception.address))),
+    );
+
+    let mut mechanism = Mechanism {
+        ty: Annotated::new("minidump".to_string()),
+        handled: Annotated::new(false),
+        data: Annotated::new(data),
+        meta: Annotated::new(meta),
+        ..Default::default()
+    };
+    normalize_mechanism_meta(&mut mechanism, os_hint);
+    mechanism
+}
+
+fn get_exception_type(mechanism: &Mechanism, code: u32) -> String {
+    let meta = mechanism.meta.value();
+    meta.and_then(|meta| meta.signal.value())
+        .and_then(|signal| signal.name.value())
+        .or_else(|| {
+            meta.and_then(|meta| meta.mach_exception.value())
+                .and_then(|mach| mach.name.value())
+        }).or_else(|| {
+            meta.and_then(|meta| meta.ntstatus.value()).and_then(|ntstatus| {
+                ntstatus
+                    .exception_name
+                    .value()
+                    .or_else(|| ntstatus.name.value())
+            })
+        }).cloned()
+        .unwrap_or_else(|| format!("0x{:08x}", code))
+}
+
+fn make_thread(id: u32, registers: Object<RegVal>, arch: Option<CpuArch>, crashed: bool) -> Thread {
+    let instruction_addr = arch
+        .and_then(CpuArch::instruction_pointer)
+        .and_then(|name| registers.get(name))
+        .and_then(|value| value.value())
+        .map(|value| Addr(value.0));
+
+    let stacktrace = match instruction_addr {
+        Some(addr) => Annotated::new(Stacktrace {
+            frames: Annotated::new(vec![Annotated::new(Frame {
+                instruction_addr: Annotated::new(addr),
+                ..Default::default()
+            })]),
+            registers: Annotated::new(registers),
+            ..Default::default()
+        }),
+        None => Annotated::empty(),
+    };
+
+    Thread {
+        id: Annotated::new(ThreadId::Int(u64::from(id))),
+        crashed: Annotated::new(crashed),
+        stacktrace,
+        ..Default::default()
+    }
+}
+
+fn insert_context(contexts: &mut Annotated<Contexts>, key: &str, context: Context) {
+    let contexts = contexts
+        .0
+        .get_or_insert_with(|| Contexts(Object::new()));
+    contexts
+        .0
+        .entry(key.to_string())
+        .or_insert_with(|| Annotated::new(context));
+}
+
+/// Reads a minidump and fills the event with its contents.
+///
+/// Threads, debug images and the exception are only written if the event does not have them
+/// yet.  The `os` and `device` contexts are added unless they already exist.  Every thread
+/// carries the registers of its CPU context and a single frame pointing to the instruction
+/// pointer.  The exception is raised on the crashed thread and has a `minidump` mechanism.
+pub fn process_minidump(event: &mut Event, data: &[u8]) -> Result<(), ParseMinidumpError> {
+    let reader = Reader { data };
+
+    if reader.u32(0)? != MINIDUMP_SIGNATURE {
+        return Err(ParseMinidumpError::InvalidSignature);
+    }
+    if reader.u32(4)? & 0xffff != MINIDUMP_VERSION {
+        return Err(ParseMinidumpError::UnsupportedVersion);
+    }
+
+    let stream_count = reader.u32(8)? as usize;
+    let directory = reader.u32(12)? as usize;
+    let timestamp = reader.u32(20)?;
+
+    let mut streams = vec![];
+    for index in 0..stream_count {
+        let entry = directory + index * 12;
+        streams.push((reader.u32(entry)?, reader.u32(entry + 8)? as usize));
+    }
+    let find_stream = |ty: u32| {
+        streams
+            .iter()
+            .find(|&&(stream_type, _)| stream_type == ty)
+            .map(|&(_, rva)| rva)
+    };
+
+    // The system info determines how all other streams are interpreted.
+    let system_info = match find_stream(SYSTEM_INFO_STREAM) {
+        Some(offset) => read_system_info(&reader, offset)?,
+        None => SystemInfo::default(),
+    };
+    let arch = system_info.arch;
+    let arch_name = arch.and_then(CpuArch::name);
+    let os_hint = get_os_hint(system_info.platform_id);
+
+    let threads = match find_stream(THREAD_LIST_STREAM) {
+        Some(offset) => read_threads(&reader, offset, arch)?,
+        None => vec![],
+    };
+
+    let modules = match find_stream(MODULE_LIST_STREAM) {
+        Some(offset) => read_modules(&reader, offset, arch_name)?,
+        None => vec![],
+    };
+
+    let mut exception = match find_stream(EXCEPTION_STREAM) {
+        Some(offset) => Some(read_exception(&reader, offset, arch)?),
+        None => None,
+    };
+
+    if event.timestamp.0.is_none() && timestamp != 0 {
+        event.timestamp = Annotated::new(Utc.timestamp_opt(i64::from(timestamp), 0).unwrap());
+    }
+    event.level.0.get_or_insert(Level::Fatal);
+    event.platform.0.get_or_insert_with(|| "native".to_string());
+
+    if event.threads.0.is_none() && !threads.is_empty() {
+        let crashed_id = exception.as_ref().map(|exception| exception.thread_id);
+        let threads = threads
+            .into_iter()
+            .map(|(id, registers)| {
+                let crashed = crashed_id == Some(id);
+                // The exception context is captured at the time of the crash, while the thread
+                // context of the crashed thread points into the exception handler.
+                let registers = match exception {
+                    Some(ref mut exception) if crashed => {
+                        exception.registers.take().unwrap_or(registers)
+                    }
+                    _ => registers,
+                };
+                Annotated::new(make_thread(id, registers, arch, crashed))
+            }).collect();
+        event.threads = Annotated::new(Values::new(threads));
+    }
+
+    if event.exceptions.0.is_none() {
+        if let Some(exception) = exception {
+            let mechanism = make_mechanism(&exception, os_hint);
+            let exception = Exception {
+                ty: Annotated::new(get_exception_type(&mechanism, exception.code)),
+                thread_id: Annotated::new(ThreadId::Int(u64::from(exception.thread_id))),
+                mechanism: Annotated::new(mechanism),
+                ..Default::default()
+            };
+            event.exceptions = Annotated::new(Values::new(vec![Annotated::new(exception)]));
+        }
+    }
+
+    if !modules.is_empty() {
+        let debug_meta = event.debug_meta.0.get_or_insert_with(DebugMeta::default);
+        if debug_meta.images.0.is_none() {
+            debug_meta.images = Annotated::new(
+                modules
+                    .into_iter()
+                    .map(|image| Annotated::new(DebugImage::Symbolic(Box::new(image))))
+                    .collect(),
+            );
+        }
+    }
+
+    if let Some(name) = get_os_name(system_info.platform_id) {
+        let os = OsContext {
+            name: Annotated::new(name.to_string()),
+            version: system_info
+                .version
+                .map_or_else(Annotated::empty, Annotated::new),
+            raw_description: system_info
+                .csd_version
+                .map_or_else(Annotated::empty, Annotated::new),
+            ..Default::default()
+        };
+        insert_context(&mut event.contexts, "os", Context::Os(Box::new(os)));
+    }
+
+    if let Some(arch) = arch_name {
+        let device = DeviceContext {
+            arch: Annotated::new(arch.to_string()),
+            ..Default::default()
+        };
+        insert_context(&mut event.contexts, "device", Context::Device(Box::new(device)));
+    }
+
+    if let Some(offset) = find_stream(MISC_INFO_STREAM) {
+        let flags = reader.u32(offset + 4)?;
+        let mut app = AppContext::default();
+
+        if flags & MISC_INFO_PROCESS_ID != 0 {
+            let process_id = reader.u32(offset + 8)?;
+            app.other.insert(
+                "process_id".to_string(),
+                Annotated::new(Value::U64(u64::from(process_id))),
+            );
+        }
+        if flags & MISC_INFO_PROCESS_TIMES != 0 {
+            let create_time = reader.u32(offset + 12)?;
+            app.app_start_time =
+                Annotated::new(Utc.timestamp_opt(i64::from(create_time), 0).unwrap());
+        }
+
+        if flags & (MISC_INFO_PROCESS_ID | MISC_INFO_PROCESS_TIMES) != 0 {
+            insert_context(&mut event.contexts, "app", Context::App(Box::new(app)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a minidump into a new event.
+///
+/// See `process_minidump` for the fields that are filled.
+pub fn parse_minidump(data: &[u8]) -> Result<Event, ParseMinidumpError> {
+    let mut event = Event::default();
+    process_minidump(&mut event, data)?;
+    Ok(event)
+}
+
+#[cfg(test)]
+fn make_test_minidump() -> Vec<u8> {
+    fn put_u16(buf: &mut [u8], offset: usize, value: u16) {
+        buf[offset..offset + 2].copy_from_slice(&[value as u8, (value >> 8) as u8]);
+    }
+    fn put_u32(buf: &mut [u8], offset: usize, value: u32) {
+        for i in 0..4 {
+            buf[offset + i] = (value >> (i * 8)) as u8;
+        }
+    }
+    fn put_u64(buf: &mut [u8], offset: usize, value: u64) {
+        put_u32(buf, offset, value as u32);
+        put_u32(buf, offset + 4, (value >> 32) as u32);
+    }
+    fn append(buf: &mut Vec<u8>, len: usize) -> usize {
+        let offset = buf.len();
+        buf.resize(offset + len, 0);
+        offset
+    }
+    fn append_context(buf: &mut Vec<u8>, rip: u64, rsp: u64) -> usize {
+        let offset = append(buf, 1232);
+        put_u64(buf, offset + 248, rip);
+        put_u64(buf, offset + 152, rsp);
+        offset
+    }
+    fn append_string(buf: &mut Vec<u8>, value: &str) -> usize {
+        let units: Vec<u16> = value.encode_utf16().collect();
+        let offset = append(buf, 4 + units.len() * 2);
+        put_u32(buf, offset, (units.len() * 2) as u32);
+        for (index, unit) in units.into_iter().enumerate() {
+            put_u16(buf, offset + 4 + index * 2, unit);
+        }
+        offset
+    }
+
+    let mut buf = vec![];
+    append(&mut buf, 32);
+    put_u32(&mut buf, 0, MINIDUMP_SIGNATURE);
+    put_u32(&mut buf, 4, MINIDUMP_VERSION);
+    put_u32(&mut buf, 8, 5);
+    put_u32(&mut buf, 12, 32);
+    put_u32(&mut buf, 20, 1_546_300_800);
+    let directory = append(&mut buf, 5 * 12);
+
+    // SystemInfo: amd64 on Linux
+    let csd = append_string(&mut buf, "Linux 4.15.0 #1 SMP");
+    let system_info = append(&mut buf, 56);
+    put_u16(&mut buf, system_info, 9);
+    put_u32(&mut buf, system_info + 8, 4);
+    put_u32(&mut buf, system_info + 12, 15);
+    put_u32(&mut buf, system_info + 16, 0);
+    put_u32(&mut buf, system_info + 20, 0x8201);
+    put_u32(&mut buf, system_info + 24, csd as u32);
+
+    // ThreadList: two threads
+    let context1 = append_context(&mut buf, 0x1000, 0x7fff_0000);
+    let context2 = append_context(&mut buf, 0x2000, 0x7ffe_0000);
+    let thread_list = append(&mut buf, 4 + 2 * 48);
+    put_u32(&mut buf, thread_list, 2);
+    for (index, &(id, context)) in [(1u32, context1), (2, context2)].iter().enumerate() {
+        let thread = thread_list + 4 + index * 48;
+        put_u32(&mut buf, thread, id);
+        put_u32(&mut buf, thread + 40, 1232);
+        put_u32(&mut buf, thread + 44, context as u32);
+    }
+
+    // ModuleList: one module with an ELF build id
+    let name = append_string(&mut buf, "/usr/bin/crash");
+    let codeview = append(&mut buf, 24);
+    put_u32(&mut buf, codeview, CODEVIEW_ELF_SIGNATURE);
+    for i in 0..20 {
+        buf[codeview + 4 + i] = i as u8;
+    }
+    let module_list = append(&mut buf, 4 + 108);
+    put_u32(&mut buf, module_list, 1);
+    put_u64(&mut buf, module_list + 4, 0x40_0000);
+    put_u32(&mut buf, module_list + 4 + 8, 0x1_0000);
+    put_u32(&mut buf, module_list + 4 + 20, name as u32);
+    put_u32(&mut buf, module_list + 4 + 76, 24);
+    put_u32(&mut buf, module_list + 4 + 80, codeview as u32);
+
+    // Exception: SIGSEGV with SEGV_MAPERR on thread 2
+    let exception_context = append_context(&mut buf, 0x2042, 0x7ffe_0000);
+    let exception = append(&mut buf, 168);
+    put_u32(&mut buf, exception, 2);
+    put_u32(&mut buf, exception + 8, 11);
+    put_u32(&mut buf, exception + 12, 1);
+    put_u64(&mut buf, exception + 24, 0xdead);
+    put_u32(&mut buf, exception + 160, 1232);
+    put_u32(&mut buf, exception + 164, exception_context as u32);
+
+    // MiscInfo: process id
+    let misc_info = append(&mut buf, 24);
+    put_u32(&mut buf, misc_info, 24);
+    put_u32(&mut buf, misc_info + 4, MISC_INFO_PROCESS_ID);
+    put_u32(&mut buf, misc_info + 8, 4711);
+
+    let streams = [
+        (SYSTEM_INFO_STREAM, system_info, 56),
+        (THREAD_LIST_STREAM, thread_list, 4 + 2 * 48),
+        (MODULE_LIST_STREAM, module_list, 4 + 108),
+        (EXCEPTION_STREAM, exception, 168),
+        (MISC_INFO_STREAM, misc_info, 24),
+    ];
+    for (index, &(ty, rva, size)) in streams.iter().enumerate() {
+        put_u32(&mut buf, directory + index * 12, ty);
+        put_u32(&mut buf, directory + index * 12 + 4, size as u32);
+        put_u32(&mut buf, directory + index * 12 + 8, rva as u32);
+    }
+
+    buf
+}
+
+#[test]
+fn test_parse_minidump() {
+    let event = parse_minidump(&make_test_minidump()).unwrap();
+
+    assert_eq_dbg!(
+        event.timestamp.value().map(|date| date.to_rfc3339()),
+        Some("2019-01-01T00:00:00+00:00".to_string())
+    );
+
+    let threads = event.threads.value().unwrap().values.value().unwrap();
+    assert_eq!(threads.len(), 2);
+
+    let thread = threads[0].value().unwrap();
+    assert_eq_dbg!(thread.id.value(), Some(&ThreadId::Int(1)));
+    assert_eq_dbg!(thread.crashed.value(), Some(&false));
+    let stacktrace = thread.stacktrace.value().unwrap();
+    assert_eq_dbg!(
+        stacktrace.registers.value().unwrap().get("rsp"),
+        Some(&Annotated::new(RegVal(0x7fff_0000)))
+    );
+
+    // The crashed thread uses the exception context.
+    let crashed = threads[1].value().unwrap();
+    assert_eq_dbg!(crashed.crashed.value(), Some(&true));
+    let frames = crashed.stacktrace.value().unwrap().frames.value().unwrap();
+    assert_eq_dbg!(
+        frames[0].value().unwrap().instruction_addr.value(),
+        Some(&Addr(0x2042))
+    );
+
+    let exception = event.exceptions.value().unwrap().values.value().unwrap()[0]
+        .value()
+        .unwrap();
+    assert_eq_dbg!(exception.ty.value(), Some(&"SIGSEGV".to_string()));
+    assert_eq_dbg!(exception.thread_id.value(), Some(&ThreadId::Int(2)));
+    let mechanism = exception.mechanism.value().unwrap();
+    assert_eq_dbg!(mechanism.ty.value(), Some(&"minidump".to_string()));
+    let signal = mechanism.meta.value().unwrap().signal.value().unwrap();
+    assert_eq_dbg!(signal.number.value(), Some(&11));
+    assert_eq_dbg!(signal.code.value(), Some(&1));
+
+    let images = event.debug_meta.value().unwrap().images.value().unwrap();
+    match images[0].value() {
+        Some(DebugImage::Symbolic(image)) => {
+            assert_eq_dbg!(image.name.value(), Some(&"/usr/bin/crash".to_string()));
+            assert_eq_dbg!(image.arch.value(), Some(&"x86_64".to_string()));
+            assert_eq_dbg!(image.image_addr.value(), Some(&Addr(0x40_0000)));
+            assert_eq_dbg!(image.image_size.value(), Some(&0x1_0000));
+            assert_eq_dbg!(
+                image.id.value().map(ToString::to_string),
+                Some("03020100-0504-0706-0809-0a0b0c0d0e0f".to_string())
+            );
+        }
+        other => panic!("unexpected image {:?}", other),
+    }
+
+    let contexts = event.contexts.value().unwrap();
+    match contexts.get("os").and_then(Annotated::value) {
+        Some(Context::Os(os)) => {
+            assert_eq_dbg!(os.name.value(), Some(&"Linux".to_string()));
+            assert_eq_dbg!(os.version.value(), Some(&"4.15.0".to_string()));
+            assert_eq_dbg!(
+                os.raw_description.value(),
+                Some(&"Linux 4.15.0 #1 SMP".to_string())
+            );
+        }
+        other => panic!("unexpected context {:?}", other),
+    }
+    match contexts.get("app").and_then(Annotated::value) {
+        Some(Context::App(app)) => {
+            assert_eq_dbg!(
+                app.other.get("process_id"),
+                Some(&Annotated::new(Value::U64(4711)))
+            );
+        }
+        other => panic!("unexpected context {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_minidump_errors() {
+    match parse_minidump(b"MDMX") {
+        Err(ParseMinidumpError::InvalidSignature) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let mut data = make_test_minidump();
+    data.truncate(200);
+    match parse_minidump(&data) {
+        Err(ParseMinidumpError::Truncated) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_windows_exception_code() {
+    let exception = ParsedException {
+        thread_id: 1,
+        code: 0xc000_0005,
+        flags: 0,
+        address: 0x10,
+        registers: None,
+    };
+
+    let mechanism = make_mechanism(&exception, Some(OsHint::Windows));
+    let ntstatus = mechanism.meta.value().unwrap().ntstatus.value().unwrap();
+    assert_eq_dbg!(ntstatus.number.value(), Some(&0xc000_0005));
+    assert_eq_dbg!(
+        get_exception_type(&mechanism, exception.code),
+        "EXCEPTION_ACCESS_VIOLATION"
+    );
+}
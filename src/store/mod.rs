@@ -1,7 +1,7 @@
 //! Utility code for sentry's internal store.
 use std::mem;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -9,18 +9,40 @@ use serde_derive::{Deserialize, Serialize};
 
 use crate::processor::{MaxChars, ProcessValue, ProcessingState, Processor};
 use crate::protocol::{
-    Breadcrumb, ClientSdkInfo, Event, EventType, Exception, Frame, IpAddr, Level, Request,
-    Stacktrace, Tags, User,
+    Breadcrumb, ClientSdkInfo, DebugMeta, Event, EventType, Exception, Frame, IpAddr, Level,
+    Request, Stacktrace, Tags, User,
 };
 use crate::types::{Annotated, Array, Meta, Object, Remark, RemarkType, Value};
 
+mod anonymize;
+mod apple_crash_report;
+mod client_ip;
+mod debug_images;
+mod demangle;
 mod escalate;
+pub mod filter;
 mod geo;
+mod js_stacktrace;
+mod jvm_stacktrace;
 mod mechanism;
+pub mod minidump;
+pub mod proguard;
+mod python_traceback;
+mod registers;
 mod request;
+pub mod sampling;
+pub mod sourcemaps;
 mod stacktrace;
+mod timestamp;
 
-pub use crate::store::geo::GeoIpLookup;
+pub use crate::store::anonymize::{anonymize_ip, IpAnonymization};
+pub use crate::store::apple_crash_report::{parse_apple_crash_report, ParseAppleCrashReportError};
+pub use crate::store::client_ip::{get_client_ip, ClientIpSource, IpNetwork, ParseIpNetworkError};
+pub use crate::store::geo::{GeoIpLookup, GeoLookupError, GeoProvider, GeoTable};
+pub use crate::store::js_stacktrace::parse_js_stacktrace;
+pub use crate::store::jvm_stacktrace::parse_jvm_exceptions;
+pub use crate::store::python_traceback::parse_python_traceback;
+pub use crate::store::registers::{get_caller_address, CpuFamily};
 
 fn parse_type_and_value(
     ty: Annotated<String>,
@@ -59,16 +81,42 @@ pub struct StoreConfig {
     pub key_id: Option<String>,
     pub protocol_version: Option<String>,
     pub stacktrace_frames_hard_limit: Option<usize>,
+    /// Removes template arguments and argument lists from demangled C++ function names.
+    pub simplify_cpp_symbols: bool,
+    /// Maximum age of the event timestamp in seconds before it is replaced with `received`.
+    pub max_secs_in_past: Option<i64>,
+    /// Maximum number of seconds the event timestamp may lie in the future.
+    pub max_secs_in_future: Option<i64>,
+    /// The time at which the SDK sent the event, used to correct the device clock.
+    pub sent_at: Option<DateTime<Utc>>,
+    /// The time at which the event was received, defaulting to the current time.
+    ///
+    /// All time-dependent normalization uses this value, so that reprocessing an event with the
+    /// same config yields the same result.
+    pub received_at: Option<DateTime<Utc>>,
+    /// Networks of proxies in CIDR notation whose forwarding headers are trusted.
+    ///
+    /// If `client_ip` lies in one of these networks, the client IP is derived from the
+    /// `Forwarded`, `X-Forwarded-For` or `X-Real-IP` request headers instead.  Invalid networks
+    /// are rejected when the config is deserialized.
+    pub trusted_proxies: Vec<IpNetwork>,
+    /// How IP addresses of the client are anonymized after the geo lookup.
+    pub ip_anonymization: IpAnonymization,
 }
 
 impl StoreConfig {
+    /// Returns the time at which the event was received.
+    pub fn received_at(&self) -> DateTime<Utc> {
+        self.received_at.unwrap_or_else(Utc::now)
+    }
+
     /// Returns the SDK info.
     fn get_sdk_info(&self) -> Option<ClientSdkInfo> {
         self.client.as_ref().and_then(|client| {
             client
-                .splitn(1, '/')
+                .splitn(2, '/')
                 .collect_tuple()
-                .or_else(|| client.splitn(1, ' ').collect_tuple())
+                .or_else(|| client.splitn(2, ' ').collect_tuple())
                 .map(|(name, version)| ClientSdkInfo {
                     name: Annotated::new(name.to_owned()),
                     version: Annotated::new(version.to_owned()),
@@ -81,20 +129,26 @@ impl StoreConfig {
 /// The processor that normalizes events for store.
 pub struct StoreNormalizeProcessor<'a> {
     config: StoreConfig,
-    geoip_lookup: Option<&'a GeoIpLookup>,
+    geo_provider: Option<&'a dyn GeoProvider>,
     bag_size_state: Option<BagSizeState>,
+    /// The client IP of the current event and where it was taken from.
+    client_ip: Option<(String, ClientIpSource)>,
+    /// The platform of the current event.
+    platform: Option<String>,
 }
 
 impl<'a> StoreNormalizeProcessor<'a> {
     /// Creates a new normalization processor.
     pub fn new(
         config: StoreConfig,
-        geoip_lookup: Option<&'a GeoIpLookup>,
+        geo_provider: Option<&'a dyn GeoProvider>,
     ) -> StoreNormalizeProcessor<'a> {
         StoreNormalizeProcessor {
             config,
-            geoip_lookup,
+            geo_provider,
             bag_size_state: None,
+            client_ip: None,
+            platform: None,
         }
     }
 
@@ -102,6 +156,25 @@ impl<'a> StoreNormalizeProcessor<'a> {
     pub fn config(&self) -> &StoreConfig {
         &self.config
     }
+
+    /// Returns the client IP of the current event, falling back to the configured client IP.
+    fn client_ip(&self) -> Option<&str> {
+        match self.client_ip {
+            Some((ref client_ip, _)) => Some(client_ip),
+            None => self.config.client_ip.as_deref(),
+        }
+    }
+
+    /// Returns the client IP as user IP address, annotated with the source it was taken from.
+    fn client_ip_address(&self) -> Option<Annotated<IpAddr>> {
+        let client_ip = self.client_ip()?;
+        let mut meta = Meta::default();
+        if let Some((_, source)) = self.client_ip {
+            meta.add_remark(Remark::new(RemarkType::Annotated, source.to_string()));
+        }
+
+        Some(Annotated(Some(IpAddr(client_ip.to_string())), meta))
+    }
 }
 
 impl<'a> Processor for StoreNormalizeProcessor<'a> {
@@ -262,6 +335,19 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
         event: Annotated<Event>,
         state: ProcessingState,
     ) -> Annotated<Event> {
+        self.client_ip = self.config.client_ip.as_ref().map(|remote_addr| {
+            let headers = event
+                .value()
+                .and_then(|event| event.request.value())
+                .and_then(|request| request.headers.value());
+            client_ip::get_client_ip(remote_addr, headers, &self.config.trusted_proxies)
+        });
+
+        self.platform = event
+            .value()
+            .and_then(|event| event.platform.value())
+            .cloned();
+
         let mut event = ProcessValue::process_child_values(event, self, state.clone());
 
         if let Some(ref mut event) = event.0 {
@@ -284,9 +370,19 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
                 event.dist.0 = None;
             }
 
-            event.timestamp.0.get_or_insert_with(Utc::now);
-            // TODO: Validate that timestamp is not too far in the past and not in the future
-            event.received.0 = Some(Utc::now());
+            let received = self.config.received_at();
+            if let Some(sent_at) = self.config.sent_at {
+                timestamp::correct_clock_drift(event, sent_at, received);
+            }
+
+            event.timestamp.0.get_or_insert(received);
+            timestamp::validate_timestamp(
+                event,
+                received,
+                self.config.max_secs_in_past,
+                self.config.max_secs_in_future,
+            );
+            event.received.0 = Some(received);
             event.logger.0.get_or_insert_with(String::new);
             event.platform.0.get_or_insert_with(|| "other".to_string());
 
@@ -376,6 +472,8 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
                 }
             }
 
+            registers::normalize_event_registers(event);
+
             let http_ip = event
                 .request
                 .0
@@ -391,7 +489,7 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
                 if user.ip_address.0.is_none() {
                     user.ip_address = Annotated::new(IpAddr(http_ip.clone()));
                 }
-            } else if let Some(ref client_ip) = self.config.client_ip {
+            } else if let Some(client_ip) = self.client_ip_address() {
                 let should_use_client_ip =
                     self.config.is_public_auth || match event.platform.0.as_ref().map(|x| &**x) {
                         Some("javascript") | Some("cocoa") | Some("objc") => true,
@@ -401,10 +499,18 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
                 if should_use_client_ip {
                     let mut user = event.user.0.get_or_insert_with(Default::default);
                     if user.ip_address.0.is_none() {
-                        user.ip_address = Annotated::new(IpAddr(client_ip.clone()));
+                        user.ip_address = client_ip;
                     }
                 }
             }
+
+            anonymize::anonymize_event_ips(event, self.config.ip_anonymization);
+        }
+
+        // Frames are mapped to images after normalization, so that the instruction addresses of
+        // all stacktraces are final.
+        if let Some(mut processor) = debug_images::ImageMappingProcessor::from_event(&event) {
+            event = processor.process_event(event, state.clone());
         }
 
         // XXX: Remove or deactivate once Sentry can handle partially invalid interfaces.
@@ -430,10 +536,30 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
         state: ProcessingState,
     ) -> Annotated<Request> {
         let request = ProcessValue::process_child_values(request, self, state);
-        let client_ip = self.config.client_ip.as_ref().map(String::as_str);
+        let client_ip = self.client_ip();
         request.and_then(|r| request::normalize_request(r, client_ip))
     }
 
+    fn process_debug_meta(
+        &mut self,
+        debug_meta: Annotated<DebugMeta>,
+        state: ProcessingState,
+    ) -> Annotated<DebugMeta> {
+        let mut debug_meta = ProcessValue::process_child_values(debug_meta, self, state);
+
+        if let Some(ref mut debug_meta) = debug_meta.0 {
+            if let Some(ref mut images) = debug_meta.images.0 {
+                for image in images.iter_mut() {
+                    if let Some(ref mut image) = image.0 {
+                        debug_images::normalize_debug_image(image);
+                    }
+                }
+            }
+        }
+
+        debug_meta
+    }
+
     fn process_user(&mut self, user: Annotated<User>, state: ProcessingState) -> Annotated<User> {
         let user = ProcessValue::process_child_values(user, self, state);
 
@@ -441,11 +567,11 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
             ip_address: user.ip_address.and_then(|ip| {
                 // Fill in ip addresses marked as {{auto}}
                 if ip.is_auto() {
-                    if let Some(ref client_ip) = self.config.client_ip {
-                        return IpAddr(client_ip.clone());
+                    if let Some(client_ip) = self.client_ip_address() {
+                        return client_ip;
                     }
                 }
-                ip
+                Annotated::new(ip)
             }),
             email: user.email.and_then(|email| {
                 // Validate email
@@ -461,14 +587,18 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
             ..user
         }).filter_map(Annotated::is_valid, |mut user| {
             // Infer user.geo from user.ip_address
-            if let (None, Some(geoip_lookup), Some(ip_address)) = (
+            if let (None, Some(geo_provider), Some(ip_address)) = (
                 user.geo.0.as_ref(),
-                self.geoip_lookup.as_ref(),
-                user.ip_address.0.as_ref(),
+                self.geo_provider,
+                user.ip_address.0.as_ref().and_then(|ip| ip.0.parse().ok()),
             ) {
-                if let Ok(Some(geo)) = geoip_lookup.lookup(&ip_address.0) {
-                    user.geo = Annotated::new(geo);
-                    // TODO: Errorhandling
+                match geo_provider.lookup(ip_address) {
+                    Ok(Some(mut geo)) => {
+                        geo::reduce_precision(&mut geo);
+                        user.geo = Annotated::new(geo);
+                    }
+                    Ok(None) => (),
+                    Err(error) => user.geo.1.add_error(error.to_string(), None),
                 }
             }
             user
@@ -489,10 +619,20 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
         exception: Annotated<Exception>,
         state: ProcessingState,
     ) -> Annotated<Exception> {
+        let is_javascript = matches!(self.platform.as_deref(), Some("javascript") | Some("node"));
+
+        let exception = exception.map_value(|mut exception| {
+            if is_javascript {
+                js_stacktrace::process_js_stack(&mut exception);
+            }
+            exception
+        });
         let exception = ProcessValue::process_child_values(exception, self, state);
 
         exception
             .and_then(|mut exception| {
+                // The mangled name is only demangled after it was trimmed.
+                mechanism::normalize_exception_type(&mut exception);
                 stacktrace::process_non_raw_stacktrace(&mut exception.stacktrace);
 
                 let (ty, value) = parse_type_and_value(
@@ -533,14 +673,25 @@ impl<'a> Processor for StoreNormalizeProcessor<'a> {
         }
 
         let frame = ProcessValue::process_child_values(frame, self, state);
-
-        frame.filter_map(Annotated::is_valid, |frame| Frame {
-            in_app: frame.in_app.or_else(|| false),
-            function: frame.function.and_then(remove_questionmark),
-            symbol: frame.symbol.and_then(remove_questionmark),
-            pre_lines: frame.pre_lines.and_then(fill_lines),
-            post_lines: frame.post_lines.and_then(fill_lines),
-            ..frame
+        let simplify_cpp_symbols = self.config.simplify_cpp_symbols;
+        let is_native = self
+            .platform
+            .as_deref()
+            .is_some_and(demangle::is_native_platform);
+
+        frame.filter_map(Annotated::is_valid, |frame| {
+            let mut frame = Frame {
+                in_app: frame.in_app.or_else(|| false),
+                function: frame.function.and_then(remove_questionmark),
+                symbol: frame.symbol.and_then(remove_questionmark),
+                pre_lines: frame.pre_lines.and_then(fill_lines),
+                post_lines: frame.post_lines.and_then(fill_lines),
+                ..frame
+            };
+            if is_native {
+                demangle::demangle_frame(&mut frame, simplify_cpp_symbols);
+            }
+            frame
         })
     }
 
@@ -635,11 +786,48 @@ fn test_exception_invalid() {
     );
 }
 
+#[test]
+fn test_sdk_info_from_client() {
+    let config = StoreConfig {
+        client: Some("sentry.python/0.5.3".to_string()),
+        ..Default::default()
+    };
+
+    let info = config.get_sdk_info().unwrap();
+    assert_eq_str!(info.name.value().unwrap(), "sentry.python");
+    assert_eq_str!(info.version.value().unwrap(), "0.5.3");
+
+    let config = StoreConfig {
+        client: Some("raven-js 3.23.3".to_string()),
+        ..Default::default()
+    };
+
+    let info = config.get_sdk_info().unwrap();
+    assert_eq_str!(info.name.value().unwrap(), "raven-js");
+    assert_eq_str!(info.version.value().unwrap(), "3.23.3");
+
+    let config = StoreConfig {
+        client: Some("unknown".to_string()),
+        ..Default::default()
+    };
+    assert_eq_dbg!(config.get_sdk_info(), None);
+}
+
 #[test]
 fn test_geo_from_ip_address() {
-    use crate::protocol::Geo;
+    use std::path::Path;
+
+    // The databases are downloaded by `make test`.
+    if !Path::new("GeoLite2-City.mmdb").exists() {
+        return;
+    }
+
+    let mut lookup = GeoIpLookup::open("GeoLite2-City.mmdb").unwrap();
+    let has_asn = Path::new("GeoLite2-ASN.mmdb").exists();
+    if has_asn {
+        lookup = lookup.with_asn_database("GeoLite2-ASN.mmdb").unwrap();
+    }
 
-    let lookup = GeoIpLookup::open("GeoLite2-City.mmdb").unwrap();
     let mut processor = StoreNormalizeProcessor::new(StoreConfig::default(), Some(&lookup));
 
     let user = Annotated::new(User {
@@ -649,13 +837,105 @@ fn test_geo_from_ip_address() {
 
     let user = user.process(&mut processor);
 
-    let expected = Annotated::new(Geo {
-        country_code: Annotated::new("AT".to_string()),
-        city: Annotated::new("Vienna".to_string()),
-        region: Annotated::new("Austria".to_string()),
+    let geo = user.0.unwrap().geo.0.unwrap();
+    assert_eq_str!(geo.country_code.value().unwrap(), "AT");
+    assert_eq_str!(geo.city.value().unwrap(), "Vienna");
+    assert_eq_str!(geo.region.value().unwrap(), "Vienna");
+    assert_eq_str!(geo.subdivision.value().unwrap(), "AT-9");
+    assert!(geo.postal_code.value().is_some());
+    assert_eq_str!(geo.timezone.value().unwrap(), "Europe/Vienna");
+
+    if has_asn {
+        assert!(geo.asn.value().is_some());
+        assert!(geo.organization.value().is_some());
+    }
+}
+
+#[test]
+fn test_geo_from_table() {
+    use crate::protocol::Geo;
+
+    let mut table = GeoTable::new();
+    table.insert(
+        "213.47.0.0/16".parse().unwrap(),
+        Geo {
+            country_code: Annotated::new("AT".to_string()),
+            city: Annotated::new("Vienna".to_string()),
+            region: Annotated::new("Vienna".to_string()),
+            subdivision: Annotated::new("AT-9".to_string()),
+            postal_code: Annotated::new("1010".to_string()),
+            timezone: Annotated::new("Europe/Vienna".to_string()),
+            asn: Annotated::new(8412),
+            organization: Annotated::new("T-Mobile Austria GmbH".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let mut processor = StoreNormalizeProcessor::new(StoreConfig::default(), Some(&table));
+
+    let user = Annotated::new(User {
+        ip_address: Annotated::new(IpAddr("213.47.147.207".to_string())),
+        ..Default::default()
+    });
+
+    let user = user.process(&mut processor);
+
+    let geo = user.0.unwrap().geo.0.unwrap();
+    assert_eq_str!(geo.country_code.value().unwrap(), "AT");
+    assert_eq_str!(geo.city.value().unwrap(), "Vienna");
+    assert_eq_str!(geo.region.value().unwrap(), "Vienna");
+    assert_eq_str!(geo.subdivision.value().unwrap(), "AT-9");
+    assert_eq_str!(geo.postal_code.value().unwrap(), "1010");
+    assert_eq_str!(geo.timezone.value().unwrap(), "Europe/Vienna");
+    assert_eq_dbg!(geo.asn.value(), Some(&8412));
+    assert_eq_str!(geo.organization.value().unwrap(), "T-Mobile Austria GmbH");
+}
+
+#[test]
+fn test_geo_from_provider() {
+    use crate::protocol::Geo;
+
+    struct FailingProvider;
+
+    impl GeoProvider for FailingProvider {
+        fn lookup(&self, _: std::net::IpAddr) -> Result<Option<Geo>, GeoLookupError> {
+            Err(GeoLookupError::from(maxminddb::MaxMindDBError::IoError(
+                "database unavailable".to_string(),
+            )))
+        }
+    }
+
+    let mut table = GeoTable::new();
+    table.insert(
+        "213.47.0.0/16".parse().unwrap(),
+        Geo {
+            country_code: Annotated::new("AT".to_string()),
+            city: Annotated::new("Vienna".to_string()),
+            latitude: Annotated::new(48.208_49),
+            longitude: Annotated::new(16.372_08),
+            ..Default::default()
+        },
+    );
+
+    let user = Annotated::new(User {
+        ip_address: Annotated::new(IpAddr("213.47.147.207".to_string())),
         ..Default::default()
     });
-    assert_eq_dbg!(user.0.unwrap().geo, expected)
+
+    let mut processor = StoreNormalizeProcessor::new(StoreConfig::default(), Some(&table));
+    let geo = user.clone().process(&mut processor).0.unwrap().geo.0.unwrap();
+    assert_eq_str!(geo.city.value().unwrap(), "Vienna");
+    assert_eq_dbg!(geo.latitude.value(), Some(&48.2));
+    assert_eq_dbg!(geo.longitude.value(), Some(&16.4));
+
+    let provider = FailingProvider;
+    let mut processor = StoreNormalizeProcessor::new(StoreConfig::default(), Some(&provider));
+    let geo = user.process(&mut processor).0.unwrap().geo;
+    assert_eq_dbg!(geo.value(), None);
+    assert_eq_str!(
+        geo.meta().iter_errors().next().unwrap(),
+        "geoip lookup failed: database unavailable"
+    );
 }
 
 #[test]
@@ -843,3 +1123,212 @@ fn test_databag_array_stripping() {
   }
 }"#);
 }
+
+#[test]
+fn test_received_at_is_reproducible() {
+    use chrono::TimeZone;
+
+    let input = r#"{"message":"hello","timestamp":946684800,"breadcrumbs":[{"timestamp":946684700}]}"#;
+    let normalize = || {
+        let config = StoreConfig {
+            received_at: Some(Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 5).unwrap()),
+            sent_at: Some(Utc.with_ymd_and_hms(1999, 12, 31, 23, 0, 0).unwrap()),
+            max_secs_in_future: Some(60),
+            ..Default::default()
+        };
+
+        let mut processor = StoreNormalizeProcessor::new(config, None);
+        let event = Annotated::<Event>::from_json(input).unwrap();
+        event.process(&mut processor).to_json().unwrap()
+    };
+
+    let output = normalize();
+    assert_eq_str!(output, normalize());
+
+    let event = Annotated::<Event>::from_json(&output).unwrap().0.unwrap();
+    assert_eq_dbg!(
+        event.received.0,
+        Some(Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 5).unwrap())
+    );
+    assert_eq_dbg!(
+        event.timestamp.0,
+        Some(Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 5).unwrap())
+    );
+}
+
+#[test]
+fn test_client_ip_from_trusted_proxy() {
+    let input = r#"{
+        "platform": "javascript",
+        "request": {
+            "headers": {"X-Forwarded-For": "198.51.100.7, 10.0.0.2"},
+            "env": {"REMOTE_ADDR": "{{auto}}"}
+        }
+    }"#;
+
+    let config = StoreConfig {
+        client_ip: Some("10.0.0.1".to_string()),
+        trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+        ..Default::default()
+    };
+
+    let mut processor = StoreNormalizeProcessor::new(config, None);
+    let event = Annotated::<Event>::from_json(input).unwrap();
+    let event = event.process(&mut processor).0.unwrap();
+
+    let request = event.request.value().unwrap();
+    assert_eq_dbg!(
+        request.env.value().unwrap().get("REMOTE_ADDR").unwrap().value(),
+        Some(&Value::String("198.51.100.7".to_string()))
+    );
+
+    let ip_address = &event.user.value().unwrap().ip_address;
+    assert_eq_str!(ip_address.value().unwrap().as_str(), "198.51.100.7");
+
+    let input = r#"{"platform": "javascript", "user": {"ip_address": "{{auto}}"}}"#;
+    let config = StoreConfig {
+        client_ip: Some("10.0.0.1".to_string()),
+        trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+        ..Default::default()
+    };
+
+    let mut processor = StoreNormalizeProcessor::new(config, None);
+    let event = Annotated::<Event>::from_json(input).unwrap();
+    let event = event.process(&mut processor).0.unwrap();
+
+    let ip_address = &event.user.value().unwrap().ip_address;
+    assert_eq_str!(ip_address.value().unwrap().as_str(), "10.0.0.1");
+    assert_eq_str!(
+        ip_address.meta().iter_remarks().next().unwrap().rule_id(),
+        "remote-addr"
+    );
+}
+
+#[test]
+fn test_invalid_trusted_proxies() {
+    let config: StoreConfig =
+        serde_json::from_str(r#"{"trusted_proxies": ["10.0.0.0/8", "2001:db8::/32"]}"#).unwrap();
+    assert_eq_str!(config.trusted_proxies[1].to_string(), "2001:db8::/32");
+
+    let result = serde_json::from_str::<StoreConfig>(r#"{"trusted_proxies": ["invalid"]}"#);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .starts_with("invalid ip network"));
+}
+
+#[test]
+fn test_ip_anonymization() {
+    let input = r#"{"request": {"env": {"REMOTE_ADDR": "{{auto}}"}}}"#;
+    let config = StoreConfig {
+        client_ip: Some("203.0.113.7".to_string()),
+        ip_anonymization: IpAnonymization::Truncate,
+        ..Default::default()
+    };
+
+    let mut processor = StoreNormalizeProcessor::new(config, None);
+    let event = Annotated::<Event>::from_json(input).unwrap();
+    let event = event.process(&mut processor).0.unwrap();
+
+    let request = event.request.value().unwrap();
+    assert_eq_dbg!(
+        request.env.value().unwrap()["REMOTE_ADDR"].value(),
+        Some(&Value::String("203.0.113.0".to_string()))
+    );
+
+    let ip_address = &event.user.value().unwrap().ip_address;
+    assert_eq_str!(ip_address.value().unwrap().as_str(), "203.0.113.0");
+    assert_eq_dbg!(
+        ip_address.meta().iter_remarks().next().unwrap().ty,
+        RemarkType::Masked
+    );
+}
+
+#[test]
+fn test_js_stack_only_for_javascript() {
+    let normalize = |platform: &str| {
+        let input = r#"{
+            "platform": "PLATFORM",
+            "exception": {"values": [{"type": "Error", "value": "boom\n    at foo@localhost:5432"}]}
+        }"#;
+
+        let mut processor = StoreNormalizeProcessor::new(StoreConfig::default(), None);
+        let event = Annotated::<Event>::from_json(&input.replace("PLATFORM", platform)).unwrap();
+        let event = event.process(&mut processor).0.unwrap();
+        let exceptions = event.exceptions.0.unwrap().values.0.unwrap();
+        exceptions[0].0.clone().unwrap()
+    };
+
+    let exception = normalize("python");
+    assert_eq_str!(
+        exception.value.value().unwrap().as_str(),
+        "boom\n    at foo@localhost:5432"
+    );
+    assert_eq_dbg!(exception.stacktrace.value(), None);
+
+    let exception = normalize("node");
+    assert_eq_str!(exception.value.value().unwrap().as_str(), "boom");
+    assert!(exception.stacktrace.value().is_some());
+}
+
+#[test]
+fn test_demangle_native_frames_only() {
+    let normalize = |platform: &str, function: &str| {
+        let input = r#"{
+            "platform": "PLATFORM",
+            "stacktrace": {"frames": [{"function": "FUNCTION"}]}
+        }"#;
+        let mut processor = StoreNormalizeProcessor::new(StoreConfig::default(), None);
+        let event = Annotated::<Event>::from_json(
+            &input
+                .replace("PLATFORM", platform)
+                .replace("FUNCTION", function),
+        )
+        .unwrap();
+        let event = event.process(&mut processor).0.unwrap();
+        let frames = event.stacktrace.0.unwrap().frames.0.unwrap();
+        let frame = frames[0].0.clone().unwrap();
+        (frame.function.0, frame.symbol.0)
+    };
+
+    assert_eq_dbg!(
+        normalize("native", "_ZN3foo3barEv"),
+        (
+            Some("foo::bar()".to_string()),
+            Some("_ZN3foo3barEv".to_string())
+        )
+    );
+    assert_eq_dbg!(
+        normalize("python", "_ZN3foo3barEv"),
+        (Some("_ZN3foo3barEv".to_string()), None)
+    );
+    assert_eq_dbg!(
+        normalize("python", "_Render"),
+        (Some("_Render".to_string()), None)
+    );
+    assert_eq_dbg!(
+        normalize("native", "_ZoomIn"),
+        (Some("_ZoomIn".to_string()), None)
+    );
+}
+
+#[test]
+fn test_exception_type_from_cpp_exception() {
+    let normalize = |name: &str| {
+        let input = r#"{"exception": {"values": [{"mechanism": {
+            "type": "generic",
+            "meta": {"cpp_exception": {"name": "NAME"}}
+        }}]}}"#;
+
+        let mut processor = StoreNormalizeProcessor::new(StoreConfig::default(), None);
+        let event = Annotated::<Event>::from_json(&input.replace("NAME", name)).unwrap();
+        let event = event.process(&mut processor).0.unwrap();
+        let exceptions = event.exceptions.0.unwrap().values.0.unwrap();
+        exceptions[0].0.clone().unwrap().ty.0.unwrap()
+    };
+
+    assert_eq_str!(normalize("St13runtime_error"), "std::runtime_error");
+
+    let ty = normalize(&format!("{}i", "P".repeat(50_000)));
+    assert!(ty.starts_with("PPP") && ty.ends_with("..."));
+}
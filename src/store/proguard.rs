@@ -0,0 +1,755 @@
+//! Deobfuscation of Java and Android events with ProGuard and R8 mapping files.
+//!
+//! Mapping files are referenced by `proguard` debug images and loaded by their UUID.  Class names
+//! of exceptions and frames are rewritten to their original names, and frames of methods that
+//! have been inlined by the optimizer are expanded into one frame per original method.
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use failure::Fail;
+use lazy_static::lazy_static;
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::processor::{ProcessValue, ProcessingState, Processor};
+use crate::protocol::{DebugImage, DebugMeta, Event, Exception, Frame, Stacktrace, Thread};
+use crate::types::Annotated;
+
+lazy_static! {
+    /// A class mapping: `com.example.Original -> a.b:`.
+    static ref CLASS_RE: Regex =
+        Regex::new(r"^(?P<original>[^\s:]+) -> (?P<obfuscated>[^\s:]+):$").unwrap();
+    /// A method mapping: `1:3:void original(int):10:12 -> a`.
+    static ref METHOD_RE: Regex = Regex::new(
+        r"^(?:(?P<start>\d+):(?P<end>\d+):)?\S+ (?P<original>[^\s(]+)\([^)]*\)(?::(?P<original_start>\d+)(?::(?P<original_end>\d+))?)? -> (?P<obfuscated>\S+)$"
+    ).unwrap();
+    /// A field mapping: `int original -> a`.
+    static ref FIELD_RE: Regex =
+        Regex::new(r"^\S+ (?P<original>[^\s(]+) -> (?P<obfuscated>\S+)$").unwrap();
+}
+
+/// Provides access to ProGuard mapping files.
+pub trait ProguardMappingProvider {
+    /// Returns the contents of the mapping file with the given UUID.
+    fn get_mapping(&self, uuid: Uuid) -> Option<Vec<u8>>;
+}
+
+/// Loads mapping files named `<uuid>.txt` from a local directory.
+#[derive(Debug, Clone)]
+pub struct DirectoryMappingProvider {
+    root: PathBuf,
+}
+
+impl DirectoryMappingProvider {
+    /// Creates a provider for the given directory.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirectoryMappingProvider { root: root.into() }
+    }
+}
+
+impl ProguardMappingProvider for DirectoryMappingProvider {
+    fn get_mapping(&self, uuid: Uuid) -> Option<Vec<u8>> {
+        let filename = format!("{}.txt", uuid);
+        fs::read(self.root.join(filename)).ok()
+    }
+}
+
+/// An error returned when parsing a ProGuard mapping file fails.
+#[derive(Debug, Fail)]
+pub enum ParseProguardMappingError {
+    /// A line that is not indented is not a valid class mapping.
+    #[fail(display = "invalid class mapping on line {}", _0)]
+    InvalidClass(usize),
+
+    /// An indented line is not a valid field or method mapping, or does not follow a class.
+    #[fail(display = "invalid member mapping on line {}", _0)]
+    InvalidMember(usize),
+}
+
+#[derive(Debug, Clone)]
+struct MethodMapping {
+    obfuscated: String,
+    /// The original class, if the method was inlined from another class.
+    original_class: Option<String>,
+    original: String,
+    range: Option<(u64, u64)>,
+    original_start: Option<u64>,
+    original_end: Option<u64>,
+}
+
+impl MethodMapping {
+    fn contains(&self, line: u64) -> bool {
+        self.range
+            .is_some_and(|(start, end)| start <= line && line <= end)
+    }
+
+    fn remap_line(&self, line: Option<u64>) -> Option<u64> {
+        let start = match self.range {
+            Some((start, _)) => start,
+            None => return line,
+        };
+
+        match (self.original_start, self.original_end) {
+            (Some(original_start), Some(original_end)) if original_end != original_start => {
+                let offset = line?.saturating_sub(start);
+                Some(original_start.checked_add(offset).unwrap_or(original_start))
+            }
+            (Some(original_start), _) => Some(original_start),
+            (None, _) => line,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ClassMapping {
+    original: String,
+    fields: HashMap<String, String>,
+    methods: Vec<MethodMapping>,
+}
+
+/// A frame resolved from an obfuscated frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemappedFrame<'a> {
+    /// The original fully qualified class name.
+    pub class: &'a str,
+    /// The original method name.
+    pub method: &'a str,
+    /// The original line number.
+    pub line: Option<u64>,
+}
+
+/// A parsed ProGuard or R8 mapping file.
+#[derive(Debug, Clone, Default)]
+pub struct ProguardMapping {
+    classes: HashMap<String, ClassMapping>,
+}
+
+impl ProguardMapping {
+    /// Parses the text of a mapping file.
+    pub fn parse(text: &str) -> Result<Self, ParseProguardMappingError> {
+        let mut mapping = ProguardMapping::default();
+        let mut current: Option<(String, ClassMapping)> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if !line.starts_with(char::is_whitespace) {
+                let captures = CLASS_RE
+                    .captures(trimmed)
+                    .ok_or(ParseProguardMappingError::InvalidClass(line_number))?;
+
+                if let Some((obfuscated, class)) = current.take() {
+                    mapping.classes.insert(obfuscated, class);
+                }
+
+                let class = ClassMapping {
+                    original: captures["original"].to_string(),
+                    fields: HashMap::new(),
+                    methods: vec![],
+                };
+                current = Some((captures["obfuscated"].to_string(), class));
+                continue;
+            }
+
+            let class = match current {
+                Some((_, ref mut class)) => class,
+                None => return Err(ParseProguardMappingError::InvalidMember(line_number)),
+            };
+
+            if let Some(captures) = METHOD_RE.captures(trimmed) {
+                let number = |name| captures.name(name).and_then(|m| m.as_str().parse().ok());
+                let (original_class, original) = match captures["original"].rfind('.') {
+                    Some(dot) => (
+                        Some(captures["original"][..dot].to_string()),
+                        captures["original"][dot + 1..].to_string(),
+                    ),
+                    None => (None, captures["original"].to_string()),
+                };
+
+                class.methods.push(MethodMapping {
+                    obfuscated: captures["obfuscated"].to_string(),
+                    original_class,
+                    original,
+                    range: number("start").and_then(|start| Some((start, number("end")?))),
+                    original_start: number("original_start"),
+                    original_end: number("original_end"),
+                });
+            } else if let Some(captures) = FIELD_RE.captures(trimmed) {
+                class.fields.insert(
+                    captures["obfuscated"].to_string(),
+                    captures["original"].to_string(),
+                );
+            } else {
+                return Err(ParseProguardMappingError::InvalidMember(line_number));
+            }
+        }
+
+        if let Some((obfuscated, class)) = current {
+            mapping.classes.insert(obfuscated, class);
+        }
+
+        Ok(mapping)
+    }
+
+    /// Returns the original name of an obfuscated class.
+    pub fn remap_class(&self, class: &str) -> Option<&str> {
+        self.classes.get(class).map(|class| class.original.as_str())
+    }
+
+    /// Returns the original name of a field in an obfuscated class.
+    pub fn remap_field(&self, class: &str, field: &str) -> Option<&str> {
+        self.classes
+            .get(class)?
+            .fields
+            .get(field)
+            .map(String::as_str)
+    }
+
+    /// Resolves an obfuscated method and line to the original frames.
+    ///
+    /// If methods were inlined at this line, multiple frames are returned starting with the
+    /// innermost call.  Without a line number, the method is only resolved if its original name
+    /// is unambiguous.  If the class is unknown, no frames are returned.
+    pub fn remap_frame<'a>(
+        &'a self,
+        class: &str,
+        method: &str,
+        line: Option<u64>,
+    ) -> Vec<RemappedFrame<'a>> {
+        let class = match self.classes.get(class) {
+            Some(class) => class,
+            None => return vec![],
+        };
+
+        let make_frame = |mapping: &'a MethodMapping| RemappedFrame {
+            class: mapping.original_class.as_ref().unwrap_or(&class.original),
+            method: &mapping.original,
+            line: mapping.remap_line(line),
+        };
+
+        let candidates: Vec<_> = class
+            .methods
+            .iter()
+            .filter(|mapping| mapping.obfuscated == method)
+            .collect();
+
+        if let Some(line) = line {
+            let frames: Vec<_> = candidates
+                .iter()
+                .filter(|mapping| mapping.contains(line))
+                .map(|mapping| make_frame(mapping))
+                .collect();
+
+            if !frames.is_empty() {
+                return frames;
+            }
+        }
+
+        let mut frames = candidates
+            .iter()
+            .filter(|mapping| line.is_none() || mapping.range.is_none())
+            .map(|mapping| RemappedFrame {
+                line,
+                ..make_frame(mapping)
+            });
+
+        match frames.next() {
+            Some(first) if frames.all(|f| (f.class, f.method) == (first.class, first.method)) => {
+                vec![first]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// Processor that deobfuscates Java frames and exception types with ProGuard mapping files.
+///
+/// Frames with a `module` and `function` are rewritten to the original class, method and line.
+/// Frames that contain inlined methods are expanded into one frame per method.  The obfuscated
+/// stack trace is retained in `raw_stacktrace`, while the top-level stack trace of the event is
+/// rewritten in place.  Mapping files that cannot be loaded are flagged with errors in
+/// `DebugMeta.images`.
+pub struct ProguardProcessor {
+    mappings: Vec<ProguardMapping>,
+    /// Errors to attach to images, by their position in `DebugMeta.images`.
+    errors: Vec<(usize, String)>,
+}
+
+impl ProguardProcessor {
+    /// Creates a processor from the given mapping files.
+    pub fn new(mappings: Vec<ProguardMapping>) -> Self {
+        ProguardProcessor {
+            mappings,
+            errors: vec![],
+        }
+    }
+
+    /// Creates a processor for an event, unless the event has no ProGuard images.
+    pub fn from_event(
+        provider: &dyn ProguardMappingProvider,
+        event: &Annotated<Event>,
+    ) -> Option<Self> {
+        let images = event.value()?.debug_meta.value()?.images.value()?;
+        let mut processor = ProguardProcessor::new(vec![]);
+        let mut has_images = false;
+
+        for (position, image) in images.iter().enumerate() {
+            let uuid = match image.value() {
+                Some(DebugImage::Proguard(image)) => match image.uuid.value() {
+                    Some(&uuid) => uuid,
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            has_images = true;
+            let data = match provider.get_mapping(uuid) {
+                Some(data) => data,
+                None => {
+                    let error = "proguard mapping file not found".to_string();
+                    processor.errors.push((position, error));
+                    continue;
+                }
+            };
+
+            match ProguardMapping::parse(&String::from_utf8_lossy(&data)) {
+                Ok(mapping) => processor.mappings.push(mapping),
+                Err(error) => processor.errors.push((position, error.to_string())),
+            }
+        }
+
+        if has_images {
+            Some(processor)
+        } else {
+            None
+        }
+    }
+
+    fn remap_class(&self, class: &str) -> Option<&str> {
+        self.mappings
+            .iter()
+            .filter_map(|mapping| mapping.remap_class(class))
+            .next()
+    }
+
+    /// Returns the deobfuscated frames for a frame, or `None` if it cannot be resolved.
+    fn remap_frame(&self, frame: &Frame) -> Option<Vec<Frame>> {
+        let class = frame.module.value()?;
+        let method = frame.function.value()?;
+        let line = frame.line.value().cloned();
+
+        let remapped = self
+            .mappings
+            .iter()
+            .map(|mapping| mapping.remap_frame(class, method, line))
+            .find(|frames| !frames.is_empty());
+
+        let remapped = match remapped {
+            Some(remapped) => remapped,
+            None => {
+                let original = self.remap_class(class)?;
+                let mut frame = frame.clone();
+                frame.module = Annotated::new(original.to_string());
+                return Some(vec![frame]);
+            }
+        };
+
+        // Mappings list the innermost call first, while stack traces start with the outermost.
+        let frames = remapped
+            .iter()
+            .rev()
+            .map(|remapped| {
+                let mut frame = frame.clone();
+                frame.module = Annotated::new(remapped.class.to_string());
+                frame.function = Annotated::new(remapped.method.to_string());
+                frame.line = remapped.line.map_or_else(Annotated::empty, Annotated::new);
+                frame
+            })
+            .collect();
+
+        Some(frames)
+    }
+
+    /// Deobfuscates a stack trace and returns whether any frame was changed.
+    fn remap_stacktrace(&self, stacktrace: &mut Annotated<Stacktrace>) -> bool {
+        let frames = match stacktrace
+            .value_mut()
+            .as_mut()
+            .and_then(|stacktrace| stacktrace.frames.value_mut().as_mut())
+        {
+            Some(frames) => frames,
+            None => return false,
+        };
+
+        let mut changed = false;
+        let mut remapped_frames = Vec::with_capacity(frames.len());
+
+        for frame in frames.drain(..) {
+            match frame.value().and_then(|f| self.remap_frame(f)) {
+                Some(remapped) => {
+                    changed = true;
+                    remapped_frames.extend(remapped.into_iter().map(Annotated::new));
+                }
+                None => remapped_frames.push(frame),
+            }
+        }
+
+        *frames = remapped_frames;
+        changed
+    }
+
+    /// Deobfuscates a stack trace and retains the obfuscated stack trace if it was changed.
+    fn process_with_raw(
+        &self,
+        stacktrace: &mut Annotated<Stacktrace>,
+        raw_stacktrace: &mut Annotated<Stacktrace>,
+    ) {
+        let original = stacktrace.clone();
+        if self.remap_stacktrace(stacktrace) && raw_stacktrace.value().is_none() {
+            *raw_stacktrace = original;
+        }
+    }
+
+    fn remap_exception_type(&self, exception: &mut Exception) {
+        let ty = match exception.ty.value() {
+            Some(ty) => ty,
+            None => return,
+        };
+
+        let class = match exception.module.value() {
+            Some(module) => format!("{}.{}", module, ty),
+            None => ty.clone(),
+        };
+
+        let original = match self.remap_class(&class) {
+            Some(original) => original,
+            None => return,
+        };
+
+        match (exception.module.value(), original.rfind('.')) {
+            (Some(_), Some(dot)) => {
+                exception.module = Annotated::new(original[..dot].to_string());
+                exception.ty = Annotated::new(original[dot + 1..].to_string());
+            }
+            (Some(_), None) => {
+                exception.module = Annotated::empty();
+                exception.ty = Annotated::new(original.to_string());
+            }
+            (None, _) => exception.ty = Annotated::new(original.to_string()),
+        }
+    }
+}
+
+impl Processor for ProguardProcessor {
+    fn process_event(
+        &mut self,
+        event: Annotated<Event>,
+        state: ProcessingState,
+    ) -> Annotated<Event> {
+        let mut event = ProcessValue::process_child_values(event, self, state);
+
+        // The top-level stack trace has no raw counterpart to retain the obfuscated frames.
+        if let Some(ref mut event) = event.0 {
+            self.remap_stacktrace(&mut event.stacktrace);
+        }
+
+        event
+    }
+
+    fn process_exception(
+        &mut self,
+        mut exception: Annotated<Exception>,
+        _state: ProcessingState,
+    ) -> Annotated<Exception> {
+        if let Some(ref mut exception) = exception.0 {
+            self.remap_exception_type(exception);
+            self.process_with_raw(&mut exception.stacktrace, &mut exception.raw_stacktrace);
+        }
+
+        exception
+    }
+
+    fn process_thread(
+        &mut self,
+        mut thread: Annotated<Thread>,
+        _state: ProcessingState,
+    ) -> Annotated<Thread> {
+        if let Some(ref mut thread) = thread.0 {
+            self.process_with_raw(&mut thread.stacktrace, &mut thread.raw_stacktrace);
+        }
+
+        thread
+    }
+
+    fn process_debug_meta(
+        &mut self,
+        mut debug_meta: Annotated<DebugMeta>,
+        _state: ProcessingState,
+    ) -> Annotated<DebugMeta> {
+        let images = debug_meta
+            .value_mut()
+            .as_mut()
+            .and_then(|debug_meta| debug_meta.images.value_mut().as_mut());
+
+        if let Some(images) = images {
+            for (position, error) in self.errors.drain(..) {
+                if let Some(image) = images.get_mut(position) {
+                    image.meta_mut().add_error(error, None);
+                }
+            }
+        }
+
+        debug_meta
+    }
+}
+
+#[cfg(test)]
+static TEST_MAPPING: &str = "\
+# compiler: R8
+io.sentry.sample.MainActivity -> io.sentry.sample.a:
+    java.lang.String tag -> a
+    1:1:void <init>():12:12 -> <init>
+    2:3:void onCreate(android.os.Bundle):20:21 -> onCreate
+    4:4:void io.sentry.sample.Util.crash():40:40 -> a
+    4:4:void bar():30 -> a
+    4:4:void onClick(android.view.View):25 -> a
+    5:5:void foo():50 -> a
+io.sentry.sample.CustomException -> io.sentry.sample.b:
+    void <init>(java.lang.String) -> <init>
+";
+
+#[test]
+fn test_parse_mapping() {
+    let mapping = ProguardMapping::parse(TEST_MAPPING).unwrap();
+
+    assert_eq!(
+        mapping.remap_class("io.sentry.sample.a"),
+        Some("io.sentry.sample.MainActivity")
+    );
+    assert_eq!(mapping.remap_class("io.sentry.sample.c"), None);
+    assert_eq!(mapping.remap_field("io.sentry.sample.a", "a"), Some("tag"));
+
+    assert_eq_dbg!(
+        mapping.remap_frame("io.sentry.sample.a", "onCreate", Some(3)),
+        vec![RemappedFrame {
+            class: "io.sentry.sample.MainActivity",
+            method: "onCreate",
+            line: Some(21),
+        }]
+    );
+    assert_eq_dbg!(
+        mapping.remap_frame("io.sentry.sample.a", "a", Some(4)),
+        vec![
+            RemappedFrame {
+                class: "io.sentry.sample.Util",
+                method: "crash",
+                line: Some(40),
+            },
+            RemappedFrame {
+                class: "io.sentry.sample.MainActivity",
+                method: "bar",
+                line: Some(30),
+            },
+            RemappedFrame {
+                class: "io.sentry.sample.MainActivity",
+                method: "onClick",
+                line: Some(25),
+            },
+        ]
+    );
+    assert_eq_dbg!(
+        mapping.remap_frame("io.sentry.sample.b", "<init>", None),
+        vec![RemappedFrame {
+            class: "io.sentry.sample.CustomException",
+            method: "<init>",
+            line: None,
+        }]
+    );
+    assert_eq_dbg!(mapping.remap_frame("io.sentry.sample.a", "a", None), vec![]);
+}
+
+#[test]
+fn test_remap_line_overflow() {
+    let mapping = MethodMapping {
+        obfuscated: "a".to_string(),
+        original_class: None,
+        original: "run".to_string(),
+        range: Some((1, u64::MAX)),
+        original_start: Some(u64::MAX - 1),
+        original_end: Some(u64::MAX),
+    };
+
+    assert_eq!(mapping.remap_line(Some(2)), Some(u64::MAX));
+    assert_eq!(mapping.remap_line(Some(10)), Some(u64::MAX - 1));
+}
+
+#[test]
+fn test_parse_invalid_mapping() {
+    assert_eq!(
+        ProguardMapping::parse("    int a -> b")
+            .unwrap_err()
+            .to_string(),
+        "invalid member mapping on line 1"
+    );
+    assert_eq!(
+        ProguardMapping::parse("a.A -> a:\n    int a -> b\nfoo\n")
+            .unwrap_err()
+            .to_string(),
+        "invalid class mapping on line 3"
+    );
+}
+
+#[test]
+fn test_process_event() {
+    use crate::protocol::{ProguardDebugImage, Values};
+
+    let uuid: Uuid = "395835f4-03e0-4436-80d3-136f0749a893".parse().unwrap();
+    let root = std::env::temp_dir().join(format!("proguard-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join(format!("{}.txt", uuid)), TEST_MAPPING).unwrap();
+    let provider = DirectoryMappingProvider::new(&root);
+
+    let frame = |module: &str, function: &str, line: u64| {
+        Annotated::new(Frame {
+            module: Annotated::new(module.to_string()),
+            function: Annotated::new(function.to_string()),
+            filename: Annotated::new("SourceFile".to_string()),
+            line: Annotated::new(line),
+            ..Default::default()
+        })
+    };
+
+    let stacktrace = Annotated::new(Stacktrace {
+        frames: Annotated::new(vec![
+            frame("android.view.View", "performClick", 7),
+            frame("io.sentry.sample.a", "a", 4),
+        ]),
+        ..Default::default()
+    });
+
+    let event = Annotated::new(Event {
+        exceptions: Annotated::new(Values::new(vec![Annotated::new(Exception {
+            ty: Annotated::new("b".to_string()),
+            module: Annotated::new("io.sentry.sample".to_string()),
+            stacktrace: stacktrace.clone(),
+            ..Default::default()
+        })])),
+        debug_meta: Annotated::new(DebugMeta {
+            images: Annotated::new(vec![
+                Annotated::new(DebugImage::Proguard(Box::new(ProguardDebugImage {
+                    uuid: Annotated::new(uuid),
+                    ..Default::default()
+                }))),
+                Annotated::new(DebugImage::Proguard(Box::new(ProguardDebugImage {
+                    uuid: Annotated::new(Uuid::nil()),
+                    ..Default::default()
+                }))),
+            ]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    let mut processor = ProguardProcessor::from_event(&provider, &event).unwrap();
+    let event = processor.process_event(event, ProcessingState::root());
+    fs::remove_dir_all(root).unwrap();
+
+    let event = event.value().unwrap();
+    let exception = event
+        .exceptions
+        .value()
+        .and_then(|exceptions| exceptions.values.value())
+        .and_then(|values| values[0].value())
+        .unwrap();
+
+    assert_eq_dbg!(exception.ty.value(), Some(&"CustomException".to_string()));
+    assert_eq_dbg!(
+        exception.module.value(),
+        Some(&"io.sentry.sample".to_string())
+    );
+    assert_eq_dbg!(exception.raw_stacktrace, stacktrace);
+
+    let frames = exception
+        .stacktrace
+        .value()
+        .and_then(|stacktrace| stacktrace.frames.value())
+        .unwrap();
+
+    assert_eq_dbg!(
+        frames.clone(),
+        vec![
+            frame("android.view.View", "performClick", 7),
+            frame("io.sentry.sample.MainActivity", "onClick", 25),
+            frame("io.sentry.sample.MainActivity", "bar", 30),
+            frame("io.sentry.sample.Util", "crash", 40),
+        ]
+    );
+
+    let images = event
+        .debug_meta
+        .value()
+        .and_then(|debug_meta| debug_meta.images.value())
+        .unwrap();
+    assert!(images[0].is_valid());
+    assert_eq_dbg!(
+        images[1].meta().iter_errors().collect::<Vec<_>>(),
+        vec!["proguard mapping file not found"]
+    );
+}
+
+#[test]
+fn test_process_event_stacktrace() {
+    use crate::protocol::ProguardDebugImage;
+
+    let uuid: Uuid = "395835f4-03e0-4436-80d3-136f0749a893".parse().unwrap();
+    let root = std::env::temp_dir().join(format!("proguard-stacktrace-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join(format!("{}.txt", uuid)), TEST_MAPPING).unwrap();
+    let provider = DirectoryMappingProvider::new(&root);
+
+    let frame = |module: &str, function: &str, line: u64| {
+        Annotated::new(Frame {
+            module: Annotated::new(module.to_string()),
+            function: Annotated::new(function.to_string()),
+            line: Annotated::new(line),
+            ..Default::default()
+        })
+    };
+
+    let event = Annotated::new(Event {
+        stacktrace: Annotated::new(Stacktrace {
+            frames: Annotated::new(vec![frame("io.sentry.sample.a", "onCreate", 3)]),
+            ..Default::default()
+        }),
+        debug_meta: Annotated::new(DebugMeta {
+            images: Annotated::new(vec![Annotated::new(DebugImage::Proguard(Box::new(
+                ProguardDebugImage {
+                    uuid: Annotated::new(uuid),
+                    ..Default::default()
+                },
+            )))]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    let mut processor = ProguardProcessor::from_event(&provider, &event).unwrap();
+    let event = processor.process_event(event, ProcessingState::root());
+    fs::remove_dir_all(root).unwrap();
+
+    let frames = event
+        .value()
+        .and_then(|event| event.stacktrace.value())
+        .and_then(|stacktrace| stacktrace.frames.value())
+        .unwrap();
+
+    assert_eq_dbg!(
+        frames.clone(),
+        vec![frame("io.sentry.sample.MainActivity", "onCreate", 21)]
+    );
+}
@@ -0,0 +1,349 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::protocol::{Exception, Frame, Mechanism, Stacktrace, Values};
+use crate::store::stacktrace::process_non_raw_frame;
+use crate::types::{Annotated, Object, Value};
+
+const TRACEBACK_HEADER: &str = "Traceback (most recent call last):";
+const CONTEXT_SEPARATOR: &str =
+    "During handling of the above exception, another exception occurred:";
+const CAUSE_SEPARATOR: &str =
+    "The above exception was the direct cause of the following exception:";
+
+lazy_static! {
+    /// A frame: `File "x.py", line 10, in func`.  Syntax errors omit the function.
+    static ref FRAME_RE: Regex =
+        Regex::new(r#"^\s+File "(?P<filename>[^"]+)", line (?P<line>\d+)(?:, in (?P<function>.+))?$"#)
+            .unwrap();
+    /// The final line of a traceback: `module.ExceptionType: message`.
+    static ref EXCEPTION_RE: Regex =
+        Regex::new(r"^(?P<ty>[A-Za-z_][\w.]*)(?::(?: (?P<value>.*))?)?$").unwrap();
+    /// Caret lines pointing at the error location, including the fine grained markers of
+    /// Python 3.11.
+    static ref CARET_RE: Regex = Regex::new(r"^\s*[~^]+\s*$").unwrap();
+}
+
+/// How an exception relates to the exception printed after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Chaining {
+    /// The exception was raised from this one (`raise ... from ...`).
+    Cause,
+    /// The exception was raised while handling this one.
+    Context,
+}
+
+impl Chaining {
+    fn as_str(self) -> &'static str {
+        match self {
+            Chaining::Cause => "__cause__",
+            Chaining::Context => "__context__",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ParsedException {
+    exception: Exception,
+    frames: Vec<Frame>,
+    chaining: Option<Chaining>,
+}
+
+fn get_indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn make_exception(ty: &str, value: Option<&str>) -> Exception {
+    let (module, ty) = match ty.rfind('.') {
+        Some(index) => (Some(&ty[..index]), &ty[index + 1..]),
+        None => (None, ty),
+    };
+
+    Exception {
+        ty: Annotated::new(ty.to_string()),
+        module: module.map_or_else(Annotated::empty, |module| Annotated::new(module.to_string())),
+        value: value
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map_or_else(Annotated::empty, |value| Annotated::new(value.to_string().into())),
+        ..Default::default()
+    }
+}
+
+fn make_mechanism(chaining: Chaining) -> Mechanism {
+    let mut data = Object::new();
+    data.insert(
+        "source".to_string(),
+        Annotated::new(Value::String(chaining.as_str().to_string())),
+    );
+
+    Mechanism {
+        ty: Annotated::new("chained".to_string()),
+        data: Annotated::new(data),
+        ..Default::default()
+    }
+}
+
+fn into_exception(parsed: ParsedException) -> Exception {
+    let mut exception = parsed.exception;
+
+    if !parsed.frames.is_empty() {
+        let mut frames: Vec<_> = parsed.frames.into_iter().map(Annotated::new).collect();
+        for frame in &mut frames {
+            process_non_raw_frame(frame);
+        }
+
+        exception.stacktrace = Annotated::new(Stacktrace {
+            frames: Annotated::new(frames),
+            ..Default::default()
+        });
+    }
+
+    if let Some(chaining) = parsed.chaining {
+        exception.mechanism = Annotated::new(make_mechanism(chaining));
+    }
+
+    exception
+}
+
+/// Parses CPython traceback text into a list of exceptions.
+///
+/// Chained exceptions separated by `During handling of the above exception...` or `The above
+/// exception was the direct cause...` are returned in the order they were printed, which matches
+/// Sentry order with the final exception last.  Every exception that caused another one carries a
+/// `chained` mechanism whose `source` data is `__cause__` or `__context__`.
+///
+/// Frames have their source line in `current_line`.  The location of a `SyntaxError` is added as
+/// the top frame, with the column taken from the caret line.  Returns `None` if the text does not
+/// contain an exception.
+pub fn parse_python_traceback(text: &str) -> Option<Values<Exception>> {
+    let mut parsed: Vec<ParsedException> = vec![];
+    let mut frames: Vec<Frame> = vec![];
+    let mut in_traceback = false;
+    let mut in_message = false;
+    let mut source_indent = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+
+        if trimmed == TRACEBACK_HEADER {
+            in_traceback = true;
+            in_message = false;
+            frames.clear();
+            continue;
+        }
+
+        let chaining = match trimmed {
+            CONTEXT_SEPARATOR => Some(Chaining::Context),
+            CAUSE_SEPARATOR => Some(Chaining::Cause),
+            _ => None,
+        };
+        if let Some(chaining) = chaining {
+            if let Some(last) = parsed.last_mut() {
+                last.chaining = Some(chaining);
+            }
+            // Exceptions without a traceback are printed without a header.
+            in_traceback = true;
+            in_message = false;
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            in_message = false;
+            continue;
+        }
+
+        if let Some(captures) = FRAME_RE.captures(line) {
+            frames.push(Frame {
+                abs_path: Annotated::new(captures["filename"].to_string()),
+                line: captures["line"]
+                    .parse()
+                    .ok()
+                    .map_or_else(Annotated::empty, Annotated::new),
+                function: captures
+                    .name("function")
+                    .map_or_else(Annotated::empty, |function| {
+                        Annotated::new(function.as_str().to_string())
+                    }),
+                ..Default::default()
+            });
+            in_traceback = true;
+            in_message = false;
+            source_indent = None;
+            continue;
+        }
+
+        if !in_traceback && !in_message {
+            continue;
+        }
+
+        if get_indent(line) > 0 {
+            if let Some(frame) = frames.last_mut() {
+                if CARET_RE.is_match(line) {
+                    // Only syntax errors report the column, other frames use carets to
+                    // highlight the failing expression.
+                    if let (None, Some(indent)) = (frame.function.value(), source_indent) {
+                        if let Some(caret) = line.find('^') {
+                            let column = caret.saturating_sub(indent) as u64 + 1;
+                            frame.column = Annotated::new(column);
+                        }
+                    }
+                } else if frame.current_line.value().is_none() && !trimmed.starts_with('[') {
+                    frame.current_line = Annotated::new(trimmed.to_string());
+                    source_indent = Some(get_indent(line));
+                }
+            }
+            continue;
+        }
+
+        if in_traceback {
+            if let Some(captures) = EXCEPTION_RE.captures(trimmed) {
+                let value = captures.name("value").map(|value| value.as_str());
+                parsed.push(ParsedException {
+                    exception: make_exception(&captures["ty"], value),
+                    frames: std::mem::take(&mut frames),
+                    chaining: None,
+                });
+                in_traceback = false;
+                in_message = true;
+            }
+            continue;
+        }
+
+        // Continuation of a multi-line exception message.
+        if let Some(last) = parsed.last_mut() {
+            let value = match last.exception.value.value() {
+                Some(value) => format!("{}\n{}", value.as_str(), trimmed),
+                None => trimmed.to_string(),
+            };
+            last.exception.value = Annotated::new(value.into());
+        }
+    }
+
+    if parsed.is_empty() {
+        return None;
+    }
+
+    let exceptions = parsed
+        .into_iter()
+        .map(into_exception)
+        .map(Annotated::new)
+        .collect();
+
+    Some(Values::new(exceptions))
+}
+
+#[cfg(test)]
+fn get_exceptions(values: &Values<Exception>) -> Vec<&Exception> {
+    values
+        .values
+        .value()
+        .unwrap()
+        .iter()
+        .map(|exception| exception.value().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_parse_chained() {
+    let text = r#"Traceback (most recent call last):
+  File "/app/config.py", line 10, in load
+    return data["key"]
+KeyError: 'key'
+
+The above exception was the direct cause of the following exception:
+
+Traceback (most recent call last):
+  File "/app/main.py", line 3, in <module>
+    main()
+  File "/app/main.py", line 8, in main
+    config = load()
+             ^^^^^^
+app.errors.ConfigError: invalid config
+
+During handling of the above exception, another exception occurred:
+
+Traceback (most recent call last):
+  File "/app/main.py", line 12, in <module>
+    report()
+RuntimeError
+"#;
+
+    let values = parse_python_traceback(text).unwrap();
+    let exceptions = get_exceptions(&values);
+    assert_eq!(exceptions.len(), 3);
+
+    let key_error = exceptions[0];
+    assert_eq_dbg!(key_error.ty.value(), Some(&"KeyError".to_string()));
+    assert_eq_dbg!(
+        key_error.value.value().map(|value| value.as_str()),
+        Some("'key'")
+    );
+    let mechanism = key_error.mechanism.value().unwrap();
+    assert_eq_dbg!(mechanism.ty.value(), Some(&"chained".to_string()));
+    assert_eq_dbg!(
+        mechanism.data.value().unwrap().get("source"),
+        Some(&Annotated::new(Value::String("__cause__".to_string())))
+    );
+
+    let config_error = exceptions[1];
+    assert_eq_dbg!(config_error.ty.value(), Some(&"ConfigError".to_string()));
+    assert_eq_dbg!(config_error.module.value(), Some(&"app.errors".to_string()));
+    let frames = config_error
+        .stacktrace
+        .value()
+        .unwrap()
+        .frames
+        .value()
+        .unwrap();
+    assert_eq!(frames.len(), 2);
+    let frame = frames[1].value().unwrap();
+    assert_eq_dbg!(frame.function.value(), Some(&"main".to_string()));
+    assert_eq_dbg!(frame.filename.value(), Some(&"/app/main.py".to_string()));
+    assert_eq_dbg!(frame.line.value(), Some(&8));
+    assert_eq_dbg!(frame.current_line.value(), Some(&"config = load()".to_string()));
+    assert_eq_dbg!(frame.column.value(), None);
+    assert_eq_dbg!(
+        config_error.mechanism.value().unwrap().data.value().unwrap().get("source"),
+        Some(&Annotated::new(Value::String("__context__".to_string())))
+    );
+
+    let runtime_error = exceptions[2];
+    assert_eq_dbg!(runtime_error.ty.value(), Some(&"RuntimeError".to_string()));
+    assert_eq_dbg!(runtime_error.value.value(), None);
+    assert_eq_dbg!(runtime_error.mechanism.value(), None);
+}
+
+#[test]
+fn test_parse_syntax_error() {
+    let text = r#"Traceback (most recent call last):
+  File "/app/main.py", line 1, in <module>
+    import broken
+  File "/app/broken.py", line 3
+    foo(1,
+       ^
+SyntaxError: '(' was never closed
+"#;
+
+    let values = parse_python_traceback(text).unwrap();
+    let exceptions = get_exceptions(&values);
+    let frames = exceptions[0]
+        .stacktrace
+        .value()
+        .unwrap()
+        .frames
+        .value()
+        .unwrap();
+
+    let frame = frames[1].value().unwrap();
+    assert_eq_dbg!(frame.function.value(), None);
+    assert_eq_dbg!(frame.abs_path.value(), Some(&"/app/broken.py".to_string()));
+    assert_eq_dbg!(frame.line.value(), Some(&3));
+    assert_eq_dbg!(frame.column.value(), Some(&4));
+    assert_eq_dbg!(frame.current_line.value(), Some(&"foo(1,".to_string()));
+}
+
+#[test]
+fn test_parse_no_traceback() {
+    assert_eq!(parse_python_traceback("ValueError: not in a traceback"), None);
+}
@@ -0,0 +1,322 @@
+use crate::protocol::{Addr, Context, DebugImage, Event, Frame, Stacktrace};
+use crate::types::Annotated;
+
+/// The family of a CPU architecture, which determines registers and instruction layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuFamily {
+    /// 32-bit x86.
+    X86,
+    /// 64-bit x86.
+    X86_64,
+    /// 32-bit ARM, including Thumb.
+    Arm32,
+    /// 64-bit ARM.
+    Arm64,
+    /// 32-bit and 64-bit MIPS.
+    Mips,
+    /// An architecture that is not known.
+    Unknown,
+}
+
+impl CpuFamily {
+    /// Parses the family from an architecture name, such as `x86_64` or `armv7s`.
+    pub fn from_arch(arch: &str) -> Self {
+        match &*arch.to_lowercase() {
+            "x86" | "i386" | "i486" | "i586" | "i686" => CpuFamily::X86,
+            "x86_64" | "x86_64h" | "amd64" => CpuFamily::X86_64,
+            "arm64" | "arm64e" | "arm64_32" | "arm64v8" | "aarch64" => CpuFamily::Arm64,
+            "mips" | "mipsel" | "mips64" | "mips64el" => CpuFamily::Mips,
+            arch if arch.starts_with("arm") => CpuFamily::Arm32,
+            _ => CpuFamily::Unknown,
+        }
+    }
+
+    /// Returns the name of the register holding the instruction pointer.
+    pub fn ip_register_name(self) -> Option<&'static str> {
+        Some(match self {
+            CpuFamily::X86 => "eip",
+            CpuFamily::X86_64 => "rip",
+            CpuFamily::Arm32 | CpuFamily::Arm64 | CpuFamily::Mips => "pc",
+            CpuFamily::Unknown => return None,
+        })
+    }
+
+    /// Returns the alignment of instructions in bytes.
+    ///
+    /// Instructions on x86 have variable length.  Thumb instructions on 32-bit ARM are aligned to
+    /// two bytes.
+    pub fn instruction_alignment(self) -> Option<u64> {
+        Some(match self {
+            CpuFamily::X86 | CpuFamily::X86_64 => 1,
+            CpuFamily::Arm32 => 2,
+            CpuFamily::Arm64 | CpuFamily::Mips => 4,
+            CpuFamily::Unknown => return None,
+        })
+    }
+
+    /// Returns whether the register name is valid for this architecture.
+    ///
+    /// Names are compared case-insensitively.  All names are valid for unknown architectures.
+    pub fn is_valid_register(self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        let name = name.as_str();
+
+        match self {
+            CpuFamily::X86 => matches!(
+                name,
+                "eax" | "ebx" | "ecx" | "edx" | "esi" | "edi" | "ebp" | "esp" | "eip"
+                    | "eflags" | "cs" | "ds" | "es" | "fs" | "gs" | "ss"
+            ),
+            CpuFamily::X86_64 => match name {
+                "rax" | "rbx" | "rcx" | "rdx" | "rsi" | "rdi" | "rbp" | "rsp" | "rip"
+                | "rflags" | "cs" | "ds" | "es" | "fs" | "gs" | "ss" | "fs_base" | "gs_base"
+                | "trapno" | "err" | "faultvaddr" => true,
+                _ => is_numbered_register(name, "r", 8, 15),
+            },
+            CpuFamily::Arm32 => match name {
+                "fp" | "ip" | "sp" | "lr" | "pc" | "cpsr" | "far" | "exception" => true,
+                _ => is_numbered_register(name, "r", 0, 15),
+            },
+            CpuFamily::Arm64 => match name {
+                "fp" | "lr" | "sp" | "pc" | "cpsr" | "far" | "esr" => true,
+                _ => is_numbered_register(name, "x", 0, 30),
+            },
+            CpuFamily::Mips => match name {
+                "zero" | "at" | "v0" | "v1" | "k0" | "k1" | "gp" | "sp" | "fp" | "ra" | "pc"
+                | "hi" | "lo" => true,
+                _ => {
+                    is_numbered_register(name, "a", 0, 3)
+                        || is_numbered_register(name, "t", 0, 9)
+                        || is_numbered_register(name, "s", 0, 8)
+                        || is_numbered_register(name, "r", 0, 31)
+                }
+            },
+            CpuFamily::Unknown => true,
+        }
+    }
+
+    /// Returns the address of the call instruction for a return address.
+    ///
+    /// Return addresses point to the instruction after the call, which may belong to a different
+    /// function or line.  On x86, the previous byte is used since instructions have variable
+    /// length.  On other architectures, the address is aligned down and moved back by one
+    /// instruction.
+    pub fn get_call_address(self, return_addr: u64) -> u64 {
+        match self.instruction_alignment() {
+            Some(1) | None => return_addr.saturating_sub(1),
+            Some(alignment) => (return_addr & !(alignment - 1)).saturating_sub(alignment),
+        }
+    }
+}
+
+/// Checks for names like `r12` with a number in the given inclusive range.
+fn is_numbered_register(name: &str, prefix: &str, min: u32, max: u32) -> bool {
+    name.strip_prefix(prefix)
+        .filter(|number| !number.starts_with('0') || *number == "0")
+        .and_then(|number| number.parse::<u32>().ok())
+        .is_some_and(|number| number >= min && number <= max)
+}
+
+/// Returns the address to symbolicate for a frame that called the next frame.
+///
+/// This must not be used for the top frame of a crashed thread, whose instruction address points
+/// to the faulting instruction instead of a return address.
+pub fn get_caller_address(frame: &Frame, family: CpuFamily) -> Option<Addr> {
+    let addr = frame.instruction_addr.value()?;
+    Some(Addr(family.get_call_address(addr.0)))
+}
+
+/// Returns the architecture of an event from the device context or the debug images.
+pub fn get_event_arch(event: &Event) -> Option<&str> {
+    let device_arch = event
+        .contexts
+        .value()
+        .and_then(|contexts| contexts.get("device"))
+        .and_then(|context| match context.value() {
+            Some(Context::Device(device)) => device.arch.value(),
+            _ => None,
+        });
+
+    if device_arch.is_some() {
+        return device_arch.map(String::as_str);
+    }
+
+    let images = event
+        .debug_meta
+        .value()
+        .and_then(|debug_meta| debug_meta.images.value())?;
+
+    images
+        .iter()
+        .filter_map(|image| match image.value()? {
+            DebugImage::Apple(image) => image.arch.value(),
+            DebugImage::Symbolic(image) => image.arch.value(),
+            DebugImage::Elf(image) | DebugImage::Pe(image) | DebugImage::Wasm(image) => {
+                image.arch.value()
+            }
+            _ => None,
+        })
+        .next()
+        .map(String::as_str)
+}
+
+/// Validates register names and fills the instruction address of the top frame.
+///
+/// Registers that do not exist on the architecture receive an error.  If the top frame has no
+/// instruction address, it is taken from the instruction pointer register.
+pub fn normalize_registers(stacktrace: &mut Stacktrace, arch: &str) {
+    let family = CpuFamily::from_arch(arch);
+    if family == CpuFamily::Unknown {
+        return;
+    }
+
+    let registers = match stacktrace.registers.value_mut() {
+        Some(registers) => registers,
+        None => return,
+    };
+
+    for (name, value) in registers.iter_mut() {
+        if !family.is_valid_register(name) {
+            let error = format!("invalid register for architecture {}", arch);
+            value.meta_mut().add_error(error, None);
+        }
+    }
+
+    let instruction_addr = family
+        .ip_register_name()
+        .and_then(|name| registers.get(name))
+        .and_then(Annotated::value)
+        .map(|value| Addr(value.0));
+
+    let top_frame = stacktrace
+        .frames
+        .value_mut()
+        .as_mut()
+        .and_then(|frames| frames.last_mut())
+        .and_then(|frame| frame.value_mut().as_mut());
+
+    if let (Some(frame), Some(addr)) = (top_frame, instruction_addr) {
+        if frame.instruction_addr.value().is_none() {
+            frame.instruction_addr = Annotated::new(addr);
+        }
+    }
+}
+
+/// Normalizes the registers of all stack traces in the event.
+pub fn normalize_event_registers(event: &mut Event) {
+    let arch = match get_event_arch(event) {
+        Some(arch) => arch.to_string(),
+        None => return,
+    };
+
+    let mut stacktraces = vec![&mut event.stacktrace];
+
+    if let Some(values) = event.exceptions.value_mut() {
+        if let Some(exceptions) = values.values.value_mut() {
+            for exception in exceptions.iter_mut().filter_map(|e| e.value_mut().as_mut()) {
+                stacktraces.push(&mut exception.stacktrace);
+            }
+        }
+    }
+
+    if let Some(values) = event.threads.value_mut() {
+        if let Some(threads) = values.values.value_mut() {
+            for thread in threads.iter_mut().filter_map(|t| t.value_mut().as_mut()) {
+                stacktraces.push(&mut thread.stacktrace);
+            }
+        }
+    }
+
+    for stacktrace in stacktraces {
+        if let Some(stacktrace) = stacktrace.value_mut() {
+            normalize_registers(stacktrace, &arch);
+        }
+    }
+}
+
+#[test]
+fn test_cpu_family() {
+    assert_eq!(CpuFamily::from_arch("x86_64"), CpuFamily::X86_64);
+    assert_eq!(CpuFamily::from_arch("i686"), CpuFamily::X86);
+    assert_eq!(CpuFamily::from_arch("armv7s"), CpuFamily::Arm32);
+    assert_eq!(CpuFamily::from_arch("arm64e"), CpuFamily::Arm64);
+    assert_eq!(CpuFamily::from_arch("mips64el"), CpuFamily::Mips);
+    assert_eq!(CpuFamily::from_arch("ppc"), CpuFamily::Unknown);
+
+    assert!(CpuFamily::X86_64.is_valid_register("R15"));
+    assert!(!CpuFamily::X86_64.is_valid_register("r16"));
+    assert!(!CpuFamily::X86_64.is_valid_register("eax"));
+    assert!(CpuFamily::Arm64.is_valid_register("x29"));
+    assert!(!CpuFamily::Arm64.is_valid_register("x31"));
+    assert!(!CpuFamily::Arm64.is_valid_register("x01"));
+    assert!(CpuFamily::Arm32.is_valid_register("cpsr"));
+    assert!(CpuFamily::Mips.is_valid_register("t9"));
+    assert!(CpuFamily::Unknown.is_valid_register("anything"));
+}
+
+#[test]
+fn test_caller_address() {
+    let frame = Frame {
+        instruction_addr: Annotated::new(Addr(0x1003)),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        get_caller_address(&frame, CpuFamily::X86_64),
+        Some(Addr(0x1002))
+    );
+    assert_eq!(
+        get_caller_address(&frame, CpuFamily::Arm32),
+        Some(Addr(0x1000))
+    );
+    assert_eq!(
+        get_caller_address(&frame, CpuFamily::Arm64),
+        Some(Addr(0xffc))
+    );
+    assert_eq!(
+        get_caller_address(&Frame::default(), CpuFamily::Arm64),
+        None
+    );
+}
+
+#[test]
+fn test_normalize_registers() {
+    use crate::protocol::RegVal;
+    use crate::types::Object;
+
+    let mut registers = Object::new();
+    registers.insert("rip".to_string(), Annotated::new(RegVal(0x1234)));
+    registers.insert("rsp".to_string(), Annotated::new(RegVal(0x7fff_0000)));
+    registers.insert("x0".to_string(), Annotated::new(RegVal(0)));
+
+    let mut stacktrace = Stacktrace {
+        frames: Annotated::new(vec![
+            Annotated::new(Frame {
+                instruction_addr: Annotated::new(Addr(0x5678)),
+                ..Default::default()
+            }),
+            Annotated::new(Frame::default()),
+        ]),
+        registers: Annotated::new(registers),
+        ..Default::default()
+    };
+
+    normalize_registers(&mut stacktrace, "x86_64");
+
+    let registers = stacktrace.registers.value().unwrap();
+    assert!(registers["rip"].is_valid());
+    assert_eq_dbg!(
+        registers["x0"].meta().iter_errors().collect::<Vec<_>>(),
+        vec!["invalid register for architecture x86_64"]
+    );
+
+    let frames = stacktrace.frames.value().unwrap();
+    assert_eq_dbg!(
+        frames[0].value().unwrap().instruction_addr.value(),
+        Some(&Addr(0x5678))
+    );
+    assert_eq_dbg!(
+        frames[1].value().unwrap().instruction_addr.value(),
+        Some(&Addr(0x1234))
+    );
+}
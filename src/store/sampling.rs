@@ -0,0 +1,332 @@
+//! Rule-based sampling of events.
+//!
+//! Sampling rules are configured per project.  The first rule whose condition matches an event
+//! determines the sample rate, and the decision to keep or drop the event is derived from a hash
+//! of its event id.  Processing the same event again therefore always yields the same decision.
+//! Events must have an id to be sampled.
+use std::collections::BTreeMap;
+
+use failure::Fail;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::protocol::{Event, EventId, EventType, Level};
+use crate::store::filter::glob_match;
+
+/// Conditions that an event must fulfill to match a sampling rule.
+///
+/// Every condition that is not empty must match.  A condition with multiple values matches if
+/// any of the values match.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct RuleCondition {
+    /// Environment names.
+    pub environments: Vec<String>,
+    /// Glob patterns for the release.
+    pub releases: Vec<String>,
+    /// Event types, such as `error` or `csp`.
+    pub event_types: Vec<EventType>,
+    /// Levels, such as `warning` or `fatal`.
+    pub levels: Vec<Level>,
+    /// Tags with their accepted values.
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// Glob patterns for the transaction name.
+    pub transactions: Vec<String>,
+}
+
+fn matches_any<'a, F>(values: &[String], value: Option<&'a str>, matches: F) -> bool
+where
+    F: Fn(&str, &'a str) -> bool,
+{
+    if values.is_empty() {
+        return true;
+    }
+
+    match value {
+        Some(value) => values.iter().any(|candidate| matches(candidate, value)),
+        None => false,
+    }
+}
+
+fn contains_any<T: PartialEq>(values: &[T], value: Option<&T>) -> bool {
+    values.is_empty() || value.is_some_and(|value| values.contains(value))
+}
+
+fn get_tag<'a>(event: &'a Event, key: &str) -> Option<&'a str> {
+    event
+        .tags
+        .value()?
+        .iter()
+        .filter_map(|tag| tag.value())
+        .find(|(k, _)| k.value().map(String::as_str) == Some(key))?
+        .1
+        .value()
+        .map(String::as_str)
+}
+
+impl RuleCondition {
+    /// Returns whether the event fulfills all conditions.
+    pub fn matches(&self, event: &Event) -> bool {
+        matches_any(
+            &self.environments,
+            event.environment.value().map(String::as_str),
+            |a, b| a == b,
+        ) && matches_any(
+            &self.releases,
+            event.release.value().map(String::as_str),
+            |pattern, release| glob_match(pattern, release, false),
+        ) && contains_any(&self.event_types, event.ty.value())
+            && contains_any(&self.levels, event.level.value())
+            && matches_any(
+                &self.transactions,
+                event.transaction.value().map(String::as_str),
+                |pattern, transaction| glob_match(pattern, transaction, false),
+            )
+            && self
+                .tags
+                .iter()
+                .all(|(key, values)| matches_any(values, get_tag(event, key), |a, b| a == b))
+    }
+}
+
+/// A sampling rule with its condition and sample rate.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SamplingRule {
+    /// Identifier of the rule, reported for matching events.
+    pub id: u32,
+    /// The condition an event must fulfill.
+    #[serde(default)]
+    pub condition: RuleCondition,
+    /// The fraction of matching events to keep, between `0.0` and `1.0`.
+    pub sample_rate: f64,
+}
+
+/// The sampling rules of a project.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct SamplingConfig {
+    /// Rules in order of precedence.
+    pub rules: Vec<SamplingRule>,
+}
+
+/// An error returned when sampling an event without an id.
+#[derive(Debug, Fail)]
+#[fail(display = "event has no id")]
+pub struct MissingEventIdError;
+
+/// Whether an event is kept or dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingDecision {
+    /// The event is kept.
+    Keep,
+    /// The event is dropped.
+    Drop,
+}
+
+/// The outcome of sampling an event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplingResult {
+    /// Whether the event is kept or dropped.
+    pub decision: SamplingDecision,
+    /// The sample rate of the first matching rule, if any rule matched.
+    pub sample_rate: Option<f64>,
+    /// The identifiers of all rules whose condition matches, in order of precedence.
+    pub matched_rules: Vec<u32>,
+}
+
+/// Returns a number in `[0, 1)` that is uniformly distributed over event ids.
+pub fn get_event_id_fraction(event_id: EventId) -> f64 {
+    let digest = md5::compute(event_id.0.as_bytes());
+    let mut value = 0u64;
+    for &byte in &digest[..8] {
+        value = (value << 8) | u64::from(byte);
+    }
+
+    // Use the upper 53 bits, which are represented exactly in a double.
+    (value >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Decides whether to keep an event according to the sampling rules.
+///
+/// Events that match no rule are kept.  The decision is derived from the event id, so events
+/// without an id cannot be sampled and are rejected with an error.
+pub fn sample_event(
+    event: &Event,
+    config: &SamplingConfig,
+) -> Result<SamplingResult, MissingEventIdError> {
+    let event_id = *event.id.value().ok_or(MissingEventIdError)?;
+
+    let matched: Vec<&SamplingRule> = config
+        .rules
+        .iter()
+        .filter(|rule| rule.condition.matches(event))
+        .collect();
+
+    let sample_rate = matched.first().map(|rule| rule.sample_rate.clamp(0.0, 1.0));
+
+    let decision = match sample_rate {
+        Some(sample_rate) if get_event_id_fraction(event_id) >= sample_rate => {
+            SamplingDecision::Drop
+        }
+        _ => SamplingDecision::Keep,
+    };
+
+    Ok(SamplingResult {
+        decision,
+        sample_rate,
+        matched_rules: matched.iter().map(|rule| rule.id).collect(),
+    })
+}
+
+#[cfg(test)]
+fn make_event_id(seed: u32) -> EventId {
+    let mut bytes = [0u8; 16];
+    bytes[..4].copy_from_slice(&seed.to_be_bytes());
+    EventId(uuid::Uuid::from_bytes(bytes))
+}
+
+#[test]
+fn test_event_id_fraction() {
+    let event_id = "52df9022835246eeb317dbd739ccd059".parse().unwrap();
+    assert_eq!(
+        get_event_id_fraction(event_id),
+        get_event_id_fraction(event_id)
+    );
+
+    let kept = (0..10_000)
+        .filter(|&seed| get_event_id_fraction(make_event_id(seed)) < 0.25)
+        .count();
+    assert!(kept > 2_300 && kept < 2_700, "kept {} events", kept);
+}
+
+#[test]
+fn test_rule_condition() {
+    use crate::protocol::Tags;
+    use crate::types::Annotated;
+
+    let event = Event {
+        ty: Annotated::new(EventType::Error),
+        level: Annotated::new(Level::Warning),
+        environment: Annotated::new("production".to_string()),
+        release: Annotated::new("backend@1.2.3".to_string()),
+        transaction: Annotated::new("/api/users/{id}".to_string()),
+        tags: Annotated::new(Tags(vec![Annotated::new((
+            Annotated::new("region".to_string()),
+            Annotated::new("eu".to_string()),
+        ))])),
+        ..Default::default()
+    };
+
+    assert!(RuleCondition::default().matches(&event));
+
+    let mut tags = BTreeMap::new();
+    tags.insert(
+        "region".to_string(),
+        vec!["us".to_string(), "eu".to_string()],
+    );
+    let condition = RuleCondition {
+        environments: vec!["production".to_string()],
+        releases: vec!["backend@1.*".to_string()],
+        event_types: vec![EventType::Error],
+        levels: vec![Level::Warning, Level::Error],
+        tags,
+        transactions: vec!["/api/*".to_string()],
+    };
+    assert!(condition.matches(&event));
+
+    let condition = RuleCondition {
+        releases: vec!["frontend@*".to_string()],
+        ..Default::default()
+    };
+    assert!(!condition.matches(&event));
+
+    let mut tags = BTreeMap::new();
+    tags.insert("browser".to_string(), vec!["Chrome".to_string()]);
+    let condition = RuleCondition {
+        tags,
+        ..Default::default()
+    };
+    assert!(!condition.matches(&event));
+
+    let condition = RuleCondition {
+        levels: vec![Level::Fatal],
+        ..Default::default()
+    };
+    assert!(!condition.matches(&event));
+}
+
+#[test]
+fn test_deserialize_rule_condition() {
+    let condition: RuleCondition =
+        serde_json::from_str(r#"{"event_types": ["csp", "error"], "levels": ["log", "fatal"]}"#)
+            .unwrap();
+    assert_eq!(
+        condition.event_types,
+        vec![EventType::Csp, EventType::Error]
+    );
+    assert_eq!(condition.levels, vec![Level::Info, Level::Fatal]);
+
+    assert!(serde_json::from_str::<RuleCondition>(r#"{"levels": ["loud"]}"#).is_err());
+    assert!(serde_json::from_str::<RuleCondition>(r#"{"event_types": ["crash"]}"#).is_err());
+}
+
+#[test]
+fn test_sample_event() {
+    use crate::types::Annotated;
+
+    let config: SamplingConfig = serde_json::from_str(
+        r#"{
+            "rules": [
+                {"id": 1, "condition": {"levels": ["fatal"]}, "sample_rate": 1.0},
+                {"id": 2, "condition": {"environments": ["production"]}, "sample_rate": 0.0},
+                {"id": 3, "sample_rate": 0.5}
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let mut event = Event {
+        id: Annotated::new(make_event_id(1)),
+        environment: Annotated::new("production".to_string()),
+        ..Default::default()
+    };
+
+    assert_eq_dbg!(
+        sample_event(&event, &config).unwrap(),
+        SamplingResult {
+            decision: SamplingDecision::Drop,
+            sample_rate: Some(0.0),
+            matched_rules: vec![2, 3],
+        }
+    );
+
+    event.level = Annotated::new(Level::Fatal);
+    assert_eq_dbg!(
+        sample_event(&event, &config).unwrap(),
+        SamplingResult {
+            decision: SamplingDecision::Keep,
+            sample_rate: Some(1.0),
+            matched_rules: vec![1, 2, 3],
+        }
+    );
+
+    // The decision for the same event id never changes.
+    event.level = Annotated::empty();
+    event.environment = Annotated::new("staging".to_string());
+    let result = sample_event(&event, &config).unwrap();
+    assert_eq!(result.matched_rules, vec![3]);
+    for _ in 0..10 {
+        assert_eq!(sample_event(&event, &config).unwrap(), result);
+    }
+
+    let kept = (0..1_000)
+        .filter(|&seed| {
+            event.id = Annotated::new(make_event_id(seed));
+            sample_event(&event, &config).unwrap().decision == SamplingDecision::Keep
+        })
+        .count();
+    assert!(kept > 400 && kept < 600, "kept {} events", kept);
+
+    event.id = Annotated::empty();
+    event.environment = Annotated::new("production".to_string());
+    assert!(sample_event(&event, &config).is_err());
+}
@@ -0,0 +1,855 @@
+//! Resolution of minified JavaScript frames to their original sources.
+//!
+//! Source maps are located through the `//# sourceMappingURL` comment of the minified file and
+//! loaded from the artifacts of the event's release.  Only version 3 source maps with a flat list
+//! of mappings are supported.
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use failure::Fail;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_derive::Deserialize;
+use url::Url;
+
+use crate::processor::{ProcessValue, ProcessingState, Processor};
+use crate::protocol::{Event, Exception, Frame, Stacktrace, Thread};
+use crate::store::stacktrace::is_url;
+use crate::types::Annotated;
+
+lazy_static! {
+    static ref SOURCE_MAPPING_URL_RE: Regex =
+        Regex::new(r"^//[#@]\s*sourceMappingURL=(?P<url>\S+)\s*$").unwrap();
+}
+
+/// The number of source lines to add before and after the current line of a frame.
+const CONTEXT_LINES: usize = 5;
+
+/// The maximum number of mappings to search backwards for the original function name.
+const MAX_FUNCTION_NAME_SEARCH: usize = 1000;
+
+/// Provides access to the files uploaded for a release.
+pub trait ArtifactLookup {
+    /// Returns the contents of the artifact with the given URL, if it exists in the release.
+    fn get_artifact(&self, release: &str, url: &str) -> Option<Vec<u8>>;
+}
+
+/// Looks up release artifacts in a local directory.
+///
+/// Artifacts are stored in a sub directory per release, under the path component of their URL.
+/// The artifact `http://example.com/static/app.js` of release `1.0` is therefore loaded from
+/// `<root>/1.0/static/app.js`.
+#[derive(Debug, Clone)]
+pub struct DirectoryArtifactLookup {
+    root: PathBuf,
+}
+
+impl DirectoryArtifactLookup {
+    /// Creates a lookup for the given root directory.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirectoryArtifactLookup { root: root.into() }
+    }
+
+    fn get_path(&self, release: &str, url: &str) -> Option<PathBuf> {
+        if release.is_empty() || release.contains(['/', '\\']) || release == ".." {
+            return None;
+        }
+
+        let url = Url::parse(url).ok()?;
+        let mut path = self.root.join(release);
+        for segment in url.path_segments()? {
+            // Parsing the URL resolves `..`, so only backslashes could still escape the root.
+            if segment.is_empty() || segment.contains('\\') {
+                return None;
+            }
+            path.push(segment);
+        }
+
+        Some(path)
+    }
+}
+
+impl ArtifactLookup for DirectoryArtifactLookup {
+    fn get_artifact(&self, release: &str, url: &str) -> Option<Vec<u8>> {
+        fs::read(self.get_path(release, url)?).ok()
+    }
+}
+
+/// An error returned when parsing a source map fails.
+#[derive(Debug, Fail)]
+pub enum SourceMapError {
+    /// The source map is not valid JSON or lacks required fields.
+    #[fail(display = "invalid source map")]
+    InvalidJson,
+
+    /// The source map is not a version 3 source map.
+    #[fail(display = "unsupported source map version")]
+    UnsupportedVersion,
+
+    /// The mappings contain invalid VLQ values or point to unknown sources or names.
+    #[fail(display = "invalid source map mappings")]
+    InvalidMappings,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSourceMap {
+    version: u32,
+    #[serde(default)]
+    source_root: Option<String>,
+    sources: Vec<Option<String>>,
+    #[serde(default)]
+    sources_content: Vec<Option<String>>,
+    #[serde(default)]
+    names: Vec<String>,
+    mappings: String,
+}
+
+/// A mapping from a position in the minified file to a position in an original source.
+///
+/// All lines and columns are zero-based.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceMapToken {
+    /// Line in the minified file.
+    pub dst_line: u32,
+    /// Column in the minified file.
+    pub dst_col: u32,
+    /// Index of the original source and the position in it, if the code is mapped.
+    pub src: Option<(u32, u32, u32)>,
+    /// Index of the original name at this position.
+    pub name: Option<u32>,
+}
+
+/// A parsed source map.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    source_root: Option<String>,
+    sources: Vec<String>,
+    sources_content: Vec<Option<String>>,
+    names: Vec<String>,
+    tokens: Vec<SourceMapToken>,
+}
+
+impl SourceMap {
+    /// Parses a source map from its JSON representation.
+    pub fn from_slice(data: &[u8]) -> Result<Self, SourceMapError> {
+        let raw: RawSourceMap =
+            serde_json::from_slice(data).map_err(|_| SourceMapError::InvalidJson)?;
+
+        if raw.version != 3 {
+            return Err(SourceMapError::UnsupportedVersion);
+        }
+
+        let tokens = parse_mappings(&raw.mappings)?;
+        for token in &tokens {
+            let valid_source = token
+                .src
+                .is_none_or(|(id, _, _)| (id as usize) < raw.sources.len());
+            let valid_name = token.name.is_none_or(|id| (id as usize) < raw.names.len());
+            if !valid_source || !valid_name {
+                return Err(SourceMapError::InvalidMappings);
+            }
+        }
+
+        Ok(SourceMap {
+            source_root: raw.source_root.filter(|root| !root.is_empty()),
+            sources: raw
+                .sources
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect(),
+            sources_content: raw.sources_content,
+            names: raw.names,
+            tokens,
+        })
+    }
+
+    /// Returns the mapping at the given zero-based position in the minified file.
+    ///
+    /// This is the closest mapping at or before the position on the same line.
+    pub fn lookup_token(&self, line: u32, col: u32) -> Option<&SourceMapToken> {
+        self.lookup_index(line, col)
+            .map(|index| &self.tokens[index])
+    }
+
+    fn lookup_index(&self, line: u32, col: u32) -> Option<usize> {
+        let index = self
+            .tokens
+            .partition_point(|token| (token.dst_line, token.dst_col) <= (line, col))
+            .checked_sub(1)?;
+
+        if self.tokens[index].dst_line == line {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the path of a source as listed in the source map, prefixed with the source root.
+    pub fn get_source(&self, id: u32) -> Option<String> {
+        let source = self.sources.get(id as usize)?;
+        Some(match self.source_root {
+            Some(ref root) if !is_url(source) && !source.starts_with('/') => {
+                format!("{}/{}", root.trim_end_matches('/'), source)
+            }
+            _ => source.clone(),
+        })
+    }
+
+    /// Returns the embedded contents of a source.
+    pub fn get_source_content(&self, id: u32) -> Option<&str> {
+        self.sources_content.get(id as usize)?.as_deref()
+    }
+
+    /// Returns an original name.
+    pub fn get_name(&self, id: u32) -> Option<&str> {
+        self.names.get(id as usize).map(String::as_str)
+    }
+}
+
+fn base64_digit(byte: u8) -> Option<i64> {
+    Some(match byte {
+        b'A'..=b'Z' => byte - b'A',
+        b'a'..=b'z' => byte - b'a' + 26,
+        b'0'..=b'9' => byte - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return None,
+    } as i64)
+}
+
+/// Decodes a segment of base64 VLQ values.
+pub fn decode_vlq(segment: &str) -> Result<Vec<i64>, SourceMapError> {
+    let mut values = vec![];
+    let mut value = 0i64;
+    let mut shift = 0;
+
+    for byte in segment.bytes() {
+        let digit = base64_digit(byte).ok_or(SourceMapError::InvalidMappings)?;
+        value += (digit & 0x1f) << shift;
+
+        if digit & 0x20 != 0 {
+            shift += 5;
+            if shift > 55 {
+                return Err(SourceMapError::InvalidMappings);
+            }
+        } else {
+            let magnitude = value >> 1;
+            values.push(if value & 1 != 0 {
+                -magnitude
+            } else {
+                magnitude
+            });
+            value = 0;
+            shift = 0;
+        }
+    }
+
+    if shift != 0 {
+        return Err(SourceMapError::InvalidMappings);
+    }
+
+    Ok(values)
+}
+
+fn apply_offset(base: &mut i64, offset: i64) -> Result<u32, SourceMapError> {
+    *base += offset;
+    if *base < 0 || *base > i64::from(u32::MAX) {
+        return Err(SourceMapError::InvalidMappings);
+    }
+    Ok(*base as u32)
+}
+
+fn parse_mappings(mappings: &str) -> Result<Vec<SourceMapToken>, SourceMapError> {
+    let mut tokens = vec![];
+    let (mut src_id, mut src_line, mut src_col, mut name_id) = (0, 0, 0, 0);
+
+    for (dst_line, line) in mappings.split(';').enumerate() {
+        let dst_line = dst_line as u32;
+        let mut dst_col = 0;
+
+        for segment in line.split(',').filter(|segment| !segment.is_empty()) {
+            let values = decode_vlq(segment)?;
+            let mut token = SourceMapToken {
+                dst_line,
+                dst_col: apply_offset(&mut dst_col, values[0])?,
+                src: None,
+                name: None,
+            };
+
+            match values.len() {
+                1 => (),
+                4 | 5 => {
+                    token.src = Some((
+                        apply_offset(&mut src_id, values[1])?,
+                        apply_offset(&mut src_line, values[2])?,
+                        apply_offset(&mut src_col, values[3])?,
+                    ));
+
+                    if let Some(&offset) = values.get(4) {
+                        token.name = Some(apply_offset(&mut name_id, offset)?);
+                    }
+                }
+                _ => return Err(SourceMapError::InvalidMappings),
+            }
+
+            tokens.push(token);
+        }
+    }
+
+    // Mappings within a line should be ordered, but not all generators guarantee this.
+    tokens.sort_by_key(|token| (token.dst_line, token.dst_col));
+    Ok(tokens)
+}
+
+/// Returns the URL in the last `sourceMappingURL` comment of a minified file.
+pub fn find_source_mapping_url(source: &str) -> Option<&str> {
+    source
+        .lines()
+        .rev()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .take(5)
+        .filter_map(|line| SOURCE_MAPPING_URL_RE.captures(line))
+        .filter_map(|captures| captures.name("url"))
+        .map(|url| url.as_str())
+        .next()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn get_filename(abs_path: &str) -> String {
+    if let Ok(url) = Url::parse(abs_path) {
+        let path = url.path();
+        if !path.is_empty() && path != "/" {
+            return path.to_string();
+        }
+    }
+
+    abs_path.to_string()
+}
+
+/// Processor that resolves minified JavaScript frames through source maps.
+///
+/// Frames with a URL in `abs_path` are looked up in the artifacts of the event's release.  If the
+/// minified file references a source map, `abs_path`, `filename`, `line`, `column` and
+/// `function` are rewritten to the original source, and the source context is taken from the
+/// embedded source contents.  The minified stack traces of exceptions and threads are retained in
+/// `raw_stacktrace`.  The top-level stack trace of the event is rewritten in place.
+pub struct SourceMapProcessor<'a> {
+    lookup: &'a dyn ArtifactLookup,
+    release: String,
+    sources: HashMap<String, Option<Rc<String>>>,
+    sourcemaps: HashMap<String, Result<Rc<SourceMap>, String>>,
+    source_lines: HashMap<String, Option<Rc<Vec<String>>>>,
+}
+
+impl<'a> SourceMapProcessor<'a> {
+    /// Creates a processor that loads artifacts of the given release.
+    pub fn new(lookup: &'a dyn ArtifactLookup, release: &str) -> Self {
+        SourceMapProcessor {
+            lookup,
+            release: release.to_string(),
+            sources: HashMap::new(),
+            sourcemaps: HashMap::new(),
+            source_lines: HashMap::new(),
+        }
+    }
+
+    /// Creates a processor for a JavaScript or Node event, unless the event has no release.
+    pub fn from_event(lookup: &'a dyn ArtifactLookup, event: &Annotated<Event>) -> Option<Self> {
+        let event = event.value()?;
+        if !matches!(
+            event.platform.value().map(String::as_str),
+            Some("javascript") | Some("node")
+        ) {
+            return None;
+        }
+
+        let release = event.release.value()?;
+        Some(Self::new(lookup, release))
+    }
+
+    fn get_source(&mut self, url: &str) -> Option<Rc<String>> {
+        if let Some(source) = self.sources.get(url) {
+            return source.clone();
+        }
+
+        let source = self
+            .lookup
+            .get_artifact(&self.release, url)
+            .map(|data| Rc::new(String::from_utf8_lossy(&data).into_owned()));
+
+        self.sources.insert(url.to_string(), source.clone());
+        source
+    }
+
+    fn get_sourcemap(&mut self, url: &str) -> Option<Result<Rc<SourceMap>, String>> {
+        if let Some(sourcemap) = self.sourcemaps.get(url) {
+            return Some(sourcemap.clone());
+        }
+
+        let data = self.lookup.get_artifact(&self.release, url)?;
+        let sourcemap = SourceMap::from_slice(&data)
+            .map(Rc::new)
+            .map_err(|error| format!("{}: {}", error, url));
+
+        self.sourcemaps.insert(url.to_string(), sourcemap.clone());
+        Some(sourcemap)
+    }
+
+    /// Returns the lines of an original source, preferring the contents embedded in the source map.
+    ///
+    /// Lines are cached per source URL, since many frames usually point into the same source.
+    fn get_source_lines(
+        &mut self,
+        sourcemap: &SourceMap,
+        src_id: u32,
+        source_url: &str,
+    ) -> Option<Rc<Vec<String>>> {
+        if let Some(lines) = self.source_lines.get(source_url) {
+            return lines.clone();
+        }
+
+        let lines = match sourcemap.get_source_content(src_id) {
+            Some(content) => Some(content.lines().map(str::to_string).collect()),
+            None => self
+                .get_source(source_url)
+                .map(|content| content.lines().map(str::to_string).collect()),
+        }
+        .map(Rc::new);
+
+        self.source_lines
+            .insert(source_url.to_string(), lines.clone());
+        lines
+    }
+
+    /// Rewrites a single frame and returns whether it was changed.
+    fn process_js_frame(&mut self, frame: &mut Frame) -> bool {
+        let abs_path = match frame.abs_path.value().or_else(|| frame.filename.value()) {
+            Some(abs_path) if is_url(abs_path) => abs_path.clone(),
+            _ => return false,
+        };
+
+        let line = match frame.line.value() {
+            Some(&line) if line > 0 && line <= u64::from(u32::MAX) => line as u32 - 1,
+            _ => return false,
+        };
+
+        let col = frame.column.value().map_or(0, |&col| col.saturating_sub(1));
+        let col = col.min(u64::from(u32::MAX)) as u32;
+
+        let minified = match self.get_source(&abs_path) {
+            Some(minified) => minified,
+            None => return false,
+        };
+
+        let sourcemap_url = match find_source_mapping_url(&minified)
+            .and_then(|url| Url::parse(&abs_path).ok()?.join(url).ok())
+        {
+            Some(url) => url,
+            None => return false,
+        };
+
+        let sourcemap = match self.get_sourcemap(sourcemap_url.as_str()) {
+            Some(Ok(sourcemap)) => sourcemap,
+            Some(Err(error)) => {
+                frame.abs_path.meta_mut().add_error(error, None);
+                return false;
+            }
+            None => return false,
+        };
+
+        let index = match sourcemap.lookup_index(line, col) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let (src_id, src_line, src_col) = match sourcemap.tokens[index].src {
+            Some(src) => src,
+            None => return false,
+        };
+
+        let source = sourcemap.get_source(src_id).unwrap_or_default();
+        let source_url = match sourcemap_url.join(&source) {
+            Ok(url) => url.into_string(),
+            Err(_) => source,
+        };
+
+        if let Some(function) = frame.function.value() {
+            if let Some(name) = get_original_function_name(&sourcemap, &minified, index, function) {
+                frame.function = Annotated::new(name.to_string());
+            }
+        }
+
+        frame.filename = Annotated::new(get_filename(&source_url));
+        frame.line = Annotated::new(u64::from(src_line) + 1);
+        frame.column = Annotated::new(u64::from(src_col) + 1);
+
+        frame.pre_lines = Annotated::empty();
+        frame.current_line = Annotated::empty();
+        frame.post_lines = Annotated::empty();
+
+        if let Some(lines) = self.get_source_lines(&sourcemap, src_id, &source_url) {
+            let line = src_line as usize;
+
+            if let Some(current_line) = lines.get(line) {
+                let pre_start = line.saturating_sub(CONTEXT_LINES);
+                let post_end = (line + 1 + CONTEXT_LINES).min(lines.len());
+                let to_array = |lines: &[String]| {
+                    lines
+                        .iter()
+                        .map(|line| Annotated::new(line.clone()))
+                        .collect()
+                };
+
+                frame.pre_lines = Annotated::new(to_array(&lines[pre_start..line]));
+                frame.current_line = Annotated::new(current_line.clone());
+                frame.post_lines = Annotated::new(to_array(&lines[line + 1..post_end]));
+            }
+        }
+
+        frame.abs_path = Annotated::new(source_url);
+        true
+    }
+
+    /// Rewrites all frames of a stack trace and returns whether any frame was changed.
+    fn process_js_stacktrace(&mut self, stacktrace: &mut Annotated<Stacktrace>) -> bool {
+        let frames = stacktrace
+            .value_mut()
+            .as_mut()
+            .and_then(|stacktrace| stacktrace.frames.value_mut().as_mut());
+
+        let mut changed = false;
+        if let Some(frames) = frames {
+            for frame in frames.iter_mut() {
+                if let Some(frame) = frame.value_mut() {
+                    changed |= self.process_js_frame(frame);
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Rewrites a stack trace and retains the minified stack trace if it was changed.
+    fn process_with_raw(
+        &mut self,
+        stacktrace: &mut Annotated<Stacktrace>,
+        raw_stacktrace: &mut Annotated<Stacktrace>,
+    ) {
+        let original = stacktrace.clone();
+        if self.process_js_stacktrace(stacktrace) && raw_stacktrace.value().is_none() {
+            *raw_stacktrace = original;
+        }
+    }
+}
+
+/// Resolves the original name of the function containing a mapping.
+///
+/// Positions in stack traces point to the current statement rather than the function
+/// declaration.  The name is found by searching backwards for a mapping whose minified text is
+/// the minified function name.
+fn get_original_function_name<'s>(
+    sourcemap: &'s SourceMap,
+    minified: &str,
+    index: usize,
+    function: &str,
+) -> Option<&'s str> {
+    let function = function.rsplit('.').next()?;
+    if function.is_empty() || !function.chars().all(is_identifier_char) {
+        return None;
+    }
+
+    let lines: Vec<_> = minified.split('\n').collect();
+    let start = index.saturating_sub(MAX_FUNCTION_NAME_SEARCH);
+
+    sourcemap.tokens[start..=index]
+        .iter()
+        .rev()
+        .filter_map(|token| Some((token, token.name?)))
+        .find(|(token, _)| {
+            lines
+                .get(token.dst_line as usize)
+                .and_then(|line| line.get(token.dst_col as usize..))
+                .and_then(|text| text.strip_prefix(function))
+                .is_some_and(|rest| !rest.starts_with(is_identifier_char))
+        })
+        .and_then(|(_, name)| sourcemap.get_name(name))
+}
+
+impl<'a> Processor for SourceMapProcessor<'a> {
+    fn process_event(
+        &mut self,
+        event: Annotated<Event>,
+        state: ProcessingState,
+    ) -> Annotated<Event> {
+        let mut event = ProcessValue::process_child_values(event, self, state);
+
+        // The top-level stack trace has no raw counterpart to retain the minified frames.
+        if let Some(ref mut event) = event.0 {
+            self.process_js_stacktrace(&mut event.stacktrace);
+        }
+
+        event
+    }
+
+    fn process_exception(
+        &mut self,
+        mut exception: Annotated<Exception>,
+        _state: ProcessingState,
+    ) -> Annotated<Exception> {
+        if let Some(ref mut exception) = exception.0 {
+            self.process_with_raw(&mut exception.stacktrace, &mut exception.raw_stacktrace);
+        }
+
+        exception
+    }
+
+    fn process_thread(
+        &mut self,
+        mut thread: Annotated<Thread>,
+        _state: ProcessingState,
+    ) -> Annotated<Thread> {
+        if let Some(ref mut thread) = thread.0 {
+            self.process_with_raw(&mut thread.stacktrace, &mut thread.raw_stacktrace);
+        }
+
+        thread
+    }
+}
+
+#[cfg(test)]
+fn make_artifact_dir(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("sourcemaps-{}-{}", name, std::process::id()));
+    let dir = root.join("1.0").join("static");
+    fs::create_dir_all(&dir).unwrap();
+
+    fs::write(
+        dir.join("app.min.js"),
+        "function a(n,r){return n+r}function b(){throw new Error(\"oops\")}\n\
+         //# sourceMappingURL=app.min.js.map\n",
+    )
+    .unwrap();
+
+    fs::write(
+        dir.join("app.min.js.map"),
+        r#"{
+            "version": 3,
+            "file": "app.min.js",
+            "sources": ["../src/math.js"],
+            "sourcesContent": ["function add(a, b) {\n    return a + b;\n}\n\nfunction fail() {\n    throw new Error(\"oops\");\n}\n"],
+            "names": ["add", "fail"],
+            "mappings": "AAAA,SAASA,kBAIT,SAASC,IACL"
+        }"#,
+    )
+    .unwrap();
+
+    root
+}
+
+#[test]
+fn test_decode_vlq() {
+    assert_eq!(decode_vlq("AAAA").unwrap(), vec![0, 0, 0, 0]);
+    assert_eq!(decode_vlq("SAASC").unwrap(), vec![9, 0, 0, 9, 1]);
+    assert_eq!(decode_vlq("oBAIT").unwrap(), vec![20, 0, 4, -9]);
+    assert_eq!(decode_vlq("2HwB").unwrap(), vec![123, 24]);
+    assert!(decode_vlq("g").is_err());
+    assert!(decode_vlq("A!").is_err());
+}
+
+#[test]
+fn test_find_source_mapping_url() {
+    assert_eq!(
+        find_source_mapping_url("foo();\n//# sourceMappingURL=foo.js.map\n"),
+        Some("foo.js.map")
+    );
+    assert_eq!(
+        find_source_mapping_url("foo();\n//@ sourceMappingURL=/maps/foo.map"),
+        Some("/maps/foo.map")
+    );
+    assert_eq!(find_source_mapping_url("foo();\n// sourceMappingURL"), None);
+}
+
+#[test]
+fn test_sourcemap_lookup() {
+    let root = make_artifact_dir("lookup");
+    let lookup = DirectoryArtifactLookup::new(&root);
+
+    assert!(lookup
+        .get_artifact("1.0", "http://example.com/static/app.min.js")
+        .is_some());
+    assert!(lookup
+        .get_artifact("2.0", "http://example.com/static/app.min.js")
+        .is_none());
+    assert!(lookup
+        .get_artifact("..", "http://example.com/1.0/static/app.min.js")
+        .is_none());
+
+    let data = lookup
+        .get_artifact("1.0", "http://example.com/static/app.min.js.map")
+        .unwrap();
+    let sourcemap = SourceMap::from_slice(&data).unwrap();
+    assert_eq_dbg!(
+        sourcemap.lookup_token(0, 50),
+        Some(&SourceMapToken {
+            dst_line: 0,
+            dst_col: 40,
+            src: Some((0, 5, 4)),
+            name: None,
+        })
+    );
+    assert_eq_dbg!(sourcemap.lookup_token(1, 0), None);
+    assert_eq_str!(sourcemap.get_source(0).unwrap(), "../src/math.js");
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_invalid_sourcemap() {
+    assert!(SourceMap::from_slice(b"{").is_err());
+    assert!(SourceMap::from_slice(br#"{"version":2,"sources":[],"mappings":""}"#).is_err());
+    assert!(SourceMap::from_slice(br#"{"version":3,"sources":[],"mappings":"AAAA"}"#).is_err());
+}
+
+#[test]
+fn test_process_exception() {
+    let root = make_artifact_dir("process");
+    let lookup = DirectoryArtifactLookup::new(&root);
+
+    let minified_frame = Frame {
+        function: Annotated::new("b".to_string()),
+        abs_path: Annotated::new("http://example.com/static/app.min.js".to_string()),
+        filename: Annotated::new("/static/app.min.js".to_string()),
+        line: Annotated::new(1),
+        column: Annotated::new(41),
+        ..Default::default()
+    };
+
+    let missing_frame = Frame {
+        abs_path: Annotated::new("http://example.com/static/vendor.js".to_string()),
+        line: Annotated::new(1),
+        column: Annotated::new(1),
+        ..Default::default()
+    };
+
+    let stacktrace = Annotated::new(Stacktrace {
+        frames: Annotated::new(vec![
+            Annotated::new(missing_frame.clone()),
+            Annotated::new(minified_frame),
+        ]),
+        ..Default::default()
+    });
+
+    let event = Annotated::new(Event {
+        platform: Annotated::new("javascript".to_string()),
+        release: Annotated::new("1.0".to_string()),
+        exceptions: Annotated::new(crate::protocol::Values::new(vec![Annotated::new(
+            Exception {
+                ty: Annotated::new("Error".to_string()),
+                stacktrace: stacktrace.clone(),
+                ..Default::default()
+            },
+        )])),
+        ..Default::default()
+    });
+
+    let mut processor = SourceMapProcessor::from_event(&lookup, &event).unwrap();
+    let event = processor.process_event(event, ProcessingState::root());
+    fs::remove_dir_all(root).unwrap();
+
+    let exception = event
+        .value()
+        .and_then(|event| event.exceptions.value())
+        .and_then(|exceptions| exceptions.values.value())
+        .and_then(|values| values[0].value())
+        .unwrap();
+
+    assert_eq_dbg!(exception.raw_stacktrace, stacktrace);
+
+    let frames = exception
+        .stacktrace
+        .value()
+        .and_then(|stacktrace| stacktrace.frames.value())
+        .unwrap();
+
+    assert_eq_dbg!(frames[0].value(), Some(&missing_frame));
+
+    let lines = |lines: &[&str]| {
+        Annotated::new(
+            lines
+                .iter()
+                .map(|line| Annotated::new(line.to_string()))
+                .collect(),
+        )
+    };
+
+    assert_eq_dbg!(
+        frames[1].value(),
+        Some(&Frame {
+            function: Annotated::new("fail".to_string()),
+            abs_path: Annotated::new("http://example.com/src/math.js".to_string()),
+            filename: Annotated::new("/src/math.js".to_string()),
+            line: Annotated::new(6),
+            column: Annotated::new(5),
+            pre_lines: lines(&[
+                "function add(a, b) {",
+                "    return a + b;",
+                "}",
+                "",
+                "function fail() {",
+            ]),
+            current_line: Annotated::new("    throw new Error(\"oops\");".to_string()),
+            post_lines: lines(&["}"]),
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn test_process_event_stacktrace() {
+    let root = make_artifact_dir("event");
+    let lookup = DirectoryArtifactLookup::new(&root);
+
+    let frame = Frame {
+        function: Annotated::new("a".to_string()),
+        abs_path: Annotated::new("http://example.com/static/app.min.js".to_string()),
+        line: Annotated::new(1),
+        column: Annotated::new(18),
+        ..Default::default()
+    };
+
+    let event = Annotated::new(Event {
+        platform: Annotated::new("node".to_string()),
+        release: Annotated::new("1.0".to_string()),
+        stacktrace: Annotated::new(Stacktrace {
+            frames: Annotated::new(vec![Annotated::new(frame.clone()), Annotated::new(frame)]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    let mut processor = SourceMapProcessor::from_event(&lookup, &event).unwrap();
+    let event = processor.process_event(event, ProcessingState::root());
+    fs::remove_dir_all(root).unwrap();
+
+    assert_eq!(processor.source_lines.len(), 1);
+
+    let frames = event
+        .value()
+        .and_then(|event| event.stacktrace.value())
+        .and_then(|stacktrace| stacktrace.frames.value())
+        .unwrap();
+
+    for frame in frames {
+        let frame = frame.value().unwrap();
+        assert_eq_str!(frame.function.value().unwrap(), "add");
+        assert_eq_str!(frame.filename.value().unwrap(), "/src/math.js");
+        assert_eq_dbg!(frame.line.value(), Some(&1));
+        assert_eq_str!(frame.current_line.value().unwrap(), "function add(a, b) {");
+    }
+}
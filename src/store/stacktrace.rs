@@ -5,7 +5,7 @@ use url::Url;
 use crate::protocol::{Frame, Stacktrace};
 use crate::types::Annotated;
 
-fn is_url(filename: &str) -> bool {
+pub fn is_url(filename: &str) -> bool {
     filename.starts_with("file:")
         || filename.starts_with("http:")
         || filename.starts_with("https:")
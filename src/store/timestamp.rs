@@ -0,0 +1,244 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::processor::ToValue;
+use crate::protocol::{Event, EventProcessingError};
+use crate::types::Annotated;
+
+/// The minimum difference between the SDK clock and the server clock that is corrected.
+///
+/// Smaller differences are caused by network latency rather than a wrong device clock.
+const CLOCK_DRIFT_THRESHOLD_SECS: i64 = 60;
+
+fn push_error(event: &mut Event, ty: &str, name: &str, value: DateTime<Utc>) {
+    let error = EventProcessingError {
+        ty: Annotated::new(ty.to_string()),
+        name: Annotated::new(name.to_string()),
+        value: ToValue::to_value(Annotated::new(value)),
+    };
+
+    event
+        .errors
+        .value_mut()
+        .get_or_insert_with(Vec::new)
+        .push(Annotated::new(error));
+}
+
+/// Moves the timestamp by `drift`.
+///
+/// Timestamps that would leave the supported date range are kept as they are and marked with an
+/// error instead.
+fn shift(timestamp: &mut Annotated<DateTime<Utc>>, drift: Duration) {
+    let shifted = match timestamp.value() {
+        Some(value) => value.checked_add_signed(drift),
+        None => return,
+    };
+
+    match shifted {
+        Some(shifted) => timestamp.0 = Some(shifted),
+        None => timestamp
+            .meta_mut()
+            .add_error("timestamp out of range after clock drift correction", None),
+    }
+}
+
+/// Shifts all timestamps by the difference between the server clock and the SDK clock.
+///
+/// `sent_at` is the time at which the SDK sent the event according to its own clock.  If it
+/// differs from `received` by more than a minute, the timestamps of the event and its
+/// breadcrumbs are moved by the difference and a `clock_drift` error is recorded.
+pub fn correct_clock_drift(event: &mut Event, sent_at: DateTime<Utc>, received: DateTime<Utc>) {
+    let drift = received.signed_duration_since(sent_at);
+    if drift.num_seconds().abs() < CLOCK_DRIFT_THRESHOLD_SECS {
+        return;
+    }
+
+    shift(&mut event.timestamp, drift);
+
+    if let Some(values) = event.breadcrumbs.value_mut() {
+        if let Some(breadcrumbs) = values.values.value_mut() {
+            for breadcrumb in breadcrumbs.iter_mut() {
+                if let Some(breadcrumb) = breadcrumb.value_mut() {
+                    shift(&mut breadcrumb.timestamp, drift);
+                }
+            }
+        }
+    }
+
+    push_error(event, "clock_drift", "sent_at", sent_at);
+}
+
+/// Clamps the event timestamp to the time it was received if it lies too far in the past or in
+/// the future.
+///
+/// The original timestamp is recorded in a `past_timestamp` or `future_timestamp` error.
+pub fn validate_timestamp(
+    event: &mut Event,
+    received: DateTime<Utc>,
+    max_secs_in_past: Option<i64>,
+    max_secs_in_future: Option<i64>,
+) {
+    let timestamp = match event.timestamp.value() {
+        Some(&timestamp) => timestamp,
+        None => return,
+    };
+
+    let max_timestamp = max_secs_in_future
+        .and_then(|secs| received.checked_add_signed(Duration::try_seconds(secs)?));
+    let min_timestamp =
+        max_secs_in_past.and_then(|secs| received.checked_sub_signed(Duration::try_seconds(secs)?));
+
+    let error = if max_timestamp.is_some_and(|max| timestamp > max) {
+        "future_timestamp"
+    } else if min_timestamp.is_some_and(|min| timestamp < min) {
+        "past_timestamp"
+    } else {
+        return;
+    };
+
+    event.timestamp = Annotated::new(received);
+    push_error(event, error, "timestamp", timestamp);
+}
+
+#[test]
+fn test_validate_timestamp() {
+    use chrono::TimeZone;
+
+    let received = Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap();
+    let mut event = Event {
+        timestamp: Annotated::new(Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 30).unwrap()),
+        ..Default::default()
+    };
+
+    validate_timestamp(&mut event, received, Some(3600), Some(60));
+    assert_eq_dbg!(
+        event.timestamp.value(),
+        Some(&Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 30).unwrap())
+    );
+    assert_eq_dbg!(event.errors.value(), None);
+
+    event.timestamp = Annotated::new(Utc.with_ymd_and_hms(2000, 1, 1, 12, 5, 0).unwrap());
+    validate_timestamp(&mut event, received, Some(3600), Some(60));
+    assert_eq_dbg!(event.timestamp.value(), Some(&received));
+
+    event.timestamp = Annotated::new(Utc.with_ymd_and_hms(1999, 12, 31, 12, 0, 0).unwrap());
+    validate_timestamp(&mut event, received, Some(3600), Some(60));
+    assert_eq_dbg!(event.timestamp.value(), Some(&received));
+
+    event.timestamp = Annotated::new(Utc.with_ymd_and_hms(1999, 12, 31, 12, 0, 0).unwrap());
+    validate_timestamp(&mut event, received, None, None);
+    assert_eq_dbg!(
+        event.timestamp.value(),
+        Some(&Utc.with_ymd_and_hms(1999, 12, 31, 12, 0, 0).unwrap())
+    );
+
+    let errors: Vec<_> = event
+        .errors
+        .value()
+        .unwrap()
+        .iter()
+        .filter_map(|error| error.value())
+        .map(|error| {
+            (
+                error.ty.value().unwrap().as_str(),
+                error.value.value().cloned(),
+            )
+        })
+        .collect();
+
+    assert_eq_dbg!(
+        errors,
+        vec![
+            (
+                "future_timestamp",
+                Some(crate::types::Value::F64(946_728_300.0))
+            ),
+            (
+                "past_timestamp",
+                Some(crate::types::Value::F64(946_641_600.0))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_correct_clock_drift() {
+    use chrono::TimeZone;
+
+    use crate::protocol::{Breadcrumb, Values};
+
+    let received = Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap();
+    let sent_at = Utc.with_ymd_and_hms(2000, 1, 1, 10, 0, 0).unwrap();
+
+    let mut event = Event {
+        timestamp: Annotated::new(Utc.with_ymd_and_hms(2000, 1, 1, 9, 59, 0).unwrap()),
+        breadcrumbs: Annotated::new(Values::new(vec![Annotated::new(Breadcrumb {
+            timestamp: Annotated::new(Utc.with_ymd_and_hms(2000, 1, 1, 9, 58, 0).unwrap()),
+            ..Default::default()
+        })])),
+        ..Default::default()
+    };
+
+    correct_clock_drift(
+        &mut event,
+        received,
+        received + Duration::try_seconds(2).unwrap(),
+    );
+    assert_eq_dbg!(
+        event.timestamp.value(),
+        Some(&Utc.with_ymd_and_hms(2000, 1, 1, 9, 59, 0).unwrap())
+    );
+
+    correct_clock_drift(&mut event, sent_at, received);
+    assert_eq_dbg!(
+        event.timestamp.value(),
+        Some(&Utc.with_ymd_and_hms(2000, 1, 1, 11, 59, 0).unwrap())
+    );
+
+    let breadcrumb = event
+        .breadcrumbs
+        .value()
+        .and_then(|breadcrumbs| breadcrumbs.values.value())
+        .and_then(|values| values[0].value())
+        .unwrap();
+    assert_eq_dbg!(
+        breadcrumb.timestamp.value(),
+        Some(&Utc.with_ymd_and_hms(2000, 1, 1, 11, 58, 0).unwrap())
+    );
+
+    let error = event.errors.value().unwrap()[0].value().unwrap();
+    assert_eq_str!(error.ty.value().unwrap(), "clock_drift");
+}
+
+#[test]
+fn test_correct_clock_drift_out_of_range() {
+    use chrono::TimeZone;
+
+    use crate::protocol::{Breadcrumb, Values};
+
+    let timestamp = "+262142-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+    let received = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+    let sent_at = Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap();
+
+    let mut event = Event {
+        timestamp: Annotated::new(timestamp),
+        breadcrumbs: Annotated::new(Values::new(vec![Annotated::new(Breadcrumb {
+            timestamp: Annotated::new(timestamp),
+            ..Default::default()
+        })])),
+        ..Default::default()
+    };
+
+    correct_clock_drift(&mut event, sent_at, received);
+    assert_eq_dbg!(event.timestamp.value(), Some(&timestamp));
+    assert!(event.timestamp.meta().has_errors());
+
+    let breadcrumb = event
+        .breadcrumbs
+        .value()
+        .and_then(|breadcrumbs| breadcrumbs.values.value())
+        .map(|values| &values[0])
+        .unwrap();
+    let breadcrumb_timestamp = &breadcrumb.value().unwrap().timestamp;
+    assert_eq_dbg!(breadcrumb_timestamp.value(), Some(&timestamp));
+    assert!(breadcrumb_timestamp.meta().has_errors());
+}